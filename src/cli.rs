@@ -1,4 +1,12 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser, Subcommand};
+
+use config_picker::{
+    config_type_descriptor::LoadMode, file_attributes::parse_mode, label_archive::ArchiveFormat,
+};
+
+use crate::output::OutputFormat;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -8,6 +16,10 @@ pub struct StoreParams {
 
     #[arg(index = 2)]
     pub(crate) label: String,
+
+    /// Describes the new revision of the label
+    #[arg(long, short)]
+    pub(crate) message: Option<String>,
 }
 
 #[derive(Parser)]
@@ -16,8 +28,34 @@ pub struct LoadParams {
     #[arg(index = 1)]
     pub(crate) config_type_name: String,
 
+    /// Label, `<label>@<revision>` loads an earlier revision of it, unless a label has that name
+    #[arg(index = 2)]
+    pub(crate) label: String,
+}
+
+/// Lists the revisions of a label
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct HistoryParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+}
+
+/// Makes an earlier revision the current version of a label, as a new revision
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct RevertParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
     #[arg(index = 2)]
     pub(crate) label: String,
+
+    #[arg(index = 3)]
+    pub(crate) revision: u32,
 }
 
 #[derive(Parser)]
@@ -25,6 +63,10 @@ pub struct LoadParams {
 pub struct ListParams {
     #[arg(index = 1)]
     pub(crate) config_type_name: Option<String>,
+
+    /// Only lists the labels with this tag, of every config type if none is given
+    #[arg(long)]
+    pub(crate) tag: Option<String>,
 }
 
 #[derive(Parser)]
@@ -34,33 +76,390 @@ pub struct CreateTypeParams {
     pub(crate) config_type_name: String,
 }
 
-// #[derive(Parser)]
-// #[command(version, about, long_about = None)]
-// pub struct CopyLabelParams {
-//     #[arg(index = 1)]
-//     pub(crate) config_type_name: String,
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct CopyLabelParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    #[arg(index = 3)]
+    pub(crate) new_label: String,
+
+    /// Overwrite the new label if it already exists, the default can be set in the settings
+    #[arg(long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct RenameLabelParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    #[arg(index = 3)]
+    pub(crate) new_label: String,
+
+    /// Overwrite the new label if it already exists, the default can be set in the settings
+    #[arg(long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct WhichParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+}
+
+/// Compares the live files with a label, the last loaded label by default
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct StatusParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: Option<String>,
+}
+
+/// Shows the differences between two labels, or between a label and the live files
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct DiffParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    #[arg(index = 3)]
+    pub(crate) other_label: Option<String>,
+}
+
+/// Chooses a label from a searchable list, shows how it differs from the live files and loads it
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct PickParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: Option<String>,
+}
+
+/// Sets the description of a label, removes it if no description is given
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct DescribeParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    #[arg(index = 3)]
+    pub(crate) description: Option<String>,
+}
+
+/// Adds tags to a label
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct TagParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    #[arg(index = 3, required = true)]
+    pub(crate) tags: Vec<String>,
+
+    /// Removes the tags instead
+    #[arg(long)]
+    pub(crate) remove: bool,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct RemoveLabelParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) label: String,
+
+    /// Remove the label even if it is currently loaded
+    #[arg(long)]
+    pub(crate) force: bool,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct RemoveTypeParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    /// Remove the config type even if one of its labels is loaded
+    #[arg(long)]
+    pub(crate) force: bool,
+}
+
+/// Restores a removed config type or label, lists the trash if no config type is given
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct UndeleteParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: Option<String>,
+
+    #[arg(index = 2)]
+    pub(crate) label: Option<String>,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct AddPathParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    #[arg(index = 2)]
+    pub(crate) path: PathBuf,
+
+    /// How a stored directory is applied to the live directory on load
+    #[arg(long, value_enum, default_value_t = LoadMode::Overlay)]
+    pub(crate) load_mode: LoadMode,
+
+    /// Replace machine specific values (e.g. the home directory) in text files with variables
+    #[arg(long)]
+    pub(crate) template: bool,
+
+    /// Permission bits in octal (e.g. 600) the files are always loaded with, instead of the
+    /// stored ones
+    #[arg(long, value_parser = parse_mode_arg)]
+    pub(crate) mode: Option<u32>,
+}
+
+fn parse_mode_arg(value: &str) -> Result<u32, String> {
+    parse_mode(value).ok_or_else(|| "expected permission bits in octal, e.g. 600".into())
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct RemovePathParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    /// Path as written in the descriptor or as a live file path
+    #[arg(index = 2)]
+    pub(crate) path: String,
+}
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ShowTypeParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+}
+
+/// Restores the live files from the backup made by the most recent load
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct UndoParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+}
+
+/// Removes the stored file contents no label refers to anymore
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct GcParams {}
+
+/// Writes a config type and its labels to a single archive, to be imported on another machine
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ExportParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    /// Labels to export, every label if none is given
+    #[arg(index = 2)]
+    pub(crate) labels: Vec<String>,
+
+    /// Archive to write, defaults to `<config type>.tar.gz` (or `.zip`) in the current directory
+    #[arg(long)]
+    pub(crate) file: Option<PathBuf>,
+
+    /// Archive format, defaults to zip for `.zip` files and to tar.gz otherwise
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<ArchiveFormat>,
+}
+
+/// Adds the config type and labels of an exported archive
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ImportParams {
+    #[arg(index = 1)]
+    pub(crate) archive: PathBuf,
+
+    /// Stores a label of the archive under another name, as `<label>=<new label>`
+    #[arg(long)]
+    pub(crate) rename: Vec<String>,
+
+    /// Leave labels which already exist as they are, instead of importing nothing
+    #[arg(long)]
+    pub(crate) skip_existing: bool,
+}
+
+/// Encrypts the labels of a config type (with all their revisions) with a new passphrase or key
+/// file, or decrypts them
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+#[command(group(ArgGroup::new("encryption").required(true)))]
+pub struct RekeyParams {
+    #[arg(index = 1)]
+    pub(crate) config_type_name: String,
+
+    /// Encrypt with a new passphrase, read from CONFIG_PICKER_NEW_PASSPHRASE or asked for
+    #[arg(long, group = "encryption")]
+    pub(crate) passphrase: bool,
+
+    /// Encrypt for the age identity in the file, a new identity is written if it does not exist
+    #[arg(long, group = "encryption")]
+    pub(crate) key_file: Option<PathBuf>,
+
+    /// Decrypt the labels and store them unencrypted
+    #[arg(long, group = "encryption")]
+    pub(crate) no_encryption: bool,
+
+    /// Accept label files which are not encrypted yet (e.g. stored by an older version), they
+    /// are encrypted with the new key like the others
+    #[arg(long)]
+    pub(crate) allow_plaintext: bool,
+}
+
+/// Makes the storage root a git repository, every change of the stored config types is committed
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct InitGitParams {
+    /// URL of the repository to push to and pull from, added as `origin`
+    #[arg(long)]
+    pub(crate) remote: Option<String>,
+}
+
+/// Pushes the commits of the storage root to a remote
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct PushParams {
+    #[arg(index = 1, default_value = "origin")]
+    pub(crate) remote: String,
+}
+
+/// Pulls the commits of a remote into the storage root
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct PullParams {
+    #[arg(index = 1, default_value = "origin")]
+    pub(crate) remote: String,
+}
+
+/// Creates a profile, members are given as `<config type>` or `<config type>=<label>`, the label
+/// defaults to the profile name
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ProfileCreateParams {
+    #[arg(index = 1)]
+    pub(crate) profile_name: String,
+
+    #[arg(index = 2, required = true)]
+    pub(crate) members: Vec<String>,
+}
+
+/// Stores the live files of every config type of the profile
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ProfileStoreParams {
+    #[arg(index = 1)]
+    pub(crate) profile_name: String,
+
+    /// Stores every config type under this label and makes the profile use it
+    #[arg(index = 2)]
+    pub(crate) label: Option<String>,
+}
+
+/// Loads the label of every config type of the profile
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ProfileLoadParams {
+    #[arg(index = 1)]
+    pub(crate) profile_name: String,
+}
 
-//     #[arg(index = 2)]
-//     pub(crate) label: String,
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ProfileListParams {}
 
-//     #[arg(index = 3)]
-//     pub(crate) new_label: String,
-// }
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    Create(ProfileCreateParams),
+    Store(ProfileStoreParams),
+    Load(ProfileLoadParams),
+    List(ProfileListParams),
+}
 
-// #[derive(Parser)]
-// #[command(version, about, long_about = None)]
-// pub struct WhichParams {
-//     #[arg(index = 1)]
-//     pub(crate) config_type_name: String,
-// }
+/// Switches several config types together
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct ProfileParams {
+    #[command(subcommand)]
+    pub(crate) command: ProfileCommand,
+}
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
-pub enum Cli {
+pub struct Cli {
+    /// Storage root directory, defaults to ~/.config-picker
+    #[arg(long, global = true, env = "CONFIG_PICKER_HOME")]
+    pub(crate) root: Option<PathBuf>,
+
+    /// Output format, `json` prints a single JSON document, also for errors
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub(crate) output: OutputFormat,
+
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
     Store(StoreParams),
     Load(LoadParams),
+    History(HistoryParams),
+    Revert(RevertParams),
     List(ListParams),
     CreateType(CreateTypeParams),
-    // CopyLabel(CopyLabelParams),
-    // Which(WhichParams),
+    CopyLabel(CopyLabelParams),
+    RenameLabel(RenameLabelParams),
+    Which(WhichParams),
+    Status(StatusParams),
+    Diff(DiffParams),
+    Pick(PickParams),
+    Describe(DescribeParams),
+    Tag(TagParams),
+    RemoveLabel(RemoveLabelParams),
+    RemoveType(RemoveTypeParams),
+    Undelete(UndeleteParams),
+    AddPath(AddPathParams),
+    RemovePath(RemovePathParams),
+    ShowType(ShowTypeParams),
+    Undo(UndoParams),
+    Profile(ProfileParams),
+    Gc(GcParams),
+    Rekey(RekeyParams),
+    Export(ExportParams),
+    Import(ImportParams),
+    InitGit(InitGitParams),
+    Push(PushParams),
+    Pull(PullParams),
 }
@@ -1,87 +1,162 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fs::{copy, remove_dir_all, rename},
-    path::PathBuf,
-    rc::Rc,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
 };
 
+use chrono::Utc;
+use zeroize::Zeroizing;
+
 use crate::{
-    config_type_descriptor::ConfigTypeDescriptor,
-    directories::Directories,
+    config_type_descriptor::{ConfigTypeDescriptor, LoadMode, PathEntry},
+    directories::{
+        Directories, BACKUP_ABSENT_PATHS_FILENAME, BACKUP_RECORD_FILENAME, DESCRIPTOR_FILENAME,
+        IGNORE_PATTERNS_FILENAME, INCLUDE_PATTERNS_FILENAME, LABEL_FILE_ATTRIBUTES_FILENAME,
+        LABEL_METADATA_FILENAME,
+    },
     error::{
-        ConfigStorageConstructionError, ConfigTypeDirValidationError, CreateConfigTypeError,
-        GetConfigTypeError, IterConfigTypeLabelsError, IterConfigTypesError,
-        LoadLabeledConfigTypeError, StoreLabeledConfigTypeError,
+        AddPathError, BackupError, CollectGarbageError, ConfigStorageConstructionError,
+        ConfigTypeDirValidationError, CopyLabelError, CreateConfigTypeError, CreateProfileError,
+        DecodeStringError, DiffError, EncryptionError, ExportError, GetConfigTypeError,
+        GetProfileError, ImportError, InitGitRepositoryError, IterConfigTypeLabelsError,
+        IterConfigTypesError, LabelHistoryError, LabelMetadataError, ListProfilesError,
+        LoadLabeledConfigTypeError, LoadProfileError, PullError, PushError, ReadJsonFileError,
+        RekeyError, RemoveConfigTypeError, RemoveLabelError, RemovePathError, RenameLabelError,
+        RevertLabelError, StatusError, StorageBackendError, StoreLabeledConfigTypeError,
+        StoreProfileError, TrashError, UndeleteError, UndoError, UpdateLoadedLabelRecordError,
+        WhichConfigTypeError, WriteConfigTypeDescriptorError,
+    },
+    file_attributes::{FileAttributes, LabelFileAttributes},
+    fs_storage_backend::FsStorageBackend,
+    git_repository::GitRepository,
+    label_archive::{ArchiveFormat, ImportSummary, LabelArchive},
+    label_diff::{diff_directories, FileDiff},
+    label_encryption::{Encryption, LabelCipher, NewEncryption, PassphraseProvider},
+    label_metadata::LabelMetadata,
+    label_status::{FileState, LabelStatus},
+    load_transaction::LoadTransaction,
+    loaded_label_record::{ActiveLabel, LabelUsage, LoadedLabelRecord},
+    path_patterns::PathPatterns,
+    profile::Profile,
+    settings::Settings,
+    storage_backend::{
+        GarbageCollection, LabelFiles, LabelRevision, StorageBackend, StorageTransaction,
+    },
+    template::{load_template_file, store_template_file},
+    trash::{Trash, TrashEntry},
+    utils::{
+        collect_files_recursive, copy_dir_recursive, create_new_directory, ensure_directory,
+        hash_path, label_relative_path, read_files_recursive, read_json_file,
+        write_files_recursive, write_json_file, SubDirectoryIterator, TempDir,
     },
-    utils::{create_new_directory, ensure_directory, SubDirectoryIterator},
     variable_resolver::VariableResolver,
 };
 
-pub struct ListTypesIterator {
-    sub_directory_iterator: SubDirectoryIterator,
-}
-
-impl Iterator for ListTypesIterator {
-    type Item = Result<String, std::io::Error>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.sub_directory_iterator.next().map(|dir_entry| {
-            dir_entry.map(|dir_entry| dir_entry.file_name().to_string_lossy().into())
-        })
-    }
-}
-
-pub struct LabelIterator {
-    sub_directory_iterator: SubDirectoryIterator,
-}
-
-impl Iterator for LabelIterator {
-    type Item = Result<String, std::io::Error>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.sub_directory_iterator.next().map(|dir_entry| {
-            dir_entry.map(|dir_entry| dir_entry.file_name().to_string_lossy().into())
-        })
-    }
-}
-
 pub struct ConfigStorage {
-    variable_resolver: Rc<VariableResolver>,
-    directories: Rc<Directories>,
+    variable_resolver: Arc<VariableResolver>,
+    directories: Arc<Directories>,
+    settings: Arc<Settings>,
+    backend: Arc<dyn StorageBackend>,
+    passphrase_provider: Option<PassphraseProvider>,
 }
 
 impl ConfigStorage {
+    /// Keeps the config types and labels under `<root_dir>/db`, see `FsStorageBackend`.
     pub fn new(
         variable_resolver: VariableResolver,
         root_dir: impl Into<PathBuf>,
     ) -> Result<Self, ConfigStorageConstructionError> {
-        let directories = Rc::new(Directories::new(root_dir));
+        let root_dir = root_dir.into();
+        let backend = FsStorageBackend::new(&root_dir)
+            .map_err(|_| ConfigStorageConstructionError::InvalidStoragePath(root_dir.clone()))?;
+
+        Self::with_backend(variable_resolver, root_dir, Arc::new(backend))
+    }
+
+    /// Keeps the config types, labels and profiles in `backend`. The settings, the state of the
+    /// live files (e.g. backups) and the trash are still kept under `root_dir`.
+    pub fn with_backend(
+        mut variable_resolver: VariableResolver,
+        root_dir: impl Into<PathBuf>,
+        backend: Arc<dyn StorageBackend>,
+    ) -> Result<Self, ConfigStorageConstructionError> {
+        let directories = Arc::new(Directories::new(root_dir));
 
         ensure_directory(directories.root_dir_path(), true).map_err(|_| {
             ConfigStorageConstructionError::InvalidStoragePath(
                 directories.root_dir_path().to_path_buf(),
             )
         })?;
-        ensure_directory(directories.db_dir_path(), true).map_err(|_| {
+        ensure_directory(directories.temp_dir_path(), true).map_err(|_| {
+            ConfigStorageConstructionError::InvalidStoragePath(
+                directories.temp_dir_path().to_path_buf(),
+            )
+        })?;
+        ensure_directory(directories.state_dir_path(), true).map_err(|_| {
             ConfigStorageConstructionError::InvalidStoragePath(
-                directories.root_dir_path().to_path_buf(),
+                directories.state_dir_path().to_path_buf(),
             )
         })?;
-        ensure_directory(directories.temp_dir_path(), true).map_err(|_| {
+        ensure_directory(directories.trash_dir_path(), true).map_err(|_| {
             ConfigStorageConstructionError::InvalidStoragePath(
-                directories.temp_dir_path().to_path_buf(),
+                directories.trash_dir_path().to_path_buf(),
             )
         })?;
 
+        let settings_path = directories.settings_path();
+        let settings = if settings_path.exists() {
+            Settings::from_file(&settings_path).map_err(|e| {
+                ConfigStorageConstructionError::CouldNotReadSettings {
+                    error: e,
+                    path: settings_path,
+                }
+            })?
+        } else {
+            // the defaults are written, so the available settings can be discovered
+            let settings = Settings::default();
+            settings.write_to_file(&settings_path).map_err(|e| {
+                ConfigStorageConstructionError::CouldNotWriteSettings {
+                    error: e,
+                    path: settings_path,
+                }
+            })?;
+            settings
+        };
+
+        let user_variables_path = directories.user_variables_path();
+        if user_variables_path.exists() {
+            let user_variables = read_json_file(&user_variables_path).map_err(|e| {
+                ConfigStorageConstructionError::CouldNotReadUserVariables {
+                    error: e,
+                    path: user_variables_path,
+                }
+            })?;
+            variable_resolver.add_user_variables(user_variables);
+        }
+
         Ok(Self {
-            variable_resolver: Rc::new(variable_resolver),
+            variable_resolver: Arc::new(variable_resolver),
             directories,
+            settings: Arc::new(settings),
+            backend,
+            passphrase_provider: None,
         })
     }
 
-    pub fn iter_config_types(&self) -> Result<ListTypesIterator, IterConfigTypesError> {
-        Ok(ListTypesIterator {
-            sub_directory_iterator: SubDirectoryIterator::new(self.directories.db_dir_path())?,
-        })
+    /// Sets how the passphrase of a config type encrypted with a passphrase is asked for.
+    /// Without it, the labels of such config types can not be stored or loaded.
+    pub fn with_passphrase_provider(mut self, passphrase_provider: PassphraseProvider) -> Self {
+        self.passphrase_provider = Some(passphrase_provider);
+        self
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn iter_config_types(&self) -> Result<impl Iterator<Item = String>, IterConfigTypesError> {
+        Ok(self.backend.config_types()?.into_iter())
     }
 
     pub fn get_config_type_storage(
@@ -92,6 +167,9 @@ impl ConfigStorage {
             self.variable_resolver.clone(),
             config_type.as_ref(),
             self.directories.clone(),
+            self.settings.clone(),
+            self.backend.clone(),
+            self.passphrase_provider.clone(),
         )
         .map_err(|e| (config_type.as_ref(), e))?)
     }
@@ -105,11 +183,7 @@ impl ConfigStorage {
                 config_type: config_type.as_ref().into(),
             }),
             Err(GetConfigTypeError::ConfigTypeNotFound { config_type, .. }) => {
-                create_config_type_dir(
-                    self.variable_resolver.clone(),
-                    self.directories.clone(),
-                    config_type,
-                )
+                self.create_config_type_in_backend(config_type)
             }
             Err(GetConfigTypeError::IncorrectConfigTypeDir {
                 config_type,
@@ -120,221 +194,2984 @@ impl ConfigStorage {
             }),
         }
     }
-}
 
-fn create_config_type_dir(
-    variable_resolver: Rc<VariableResolver>,
-    directories: Rc<Directories>,
-    config_type: impl AsRef<str>,
-) -> Result<ConfigTypeStorage, CreateConfigTypeError> {
-    let config_type_dir_path = directories.config_type_dir_path(config_type.as_ref());
-    create_new_directory(&config_type_dir_path).map_err(|e| {
-        CreateConfigTypeError::CouldNotCreateDirectory {
-            path: config_type_dir_path.clone(),
-            new_directory_error: e,
+    /// Moves the config type to the trash. A config type with a loaded label is only removed if
+    /// `force` is set.
+    pub fn remove_config_type(
+        &self,
+        config_type: impl AsRef<str>,
+        force: bool,
+    ) -> Result<(), RemoveConfigTypeError> {
+        let config_type_storage = self.get_config_type_storage(config_type.as_ref())?;
+
+        if !force {
+            if let Some(record) = config_type_storage
+                .read_loaded_label_record()
+                .map_err(RemoveConfigTypeError::CouldNotDetermineLoadedLabel)?
+            {
+                return Err(RemoveConfigTypeError::ConfigTypeIsLoaded {
+                    config_type: config_type.as_ref().into(),
+                    label: record.label().into(),
+                });
+            }
         }
-    })?;
 
-    let empty_config_type_descriptor = ConfigTypeDescriptor::new();
+        let trash = self.trash();
+        trash.purge_expired()?;
+        trash.put_config_type(config_type)?;
+
+        Ok(())
+    }
+
+    /// Restores the most recently removed config type (`label` is `None`) or label from the trash.
+    pub fn undelete(
+        &self,
+        config_type: impl AsRef<str>,
+        label: Option<&str>,
+    ) -> Result<TrashEntry, UndeleteError> {
+        let trash = self.trash();
+        trash.purge_expired()?;
+        Ok(trash.restore(config_type, label)?)
+    }
 
-    empty_config_type_descriptor
-        .write_to_file(directories.config_type_descriptor_path(config_type.as_ref()))
-        .map_err(CreateConfigTypeError::CouldNotWriteDescriptorToFile)?;
+    pub fn trash_entries(&self) -> Result<Vec<TrashEntry>, TrashError> {
+        let trash = self.trash();
+        trash.purge_expired()?;
+        trash.entries()
+    }
 
-    ConfigTypeStorage::new(variable_resolver, config_type.as_ref(), directories).map_err(|e| {
-        CreateConfigTypeError::IncorrectConfigTypeFound {
-            config_type: config_type.as_ref().to_string(),
-            validation_error: e,
+    /// Creates a profile switching the config types of `members` (labels keyed by config type).
+    pub fn create_profile(
+        &self,
+        profile: impl AsRef<str>,
+        members: BTreeMap<String, String>,
+    ) -> Result<Profile, CreateProfileError> {
+        let profile = profile.as_ref();
+        if !Profile::is_valid_name(profile) {
+            return Err(CreateProfileError::InvalidProfileName(profile.into()));
+        }
+        if self.backend.read_profile(profile)?.is_some() {
+            return Err(CreateProfileError::ProfileAlreadyExists(profile.into()));
+        }
+        if members.is_empty() {
+            return Err(CreateProfileError::NoMembers(profile.into()));
         }
-    })
-}
 
-pub struct ConfigTypeStorage {
-    variable_resolver: Rc<VariableResolver>,
-    config_type: String,
-    directories: Rc<Directories>,
-    descriptor: Rc<ConfigTypeDescriptor>,
-}
+        for config_type in members.keys() {
+            self.get_config_type_storage(config_type)?;
+        }
 
-impl ConfigTypeStorage {
-    fn new(
-        variable_resolver: Rc<VariableResolver>,
-        config_type: impl AsRef<str>,
-        directories: Rc<Directories>,
-    ) -> Result<Self, ConfigTypeDirValidationError> {
-        ensure_directory(
-            directories.config_type_dir_path(config_type.as_ref()),
-            false,
-        )?;
+        let profile_data = Profile::new(members);
+        let content =
+            profile_data
+                .to_vec()
+                .map_err(|e| CreateProfileError::CouldNotWriteProfile {
+                    error: e,
+                    profile: profile.into(),
+                })?;
+        let mut transaction = StorageTransaction::new();
+        transaction.put_profile(profile, content);
+        self.backend.commit(transaction)?;
 
-        let descriptor = ConfigTypeDescriptor::from_file(
-            directories.config_type_descriptor_path(config_type.as_ref()),
-        )
-        .map_err(ConfigTypeDirValidationError::CouldNotReadDescriptor)?;
+        Ok(profile_data)
+    }
 
-        Ok(Self {
-            variable_resolver,
-            config_type: config_type.as_ref().into(),
-            directories,
-            descriptor: Rc::new(descriptor),
+    pub fn get_profile(&self, profile: impl AsRef<str>) -> Result<Profile, GetProfileError> {
+        let profile = profile.as_ref();
+        if !Profile::is_valid_name(profile) {
+            return Err(GetProfileError::InvalidProfileName(profile.into()));
+        }
+        let Some(content) = self.backend.read_profile(profile)? else {
+            return Err(GetProfileError::ProfileNotFound(profile.into()));
+        };
+
+        Profile::from_slice(&content).map_err(|e| GetProfileError::CouldNotReadProfile {
+            error: e,
+            profile: profile.into(),
         })
     }
 
-    pub fn descriptor_path(&self) -> PathBuf {
-        self.directories.config_type_dir_path(&self.config_type)
+    /// Returns the names of the profiles, sorted.
+    pub fn profiles(&self) -> Result<Vec<String>, ListProfilesError> {
+        Ok(self.backend.profiles()?)
     }
 
-    pub fn iter_labels(&self) -> Result<LabelIterator, IterConfigTypeLabelsError> {
-        Ok(LabelIterator {
-            sub_directory_iterator: SubDirectoryIterator::new(
-                self.directories.config_type_dir_path(&self.config_type),
-            )?,
-        })
+    /// Stores the live files of every member of the profile. If `label` is set, every member is
+    /// stored under it and the profile is updated to use it.
+    pub fn store_profile(
+        &self,
+        profile: impl AsRef<str>,
+        label: Option<&str>,
+    ) -> Result<Profile, StoreProfileError> {
+        let mut profile_data = self.get_profile(profile.as_ref())?;
+        if let Some(label) = label {
+            profile_data.set_label(label);
+        }
+
+        let config_type_storages = profile_data
+            .members()
+            .keys()
+            .map(|config_type| self.get_config_type_storage(config_type))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (config_type_storage, label) in config_type_storages
+            .iter()
+            .zip(profile_data.members().values())
+        {
+            config_type_storage.store(label, None).map_err(|e| {
+                StoreProfileError::CouldNotStoreConfigType {
+                    error: e,
+                    config_type: config_type_storage.config_type().into(),
+                }
+            })?;
+        }
+
+        if label.is_some() {
+            let content =
+                profile_data
+                    .to_vec()
+                    .map_err(|e| StoreProfileError::CouldNotWriteProfile {
+                        error: e,
+                        profile: profile.as_ref().into(),
+                    })?;
+            let mut transaction = StorageTransaction::new();
+            transaction.put_profile(profile.as_ref(), content);
+            self.backend.commit(transaction)?;
+        }
+
+        Ok(profile_data)
     }
 
-    pub fn store(&self, label: impl AsRef<str>) -> Result<(), StoreLabeledConfigTypeError> {
-        let temp_dir = self.directories.create_temp_dir_path();
+    /// Loads the label of every member of the profile. The live files of every member are
+    /// snapshotted before it is loaded, if loading a member fails the members loaded before it are
+    /// restored from their snapshots, as the backups may be turned off.
+    pub fn load_profile(&self, profile: impl AsRef<str>) -> Result<Profile, LoadProfileError> {
+        let profile_data = self.get_profile(profile.as_ref())?;
 
-        create_new_directory(&temp_dir).map_err(|e| {
-            StoreLabeledConfigTypeError::CouldNotCreateTempDirectory {
+        let mut members = Vec::new();
+        for (config_type, label) in profile_data.members() {
+            let config_type_storage = self.get_config_type_storage(config_type)?;
+            if !self.backend.has_label(config_type, label)? {
+                return Err(LoadProfileError::LabelNotFound {
+                    config_type: config_type.clone(),
+                    label: label.clone(),
+                });
+            }
+            members.push((config_type_storage, label));
+        }
+
+        let snapshots_dir_path = self.directories.create_temp_dir_path();
+        let snapshots_dir = TempDir::create(&snapshots_dir_path).map_err(|e| {
+            LoadProfileError::CouldNotCreateTempDirectory {
                 io_error: e,
-                path: temp_dir.clone(),
+                path: snapshots_dir_path,
             }
         })?;
 
-        let labeled_config_type_storage = LabeledConfigTypeStorage::new(
-            self.variable_resolver.clone(),
-            self.descriptor.clone(),
-            &temp_dir,
-        );
+        for (index, (config_type_storage, label)) in members.iter().enumerate() {
+            let snapshot_dir_path = snapshots_dir.path().join(config_type_storage.config_type());
+            if let Err(e) = config_type_storage.load_label(label, None, Some(&snapshot_dir_path)) {
+                for (loaded_config_type_storage, _) in members[..index].iter().rev() {
+                    let config_type = loaded_config_type_storage.config_type();
+                    if let Err(restore_error) = loaded_config_type_storage
+                        .restore_snapshot(&snapshots_dir.path().join(config_type))
+                    {
+                        log::error!(
+                            "could not restore config type, config type = \"{}\", error = {}",
+                            config_type,
+                            restore_error
+                        );
+                    }
+                }
+
+                return Err(LoadProfileError::CouldNotLoadConfigType {
+                    error: e,
+                    config_type: config_type_storage.config_type().into(),
+                });
+            }
+        }
 
-        labeled_config_type_storage.store()?;
+        Ok(profile_data)
+    }
 
-        drop(labeled_config_type_storage);
+    fn create_config_type_in_backend(
+        &self,
+        config_type: impl AsRef<str>,
+    ) -> Result<ConfigTypeStorage, CreateConfigTypeError> {
+        let empty_config_type_descriptor = ConfigTypeDescriptor::new()
+            .to_vec()
+            .map_err(CreateConfigTypeError::CouldNotWriteDescriptorToFile)?;
 
-        let labeled_config_type_dir_path = self
-            .directories
-            .labeled_config_type_dir_path(&self.config_type, label);
-        if labeled_config_type_dir_path.exists() {
-            remove_dir_all(&labeled_config_type_dir_path).map_err(|e| {
-                StoreLabeledConfigTypeError::CouldNotRemoveOldDirectory {
-                    io_error: e,
-                    path: labeled_config_type_dir_path.clone(),
+        let mut transaction = StorageTransaction::new();
+        transaction.create_config_type(
+            config_type.as_ref(),
+            BTreeMap::from([(DESCRIPTOR_FILENAME.into(), empty_config_type_descriptor)]),
+            BTreeMap::new(),
+        );
+        self.backend.commit(transaction)?;
+
+        self.get_config_type_storage(config_type.as_ref())
+            .map_err(|e| match e {
+                GetConfigTypeError::IncorrectConfigTypeDir {
+                    config_type,
+                    validation_error,
                 }
-            })?;
+                | GetConfigTypeError::ConfigTypeNotFound {
+                    config_type,
+                    validation_error,
+                } => CreateConfigTypeError::IncorrectConfigTypeFound {
+                    config_type,
+                    validation_error,
+                },
+            })
+    }
+
+    /// Removes the stored file contents that no label refers to anymore, e.g. after labels were
+    /// removed or stored again.
+    pub fn collect_garbage(&self) -> Result<GarbageCollection, CollectGarbageError> {
+        Ok(self.backend.collect_garbage()?)
+    }
+
+    /// Writes the config type with its descriptor and `labels` (every label if empty) to a single
+    /// archive at `path`. Returns the exported labels.
+    pub fn export(
+        &self,
+        config_type: impl AsRef<str>,
+        labels: &[String],
+        path: impl AsRef<Path>,
+        format: ArchiveFormat,
+    ) -> Result<Vec<String>, ExportError> {
+        let config_type = config_type.as_ref();
+        if !self.backend.has_config_type(config_type)? {
+            return Err(ExportError::ConfigTypeNotFound(config_type.into()));
         }
-        rename(&temp_dir, &labeled_config_type_dir_path).map_err(|e| {
-            StoreLabeledConfigTypeError::CouldNotRenameTempDirectory {
-                io_error: e,
-                source_path: temp_dir,
-                dest_path: labeled_config_type_dir_path,
+
+        let mut config_type_files = BTreeMap::new();
+        for name in self.backend.config_type_files(config_type)? {
+            if let Some(content) = self.backend.read_config_type_file(config_type, &name)? {
+                config_type_files.insert(name, content);
             }
-        })?;
+        }
 
-        Ok(())
+        let labels = if labels.is_empty() {
+            self.backend.labels(config_type)?
+        } else {
+            labels.to_vec()
+        };
+        let mut label_files = BTreeMap::new();
+        for label in &labels {
+            if !self.backend.has_label(config_type, label)? {
+                return Err(ExportError::LabelNotFound(label.clone()));
+            }
+            label_files.insert(label.clone(), self.backend.read_label(config_type, label)?);
+        }
+
+        LabelArchive::new(config_type, config_type_files, label_files).write(path, format)?;
+
+        Ok(labels)
     }
 
-    pub fn load(&self, label: impl AsRef<str>) -> Result<(), LoadLabeledConfigTypeError> {
-        let labeled_config_type_dir_path = self
-            .directories
-            .labeled_config_type_dir_path(&self.config_type, label);
+    /// Adds the config type and labels of an archive written by `export`.
+    ///
+    /// A missing config type is created with the descriptor of the archive, an existing one keeps
+    /// its own descriptor, which must encrypt the labels like the descriptor of the archive.
+    /// `renames` maps labels of the archive to the names they are stored under. Labels which
+    /// already exist are an error and nothing is imported, unless `skip_existing` is set, then
+    /// they are left as they are.
+    pub fn import(
+        &self,
+        path: impl AsRef<Path>,
+        renames: &BTreeMap<String, String>,
+        skip_existing: bool,
+    ) -> Result<ImportSummary, ImportError> {
+        let archive = LabelArchive::read(path.as_ref())?;
 
-        let labeled_config_type_storage = LabeledConfigTypeStorage::new(
-            self.variable_resolver.clone(),
-            self.descriptor.clone(),
-            &labeled_config_type_dir_path,
-        );
+        let descriptor = archive
+            .config_type_files()
+            .get(DESCRIPTOR_FILENAME)
+            .ok_or(ImportError::MissingDescriptor)?;
+        let descriptor =
+            ConfigTypeDescriptor::from_slice(descriptor).map_err(ImportError::InvalidDescriptor)?;
+
+        if let Some(label) = renames
+            .keys()
+            .find(|label| !archive.labels().contains_key(*label))
+        {
+            return Err(ImportError::RenamedLabelNotFound(label.clone()));
+        }
+
+        let (config_type, config_type_files, labels) = archive.into_parts();
+        let config_type_exists = self.backend.has_config_type(&config_type)?;
+
+        // the labels are imported as they are, so they must be encrypted like the local ones
+        if config_type_exists
+            && self
+                .get_config_type_storage(&config_type)?
+                .descriptor()
+                .encryption()
+                != descriptor.encryption()
+        {
+            return Err(ImportError::DifferentEncryption(config_type));
+        }
+
+        let mut imported_labels = BTreeMap::new();
+        let mut existing_labels = Vec::new();
+        let mut files_by_label = BTreeMap::new();
+        for (label, files) in labels {
+            let new_label = renames.get(&label).unwrap_or(&label).clone();
+            if files_by_label.contains_key(&new_label) {
+                return Err(ImportError::DuplicateLabel(new_label));
+            }
+
+            if config_type_exists && self.backend.has_label(&config_type, &new_label)? {
+                existing_labels.push(label);
+                continue;
+            }
+
+            imported_labels.insert(label, new_label.clone());
+            files_by_label.insert(new_label, files);
+        }
+
+        if !existing_labels.is_empty() && !skip_existing {
+            return Err(ImportError::LabelsAlreadyExist {
+                config_type,
+                labels: existing_labels,
+            });
+        }
+
+        let mut transaction = StorageTransaction::new();
+        if config_type_exists {
+            let message = format!(
+                "imported from {}",
+                path.as_ref()
+                    .file_name()
+                    .unwrap_or(path.as_ref().as_os_str())
+                    .to_string_lossy()
+            );
+            for (label, files) in files_by_label {
+                transaction.put_label(&config_type, label, files, Some(message.clone()));
+            }
+        } else {
+            transaction.create_config_type(&config_type, config_type_files, files_by_label);
+        }
+        self.backend.commit(transaction)?;
+
+        Ok(ImportSummary::new(
+            config_type,
+            !config_type_exists,
+            imported_labels,
+            existing_labels,
+        ))
+    }
+
+    /// Makes the storage root a git repository, so every change of the stored config types is
+    /// committed. `remote` is added as `origin`.
+    pub fn init_git_repository(&self, remote: Option<&str>) -> Result<(), InitGitRepositoryError> {
+        let root_dir_path = self.directories.root_dir_path();
+        if GitRepository::open(root_dir_path).is_some() {
+            return Err(InitGitRepositoryError::AlreadyARepository(
+                root_dir_path.to_path_buf(),
+            ));
+        }
 
-        labeled_config_type_storage.load()?;
+        let git_repository = GitRepository::init(root_dir_path)?;
+        if let Some(remote) = remote {
+            git_repository.add_remote("origin", remote)?;
+        }
+        git_repository.commit_all("Start keeping config types in git")?;
 
         Ok(())
     }
+
+    /// Pushes the commits of the storage root to `remote`.
+    pub fn push(&self, remote: impl AsRef<str>) -> Result<(), PushError> {
+        let git_repository =
+            GitRepository::open(self.directories.root_dir_path()).ok_or_else(|| {
+                PushError::NotARepository(self.directories.root_dir_path().to_path_buf())
+            })?;
+        Ok(git_repository.push(remote.as_ref())?)
+    }
+
+    /// Brings the commits of `remote` into the storage root, the local commits are put on top.
+    pub fn pull(&self, remote: impl AsRef<str>) -> Result<(), PullError> {
+        let git_repository =
+            GitRepository::open(self.directories.root_dir_path()).ok_or_else(|| {
+                PullError::NotARepository(self.directories.root_dir_path().to_path_buf())
+            })?;
+        Ok(git_repository.pull(remote.as_ref())?)
+    }
+
+    fn trash(&self) -> Trash {
+        Trash::new(self.directories.clone(), self.backend.clone())
+    }
 }
 
-pub struct LabeledConfigTypeStorage {
-    variable_resolver: Rc<VariableResolver>,
-    descriptor: Rc<ConfigTypeDescriptor>,
-    directory_path: PathBuf,
+pub struct ConfigTypeStorage {
+    variable_resolver: Arc<VariableResolver>,
+    config_type: String,
+    directories: Arc<Directories>,
+    settings: Arc<Settings>,
+    backend: Arc<dyn StorageBackend>,
+    descriptor: Arc<ConfigTypeDescriptor>,
+    passphrase_provider: Option<PassphraseProvider>,
+    /// Opened on first use, so the passphrase is asked for at most once.
+    cipher: OnceLock<LabelCipher>,
 }
 
-impl LabeledConfigTypeStorage {
-    pub fn new(
-        variable_resolver: Rc<VariableResolver>,
-        descriptor: Rc<ConfigTypeDescriptor>,
-        directory_path: impl Into<PathBuf>,
-    ) -> Self {
-        Self {
-            variable_resolver,
-            descriptor,
-            directory_path: directory_path.into(),
+impl ConfigTypeStorage {
+    fn new(
+        variable_resolver: Arc<VariableResolver>,
+        config_type: impl AsRef<str>,
+        directories: Arc<Directories>,
+        settings: Arc<Settings>,
+        backend: Arc<dyn StorageBackend>,
+        passphrase_provider: Option<PassphraseProvider>,
+    ) -> Result<Self, ConfigTypeDirValidationError> {
+        if !backend.has_config_type(config_type.as_ref())? {
+            return Err(ConfigTypeDirValidationError::ConfigTypeNotFound(
+                config_type.as_ref().into(),
+            ));
         }
+
+        let descriptor = backend
+            .read_config_type_file(config_type.as_ref(), DESCRIPTOR_FILENAME)?
+            .ok_or_else(|| {
+                ConfigTypeDirValidationError::DescriptorNotFound(
+                    backend.config_type_file_location(config_type.as_ref(), DESCRIPTOR_FILENAME),
+                )
+            })?;
+        let mut descriptor = ConfigTypeDescriptor::from_slice(&descriptor)
+            .map_err(ConfigTypeDirValidationError::CouldNotReadDescriptor)?;
+
+        let read_lines = |name| {
+            backend
+                .read_config_type_file(config_type.as_ref(), name)
+                .map(|content| {
+                    content.map_or_else(Vec::new, |content| {
+                        String::from_utf8_lossy(&content)
+                            .lines()
+                            .map(String::from)
+                            .collect()
+                    })
+                })
+        };
+        descriptor.set_file_patterns(
+            read_lines(INCLUDE_PATTERNS_FILENAME)?,
+            read_lines(IGNORE_PATTERNS_FILENAME)?,
+        );
+
+        Ok(Self {
+            variable_resolver,
+            config_type: config_type.as_ref().into(),
+            directories,
+            settings,
+            backend,
+            descriptor: Arc::new(descriptor),
+            passphrase_provider,
+            cipher: OnceLock::new(),
+        })
     }
 
-    pub fn store(&self) -> Result<(), StoreLabeledConfigTypeError> {
-        for path in self.descriptor.paths() {
-            let decoded_path = PathBuf::from(
-                self.variable_resolver
-                    .decode_string(path.to_string_lossy())?,
-            );
+    pub fn config_type(&self) -> &str {
+        &self.config_type
+    }
 
-            let src_file_path = decoded_path;
+    /// Where the descriptor is kept, e.g. the path of the descriptor file.
+    pub fn descriptor_location(&self) -> String {
+        self.backend
+            .config_type_file_location(&self.config_type, DESCRIPTOR_FILENAME)
+    }
 
-            let mut dest_file_path = self.directory_path.clone();
-            dest_file_path.push(path);
+    pub fn descriptor(&self) -> &ConfigTypeDescriptor {
+        &self.descriptor
+    }
 
-            println!("dest = {:?}, source = {:?}", dest_file_path, src_file_path);
+    /// Replaces the descriptor, e.g. with an edited copy. The patterns of the pattern files are
+    /// kept, as they are not part of the descriptor.
+    pub fn set_descriptor(
+        &mut self,
+        mut descriptor: ConfigTypeDescriptor,
+    ) -> Result<(), WriteConfigTypeDescriptorError> {
+        descriptor.copy_file_patterns_from(&self.descriptor);
+        self.write_descriptor(descriptor)
+    }
 
-            let parent_path = dest_file_path.parent().ok_or_else(|| {
-                StoreLabeledConfigTypeError::InvalidParentOfFileLocationInConfigTypeDescriptor(
-                    dest_file_path.clone(),
-                )
-            })?;
+    fn write_descriptor(
+        &mut self,
+        descriptor: ConfigTypeDescriptor,
+    ) -> Result<(), WriteConfigTypeDescriptorError> {
+        let mut transaction = StorageTransaction::new();
+        transaction.put_config_type_file(
+            &self.config_type,
+            DESCRIPTOR_FILENAME,
+            descriptor.to_vec()?,
+        );
+        self.backend.commit(transaction)?;
 
-            ensure_directory(parent_path, true)?;
+        self.descriptor = Arc::new(descriptor);
 
-            copy(&src_file_path, &dest_file_path).map_err(|e| {
-                StoreLabeledConfigTypeError::CouldNotCopyFile {
-                    io_error: e,
-                    source_path: src_file_path,
-                    dest_path: dest_file_path,
-                }
+        Ok(())
+    }
+
+    /// Adds an existing file or directory to the descriptor, paths under a known directory (e.g.
+    /// the home directory) are stored with the variable, so the descriptor stays portable. Returns
+    /// the path as it was written into the descriptor.
+    pub fn add_path(
+        &mut self,
+        path: impl AsRef<Path>,
+        load_mode: LoadMode,
+        template: bool,
+        mode: Option<u32>,
+    ) -> Result<String, AddPathError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(AddPathError::PathDoesNotExist(path.to_path_buf()));
+        }
+
+        let absolute_path =
+            std::path::absolute(path).map_err(|e| AddPathError::CouldNotMakePathAbsolute {
+                io_error: e,
+                path: path.to_path_buf(),
             })?;
+        let encoded_path = self.variable_resolver.encode_path(&absolute_path);
+        if label_relative_path(&encoded_path).is_none() {
+            return Err(AddPathError::PathNotUnderVariable(absolute_path));
         }
 
-        Ok(())
+        let mut descriptor = (*self.descriptor).clone();
+        if !descriptor.add_path(PathEntry::new(&encoded_path, load_mode, template, mode)) {
+            return Err(AddPathError::PathAlreadyAdded(encoded_path));
+        }
+
+        self.write_descriptor(descriptor)
+            .map_err(AddPathError::CouldNotWriteDescriptorToFile)?;
+
+        Ok(encoded_path)
     }
 
-    pub fn load(&self) -> Result<(), LoadLabeledConfigTypeError> {
-        for path in self.descriptor.paths() {
-            let decoded_path = PathBuf::from(
-                self.variable_resolver
-                    .decode_string(path.to_string_lossy())?,
-            );
+    /// Removes a path from the descriptor, given either as written in the descriptor or as a
+    /// live file path. Returns the path as it was written in the descriptor.
+    pub fn remove_path(&mut self, path: impl AsRef<str>) -> Result<String, RemovePathError> {
+        let mut descriptor = (*self.descriptor).clone();
 
-            let dest_file_path = decoded_path;
+        let mut removed_path = path.as_ref().to_string();
+        if !descriptor.remove_path(&removed_path) {
+            let absolute_path =
+                std::path::absolute(path.as_ref()).unwrap_or_else(|_| PathBuf::from(path.as_ref()));
+            removed_path = self.variable_resolver.encode_path(absolute_path);
+            if !descriptor.remove_path(&removed_path) {
+                return Err(RemovePathError::PathNotFound(path.as_ref().into()));
+            }
+        }
 
-            let mut src_file_path = self.directory_path.clone();
-            src_file_path.push(path);
+        self.write_descriptor(descriptor)
+            .map_err(RemovePathError::CouldNotWriteDescriptorToFile)?;
 
-            println!("dest = {:?}, source = {:?}", dest_file_path, src_file_path);
+        Ok(removed_path)
+    }
 
-            let parent_path = dest_file_path.parent().ok_or_else(|| {
-                LoadLabeledConfigTypeError::InvalidParentOfFileLocationInConfigTypeDescriptor(
-                    dest_file_path.clone(),
-                )
-            })?;
+    pub fn iter_labels(&self) -> Result<impl Iterator<Item = String>, IterConfigTypeLabelsError> {
+        Ok(self.backend.labels(&self.config_type)?.into_iter())
+    }
 
-            ensure_directory(parent_path, true)?;
+    pub fn has_label(&self, label: impl AsRef<str>) -> Result<bool, StorageBackendError> {
+        self.backend.has_label(&self.config_type, label.as_ref())
+    }
 
-            copy(&src_file_path, &dest_file_path).map_err(|e| {
-                LoadLabeledConfigTypeError::CouldNotCopyFile {
-                    io_error: e,
-                    source_path: src_file_path,
-                    dest_path: dest_file_path,
-                }
-            })?;
+    /// Writes the files of the label, or of one of its revisions, into `dir_path`, so they can be
+    /// loaded or compared.
+    fn checkout_label<E>(
+        &self,
+        label: &str,
+        revision: Option<u32>,
+        dir_path: &Path,
+    ) -> Result<(), E>
+    where
+        E: From<StorageBackendError> + From<EncryptionError>,
+    {
+        let files = match revision {
+            Some(revision) => {
+                self.backend
+                    .read_label_revision(&self.config_type, label, revision)?
+            }
+            None => self.backend.read_label(&self.config_type, label)?,
+        };
+        let files = self.decrypt_label_files(files)?;
+        write_files_recursive(dir_path, &files).map_err(|e| {
+            E::from(StorageBackendError::CouldNotWriteFile {
+                io_error: e,
+                path: dir_path.to_path_buf(),
+            })
+        })
+    }
+
+    /// Returns the cipher of the label files, `None` if the config type is not encrypted.
+    fn label_cipher(&self) -> Result<Option<&LabelCipher>, EncryptionError> {
+        let Some(encryption) = self.descriptor.encryption() else {
+            return Ok(None);
+        };
+        if let Some(cipher) = self.cipher.get() {
+            return Ok(Some(cipher));
         }
 
-        Ok(())
+        let cipher = match encryption {
+            Encryption::Passphrase { salt, key_check } => {
+                let passphrase_provider = self
+                    .passphrase_provider
+                    .as_ref()
+                    .ok_or_else(|| EncryptionError::NoPassphrase(self.config_type.clone()))?;
+                let passphrase = Zeroizing::new(
+                    passphrase_provider(&self.config_type)
+                        .map_err(EncryptionError::CouldNotReadPassphrase)?,
+                );
+                LabelCipher::from_passphrase(&self.config_type, &passphrase, salt, key_check)?
+            }
+            Encryption::KeyFile {
+                recipient,
+                key_file,
+            } => {
+                let key_file = self
+                    .variable_resolver
+                    .decode_string(key_file)
+                    .map_err(EncryptionError::CouldNotDecodeKeyFilePath)?;
+                LabelCipher::from_key_file(&self.config_type, recipient, key_file)?
+            }
+        };
+
+        Ok(Some(self.cipher.get_or_init(|| cipher)))
+    }
+
+    /// Encrypts the files of a label, except its metadata, if the config type is encrypted.
+    fn encrypt_label_files(&self, files: LabelFiles) -> Result<LabelFiles, EncryptionError> {
+        let Some(cipher) = self.label_cipher()? else {
+            return Ok(files);
+        };
+
+        files
+            .into_iter()
+            .map(|(path, content)| {
+                if path == Path::new(LABEL_METADATA_FILENAME) {
+                    return Ok((path, content));
+                }
+                let content = cipher.encrypt(&path, &content)?;
+                Ok((path, content))
+            })
+            .collect()
+    }
+
+    /// Encrypts the files of a label like `encrypt_label_files`, but keeps the stored content of
+    /// every file which did not change. Each encryption gives a different content, which would
+    /// otherwise be a new object and a changed file in the git history on every store.
+    fn encrypt_changed_label_files(
+        &self,
+        label: &str,
+        files: LabelFiles,
+    ) -> Result<LabelFiles, StoreLabeledConfigTypeError> {
+        let Some(cipher) = self.label_cipher()? else {
+            return Ok(files);
+        };
+        let stored_files = match self.backend.read_label(&self.config_type, label) {
+            Ok(stored_files) => stored_files,
+            Err(StorageBackendError::LabelNotFound { .. }) => LabelFiles::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        files
+            .into_iter()
+            .map(|(path, content)| {
+                if path == Path::new(LABEL_METADATA_FILENAME) {
+                    return Ok((path, content));
+                }
+                if let Some(stored_content) = stored_files.get(&path) {
+                    // a stored content which cannot be decrypted is simply replaced
+                    if let Ok(stored_plaintext) = cipher.decrypt(&path, stored_content.clone()) {
+                        if stored_plaintext == content {
+                            return Ok((path, stored_content.clone()));
+                        }
+                    }
+                }
+                let content = cipher.encrypt(&path, &content)?;
+                Ok((path, content))
+            })
+            .collect()
+    }
+
+    /// Decrypts the files of a label, except its metadata, if the config type is encrypted. Fails
+    /// on files which are not encrypted, see `rekey`.
+    fn decrypt_label_files(&self, files: LabelFiles) -> Result<LabelFiles, EncryptionError> {
+        let Some(cipher) = self.label_cipher()? else {
+            return Ok(files);
+        };
+
+        files
+            .into_iter()
+            .map(|(path, content)| {
+                if path == Path::new(LABEL_METADATA_FILENAME) {
+                    return Ok((path, content));
+                }
+                let content = cipher.decrypt(&path, content)?;
+                Ok((path, content))
+            })
+            .collect()
+    }
+
+    /// Encrypts the files of every label and of all their revisions with `new_encryption`, or
+    /// decrypts them for `NewEncryption::None`. The descriptor and the labels are written in one
+    /// transaction, then the backups of the live files are rewritten and the stored contents only
+    /// the old key can read are removed. Copies kept elsewhere (e.g. the trash, exported archives
+    /// or the history of a git repository) are not changed. Returns the number of labels.
+    ///
+    /// Unencrypted files of an encrypted config type are only accepted with `allow_plaintext`,
+    /// as they may have been put there by someone who does not know the key.
+    pub fn rekey(
+        &mut self,
+        new_encryption: NewEncryption,
+        allow_plaintext: bool,
+    ) -> Result<usize, RekeyError> {
+        let old_cipher = self.label_cipher().map_err(RekeyError::CouldNotDecrypt)?;
+        if old_cipher.is_none() && matches!(new_encryption, NewEncryption::None) {
+            return Err(RekeyError::NotEncrypted(self.config_type.clone()));
+        }
+
+        let (encryption, new_cipher) = match new_encryption {
+            NewEncryption::None => (None, None),
+            NewEncryption::Passphrase(passphrase) => {
+                let passphrase = Zeroizing::new(passphrase);
+                let (encryption, cipher) = LabelCipher::new_passphrase(&passphrase)
+                    .map_err(RekeyError::CouldNotEncrypt)?;
+                (Some(encryption), Some(cipher))
+            }
+            NewEncryption::KeyFile(key_file) => {
+                let key_file = std::path::absolute(&key_file).map_err(|e| {
+                    RekeyError::CouldNotMakePathAbsolute {
+                        io_error: e,
+                        path: key_file.clone(),
+                    }
+                })?;
+                let encoded_key_file = self.variable_resolver.encode_path(&key_file);
+                let (encryption, cipher) = LabelCipher::new_key_file(key_file, encoded_key_file)
+                    .map_err(RekeyError::CouldNotEncrypt)?;
+                (Some(encryption), Some(cipher))
+            }
+        };
+
+        let rekey_files = |files: LabelFiles| -> Result<LabelFiles, RekeyError> {
+            files
+                .into_iter()
+                .map(|(path, mut content)| {
+                    if path == Path::new(LABEL_METADATA_FILENAME) {
+                        return Ok((path, content));
+                    }
+                    if let Some(cipher) = old_cipher {
+                        if !allow_plaintext || LabelCipher::is_encrypted(&content) {
+                            content = cipher
+                                .decrypt(&path, content)
+                                .map_err(RekeyError::CouldNotDecrypt)?;
+                        }
+                    }
+                    if let Some(cipher) = &new_cipher {
+                        content = cipher
+                            .encrypt(&path, &content)
+                            .map_err(RekeyError::CouldNotEncrypt)?;
+                    }
+                    Ok((path, content))
+                })
+                .collect()
+        };
+
+        // the backups of the live files are encrypted like the labels, they are rewritten once the
+        // labels are committed
+        let mut backups = Vec::new();
+        let backups_dir_path = self.directories.backups_dir_path(&self.config_type);
+        for backup in self
+            .backups()
+            .map_err(|e| RekeyError::CouldNotRekeyBackup {
+                io_error: e,
+                path: backups_dir_path,
+            })?
+        {
+            let backup_dir_path = self.directories.backup_dir_path(&self.config_type, &backup);
+            let mut files = read_files_recursive(&backup_dir_path).map_err(|e| {
+                RekeyError::CouldNotRekeyBackup {
+                    io_error: e,
+                    path: backup_dir_path.clone(),
+                }
+            })?;
+            files.remove(Path::new(BACKUP_RECORD_FILENAME));
+            files.remove(Path::new(BACKUP_ABSENT_PATHS_FILENAME));
+            backups.push((backup_dir_path, rekey_files(files)?));
+        }
+
+        let mut descriptor = (*self.descriptor).clone();
+        descriptor.set_encryption(encryption);
+
+        let mut transaction = StorageTransaction::new();
+        transaction.put_config_type_file(
+            &self.config_type,
+            DESCRIPTOR_FILENAME,
+            descriptor.to_vec()?,
+        );
+        let labels = self.backend.labels(&self.config_type)?;
+        for label in &labels {
+            let files = rekey_files(self.backend.read_label(&self.config_type, label)?)?;
+            let mut revisions = BTreeMap::new();
+            for revision in self.backend.label_revisions(&self.config_type, label)? {
+                let revision_files = self.backend.read_label_revision(
+                    &self.config_type,
+                    label,
+                    revision.number(),
+                )?;
+                revisions.insert(revision.number(), rekey_files(revision_files)?);
+            }
+            transaction.rewrite_label(&self.config_type, label, files, revisions);
+        }
+        self.backend.commit(transaction)?;
+
+        self.descriptor = Arc::new(descriptor);
+        self.cipher = new_cipher.map(OnceLock::from).unwrap_or_default();
+
+        for (backup_dir_path, files) in backups {
+            write_files_recursive(&backup_dir_path, &files).map_err(|e| {
+                RekeyError::CouldNotRekeyBackup {
+                    io_error: e,
+                    path: backup_dir_path.clone(),
+                }
+            })?;
+        }
+
+        // the labels are already rekeyed, the old contents are also removed by the next gc
+        if let Err(e) = self.backend.collect_garbage() {
+            log::warn!(
+                "could not remove the contents encrypted with the old key, error = {}",
+                e
+            );
+        }
+
+        Ok(labels.len())
+    }
+
+    /// Returns the names of the backups, the oldest first.
+    pub fn backups(&self) -> Result<Vec<String>, std::io::Error> {
+        let backups_dir_path = self.directories.backups_dir_path(&self.config_type);
+        if !backups_dir_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = SubDirectoryIterator::new(backups_dir_path)?
+            .map(|dir_entry| {
+                dir_entry.map(|dir_entry| dir_entry.file_name().to_string_lossy().to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        backups.sort();
+
+        Ok(backups)
+    }
+
+    /// Stores the live files as a new revision of the label, `message` describes the revision.
+    /// The label is left as it is if the live files did not change since its latest revision.
+    pub fn store(
+        &self,
+        label: impl AsRef<str>,
+        message: Option<&str>,
+    ) -> Result<(), StoreLabeledConfigTypeError> {
+        let temp_dir_path = self.directories.create_temp_dir_path();
+        let temp_dir = TempDir::create(&temp_dir_path).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: temp_dir_path,
+            }
+        })?;
+
+        LabeledConfigTypeStorage::new(
+            self.variable_resolver.clone(),
+            self.descriptor.clone(),
+            temp_dir.path(),
+        )
+        .store()?;
+
+        let mut files = read_files_recursive(temp_dir.path()).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotReadStagedFiles {
+                io_error: e,
+                path: temp_dir.path().to_path_buf(),
+            }
+        })?;
+
+        // the description and tags are kept when a label is stored again
+        let metadata = match self.backend.read_label_file(
+            &self.config_type,
+            label.as_ref(),
+            Path::new(LABEL_METADATA_FILENAME),
+        ) {
+            Ok(Some(metadata)) => {
+                let mut metadata = LabelMetadata::from_slice(&metadata).map_err(|e| {
+                    StoreLabeledConfigTypeError::CouldNotReadLabelMetadata {
+                        error: e,
+                        label: label.as_ref().into(),
+                    }
+                })?;
+                metadata.touch();
+                metadata
+            }
+            Ok(None) | Err(StorageBackendError::LabelNotFound { .. }) => LabelMetadata::new(),
+            Err(e) => return Err(e.into()),
+        };
+        files.insert(
+            PathBuf::from(LABEL_METADATA_FILENAME),
+            metadata.to_vec().map_err(|e| {
+                StoreLabeledConfigTypeError::CouldNotWriteLabelMetadata {
+                    error: e,
+                    label: label.as_ref().into(),
+                }
+            })?,
+        );
+        let files = self.encrypt_changed_label_files(label.as_ref(), files)?;
+
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label(
+            &self.config_type,
+            label.as_ref(),
+            files,
+            message.map(String::from),
+        );
+        self.backend.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Returns the revisions of the label, the oldest first.
+    pub fn history(&self, label: impl AsRef<str>) -> Result<Vec<LabelRevision>, LabelHistoryError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(LabelHistoryError::LabelNotFound(label.as_ref().into()));
+        }
+
+        Ok(self
+            .backend
+            .label_revisions(&self.config_type, label.as_ref())?)
+    }
+
+    /// Stores the files of `revision` as a new revision of the label, so they become its current
+    /// files again. The description and tags of the label are kept. Returns the new revision.
+    pub fn revert(
+        &self,
+        label: impl AsRef<str>,
+        revision: u32,
+    ) -> Result<LabelRevision, RevertLabelError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(RevertLabelError::LabelNotFound(label.as_ref().into()));
+        }
+        if !self.has_revision(label.as_ref(), revision)? {
+            return Err(RevertLabelError::RevisionNotFound {
+                label: label.as_ref().into(),
+                revision,
+            });
+        }
+
+        let mut files =
+            self.backend
+                .read_label_revision(&self.config_type, label.as_ref(), revision)?;
+
+        if let Some(metadata) = self.backend.read_label_file(
+            &self.config_type,
+            label.as_ref(),
+            Path::new(LABEL_METADATA_FILENAME),
+        )? {
+            let mut metadata = LabelMetadata::from_slice(&metadata).map_err(|e| {
+                RevertLabelError::CouldNotReadLabelMetadata {
+                    error: e,
+                    label: label.as_ref().into(),
+                }
+            })?;
+            metadata.touch();
+            files.insert(
+                PathBuf::from(LABEL_METADATA_FILENAME),
+                metadata
+                    .to_vec()
+                    .map_err(|e| RevertLabelError::CouldNotWriteLabelMetadata {
+                        error: e,
+                        label: label.as_ref().into(),
+                    })?,
+            );
+        }
+
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label(
+            &self.config_type,
+            label.as_ref(),
+            files,
+            Some(format!("revert to revision {}", revision)),
+        );
+        self.backend.commit(transaction)?;
+
+        self.backend
+            .label_revisions(&self.config_type, label.as_ref())?
+            .pop()
+            .ok_or_else(|| {
+                RevertLabelError::CouldNotAccessStorage(StorageBackendError::RevisionNotFound {
+                    config_type: self.config_type.clone(),
+                    label: label.as_ref().into(),
+                    revision,
+                })
+            })
+    }
+
+    fn has_revision(&self, label: &str, revision: u32) -> Result<bool, StorageBackendError> {
+        Ok(self
+            .backend
+            .label_revisions(&self.config_type, label)?
+            .iter()
+            .any(|stored_revision| stored_revision.number() == revision))
+    }
+
+    /// Returns the metadata of the label, `None` for labels stored before metadata was kept.
+    pub fn label_metadata(
+        &self,
+        label: impl AsRef<str>,
+    ) -> Result<Option<LabelMetadata>, LabelMetadataError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(LabelMetadataError::LabelNotFound(label.as_ref().into()));
+        }
+
+        let Some(metadata) = self.backend.read_label_file(
+            &self.config_type,
+            label.as_ref(),
+            Path::new(LABEL_METADATA_FILENAME),
+        )?
+        else {
+            return Ok(None);
+        };
+
+        LabelMetadata::from_slice(&metadata).map(Some).map_err(|e| {
+            LabelMetadataError::CouldNotReadMetadata {
+                error: e,
+                label: label.as_ref().into(),
+            }
+        })
+    }
+
+    /// Changes the metadata of the label with `update` and writes it back.
+    pub fn update_label_metadata(
+        &self,
+        label: impl AsRef<str>,
+        update: impl FnOnce(&mut LabelMetadata),
+    ) -> Result<LabelMetadata, LabelMetadataError> {
+        let mut metadata = self
+            .label_metadata(label.as_ref())?
+            .unwrap_or_else(LabelMetadata::new);
+        update(&mut metadata);
+
+        let content = metadata
+            .to_vec()
+            .map_err(|e| LabelMetadataError::CouldNotWriteMetadata {
+                error: e,
+                label: label.as_ref().into(),
+            })?;
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label_file(
+            &self.config_type,
+            label.as_ref(),
+            LABEL_METADATA_FILENAME,
+            content,
+        );
+        self.backend.commit(transaction)?;
+
+        Ok(metadata)
+    }
+
+    pub fn load(&self, label: impl AsRef<str>) -> Result<(), LoadLabeledConfigTypeError> {
+        self.load_label(label.as_ref(), None, None)
+    }
+
+    /// Loads the files of the label as they were in `revision`.
+    pub fn load_revision(
+        &self,
+        label: impl AsRef<str>,
+        revision: u32,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        self.load_label(label.as_ref(), Some(revision), None)
+    }
+
+    /// Backs up the live files and loads the label. The live files are also written to
+    /// `snapshot_dir_path` if it is set, see `write_snapshot`.
+    fn load_label(
+        &self,
+        label: &str,
+        revision: Option<u32>,
+        snapshot_dir_path: Option<&Path>,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        if !self.backend.has_label(&self.config_type, label)? {
+            return Err(LoadLabeledConfigTypeError::LabelNotFound(label.into()));
+        }
+        if let Some(revision) = revision {
+            if !self.has_revision(label, revision)? {
+                return Err(LoadLabeledConfigTypeError::RevisionNotFound {
+                    label: label.into(),
+                    revision,
+                });
+            }
+        }
+
+        let checkout_dir_path = self.directories.create_temp_dir_path();
+        let checkout_dir = TempDir::create(&checkout_dir_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: checkout_dir_path,
+            }
+        })?;
+        self.checkout_label::<LoadLabeledConfigTypeError>(label, revision, checkout_dir.path())?;
+
+        let labeled_config_type_storage = LabeledConfigTypeStorage::new(
+            self.variable_resolver.clone(),
+            self.descriptor.clone(),
+            checkout_dir.path(),
+        );
+
+        let absent_paths = labeled_config_type_storage.absent_live_paths()?;
+        if let Some(snapshot_dir_path) = snapshot_dir_path {
+            self.write_snapshot(snapshot_dir_path, &absent_paths)?;
+        }
+        self.create_backup(&absent_paths)?;
+
+        self.load_with_staging(&labeled_config_type_storage)?;
+
+        self.write_loaded_label_record(label, revision, &labeled_config_type_storage)?;
+
+        Ok(())
+    }
+
+    fn load_with_staging(
+        &self,
+        labeled_config_type_storage: &LabeledConfigTypeStorage,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        let staging_dir = self.directories.create_temp_dir_path();
+        create_new_directory(&staging_dir).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: staging_dir.clone(),
+            }
+        })?;
+
+        let result = labeled_config_type_storage.load(&staging_dir);
+
+        if let Err(e) = remove_dir_all(&staging_dir) {
+            log::warn!(
+                "could not remove staging directory, path = {:?}, error = {}",
+                staging_dir,
+                e
+            );
+        }
+
+        result
+    }
+
+    /// Stores the live files, the loaded label record and the `absent_paths` the load creates as a
+    /// backup, and removes the backups exceeding the retention set in the settings.
+    fn create_backup(&self, absent_paths: &[PathBuf]) -> Result<(), BackupError> {
+        let backup_retention = self.settings.backup_retention();
+        if backup_retention == 0 {
+            return Ok(());
+        }
+
+        let temp_dir_path = self.directories.create_temp_dir_path();
+        let temp_dir = TempDir::create(&temp_dir_path).map_err(|e| {
+            BackupError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: temp_dir_path,
+            }
+        })?;
+
+        self.write_live_files(temp_dir.path())?;
+
+        // the live files of an encrypted config type hold the same secrets as its labels
+        if self.label_cipher()?.is_some() {
+            let map_err = |e| BackupError::CouldNotRewriteFiles {
+                io_error: e,
+                path: temp_dir.path().to_path_buf(),
+            };
+            let files = read_files_recursive(temp_dir.path()).map_err(map_err)?;
+            write_files_recursive(temp_dir.path(), &self.encrypt_label_files(files)?)
+                .map_err(map_err)?;
+        }
+
+        self.copy_loaded_label_record(temp_dir.path())?;
+        write_absent_paths(temp_dir.path(), absent_paths)?;
+
+        let backups_dir_path = self.directories.backups_dir_path(&self.config_type);
+        ensure_directory(&backups_dir_path, true)?;
+
+        let backup_dir_path = self.directories.backup_dir_path(
+            &self.config_type,
+            Utc::now().format("%Y-%m-%dT%H-%M-%S%.6fZ").to_string(),
+        );
+        rename(temp_dir.path(), &backup_dir_path).map_err(|e| {
+            BackupError::CouldNotRenameTempDirectory {
+                io_error: e,
+                source_path: temp_dir.path().to_path_buf(),
+                dest_path: backup_dir_path,
+            }
+        })?;
+        temp_dir.keep();
+
+        let backups = self
+            .backups()
+            .map_err(|e| BackupError::CouldNotReadBackupsDirectory {
+                io_error: e,
+                path: backups_dir_path,
+            })?;
+        for backup in &backups[..backups.len().saturating_sub(backup_retention)] {
+            let backup_dir_path = self.directories.backup_dir_path(&self.config_type, backup);
+            remove_dir_all(&backup_dir_path).map_err(|e| BackupError::CouldNotRemoveOldBackup {
+                io_error: e,
+                path: backup_dir_path,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the live files like a label into `dir_path`, which must exist.
+    fn write_live_files(&self, dir_path: &Path) -> Result<(), BackupError> {
+        LabeledConfigTypeStorage::new(
+            self.variable_resolver.clone(),
+            self.descriptor.clone(),
+            dir_path,
+        )
+        .skip_missing_files()
+        .store()
+        .map_err(|e| BackupError::CouldNotStoreLiveFiles(Box::new(e)))
+    }
+
+    /// Copies the loaded label record, if there is one, next to the files of a backup.
+    fn copy_loaded_label_record(&self, dir_path: &Path) -> Result<(), BackupError> {
+        let record_path = self.directories.last_loaded_record_path(&self.config_type);
+        if record_path.exists() {
+            let backup_record_path = dir_path.join(BACKUP_RECORD_FILENAME);
+            copy(&record_path, &backup_record_path).map_err(|e| BackupError::CouldNotCopyFile {
+                io_error: e,
+                source_path: record_path,
+                dest_path: backup_record_path,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the live files, the loaded label record and the `absent_paths` into `dir_path` like a
+    /// backup, which is neither encrypted nor counted by the retention, see `restore_snapshot`.
+    fn write_snapshot(&self, dir_path: &Path, absent_paths: &[PathBuf]) -> Result<(), BackupError> {
+        ensure_directory(dir_path, true)?;
+        self.write_live_files(dir_path)?;
+        self.copy_loaded_label_record(dir_path)?;
+        write_absent_paths(dir_path, absent_paths)
+    }
+
+    /// Restores the live files and the loaded label record from a snapshot, see
+    /// `write_snapshot`.
+    fn restore_snapshot(&self, dir_path: &Path) -> Result<(), UndoError> {
+        self.restore_live_files(dir_path, dir_path)
+    }
+
+    /// Loads the files of a backup or a snapshot in `files_dir_path` and removes the live paths
+    /// which did not exist when it was made, then replaces the loaded label record with the one of
+    /// the backup in `backup_dir_path`, or removes it if the backup does not have one.
+    fn restore_live_files(
+        &self,
+        files_dir_path: &Path,
+        backup_dir_path: &Path,
+    ) -> Result<(), UndoError> {
+        let absent_paths_path = backup_dir_path.join(BACKUP_ABSENT_PATHS_FILENAME);
+        // backups made before the absent paths were recorded do not have them
+        let absent_paths = if absent_paths_path.exists() {
+            read_json_file(&absent_paths_path).map_err(|e| UndoError::CouldNotReadAbsentPaths {
+                error: e,
+                path: absent_paths_path,
+            })?
+        } else {
+            Vec::new()
+        };
+
+        self.load_with_staging(
+            &LabeledConfigTypeStorage::new(
+                self.variable_resolver.clone(),
+                self.descriptor.clone(),
+                files_dir_path,
+            )
+            .skip_missing_files()
+            .remove_paths(absent_paths),
+        )?;
+
+        let backup_record_path = backup_dir_path.join(BACKUP_RECORD_FILENAME);
+        let record_path = self.directories.last_loaded_record_path(&self.config_type);
+        if backup_record_path.exists() {
+            ensure_directory(
+                self.directories
+                    .config_type_state_dir_path(&self.config_type),
+                true,
+            )?;
+            copy(&backup_record_path, &record_path).map_err(|e| UndoError::CouldNotCopyFile {
+                io_error: e,
+                source_path: backup_record_path,
+                dest_path: record_path,
+            })?;
+        } else if record_path.exists() {
+            std::fs::remove_file(&record_path).map_err(|e| UndoError::CouldNotRemoveFile {
+                io_error: e,
+                path: record_path,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Restores the live files and the loaded label record from the most recent backup, then
+    /// removes the backup, so the next undo goes further back. Returns the name of the backup.
+    pub fn undo(&self) -> Result<String, UndoError> {
+        let backups_dir_path = self.directories.backups_dir_path(&self.config_type);
+        let backup = self
+            .backups()
+            .map_err(|e| UndoError::CouldNotReadBackupsDirectory {
+                io_error: e,
+                path: backups_dir_path,
+            })?
+            .pop()
+            .ok_or_else(|| UndoError::NoBackupFound(self.config_type.clone()))?;
+        let backup_dir_path = self.directories.backup_dir_path(&self.config_type, &backup);
+
+        let checkout_dir = match self.descriptor.encryption() {
+            Some(_) => Some(self.checkout_backup(&backup_dir_path)?),
+            None => None,
+        };
+        self.restore_live_files(
+            checkout_dir
+                .as_ref()
+                .map_or(backup_dir_path.as_path(), TempDir::path),
+            &backup_dir_path,
+        )?;
+
+        remove_dir_all(&backup_dir_path).map_err(|e| UndoError::CouldNotRemoveBackup {
+            io_error: e,
+            path: backup_dir_path,
+        })?;
+
+        Ok(backup)
+    }
+
+    /// Writes the decrypted files of a backup of an encrypted config type into a temp directory,
+    /// so they can be loaded.
+    fn checkout_backup(&self, backup_dir_path: &Path) -> Result<TempDir, UndoError> {
+        let mut files = read_files_recursive(backup_dir_path).map_err(|e| {
+            UndoError::CouldNotCheckoutBackup {
+                io_error: e,
+                path: backup_dir_path.to_path_buf(),
+            }
+        })?;
+        files.remove(Path::new(BACKUP_RECORD_FILENAME));
+        files.remove(Path::new(BACKUP_ABSENT_PATHS_FILENAME));
+        let files = self.decrypt_label_files(files)?;
+
+        let checkout_dir_path = self.directories.create_temp_dir_path();
+        let checkout_dir = TempDir::create(&checkout_dir_path).map_err(|e| {
+            UndoError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: checkout_dir_path,
+            }
+        })?;
+        write_files_recursive(checkout_dir.path(), &files).map_err(|e| {
+            UndoError::CouldNotCheckoutBackup {
+                io_error: e,
+                path: checkout_dir.path().to_path_buf(),
+            }
+        })?;
+
+        Ok(checkout_dir)
+    }
+
+    fn write_loaded_label_record(
+        &self,
+        label: impl AsRef<str>,
+        revision: Option<u32>,
+        labeled_config_type_storage: &LabeledConfigTypeStorage,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        let mut file_hashes = BTreeMap::new();
+        for path in self.descriptor.paths() {
+            let path = path.path().to_string();
+            let live_file_path = PathBuf::from(self.variable_resolver.decode_string(&path)?);
+            let hash = hash_path(&live_file_path).map_err(|e| {
+                LoadLabeledConfigTypeError::CouldNotHashFile {
+                    io_error: e,
+                    path: live_file_path,
+                }
+            })?;
+            file_hashes.insert(path, hash);
+        }
+
+        for path in labeled_config_type_storage.stored_pattern_files()? {
+            let path = path.to_string_lossy().to_string();
+            let live_file_path = PathBuf::from(self.variable_resolver.decode_string(&path)?);
+            let hash = hash_path(&live_file_path).map_err(|e| {
+                LoadLabeledConfigTypeError::CouldNotHashFile {
+                    io_error: e,
+                    path: live_file_path,
+                }
+            })?;
+            file_hashes.insert(path, hash);
+        }
+
+        ensure_directory(
+            self.directories
+                .config_type_state_dir_path(&self.config_type),
+            true,
+        )
+        .map_err(LoadLabeledConfigTypeError::CouldNotCreateStateDirectory)?;
+
+        let record_path = self.directories.last_loaded_record_path(&self.config_type);
+        let record = LoadedLabelRecord::new(label.as_ref(), revision, file_hashes);
+        record.write_to_file(&record_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotWriteLoadedLabelRecord {
+                error: e,
+                path: record_path,
+            }
+        })?;
+
+        let usage_path = self.directories.label_usage_path(&self.config_type);
+        let mut usage =
+            self.label_usage()
+                .map_err(|e| LoadLabeledConfigTypeError::CouldNotReadLabelUsage {
+                    error: e,
+                    path: usage_path.clone(),
+                })?;
+        usage.set_loaded(label.as_ref(), record.loaded_at());
+        usage.write_to_file(&usage_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotWriteLabelUsage {
+                error: e,
+                path: usage_path,
+            }
+        })
+    }
+
+    /// When the labels were loaded the last time, empty if no label has been loaded yet.
+    pub fn label_usage(&self) -> Result<LabelUsage, ReadJsonFileError> {
+        let usage_path = self.directories.label_usage_path(&self.config_type);
+        if !usage_path.exists() {
+            return Ok(LabelUsage::default());
+        }
+
+        LabelUsage::from_file(usage_path)
+    }
+
+    /// Duplicates `label` as `new_label`. An existing `new_label` is only replaced if `force` is
+    /// set.
+    pub fn copy_label(
+        &self,
+        label: impl AsRef<str>,
+        new_label: impl AsRef<str>,
+        force: bool,
+    ) -> Result<(), CopyLabelError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(CopyLabelError::LabelNotFound(label.as_ref().into()));
+        }
+        if !force
+            && self
+                .backend
+                .has_label(&self.config_type, new_label.as_ref())?
+        {
+            return Err(CopyLabelError::LabelAlreadyExists(
+                new_label.as_ref().into(),
+            ));
+        }
+
+        let files = self.backend.read_label(&self.config_type, label.as_ref())?;
+
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label(
+            &self.config_type,
+            new_label.as_ref(),
+            files,
+            Some(format!("copied from {}", label.as_ref())),
+        );
+        self.backend.commit(transaction)?;
+
+        Ok(())
+    }
+
+    /// Renames `label` to `new_label`. An existing `new_label` is only replaced if `force` is set.
+    pub fn rename_label(
+        &self,
+        label: impl AsRef<str>,
+        new_label: impl AsRef<str>,
+        force: bool,
+    ) -> Result<(), RenameLabelError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(RenameLabelError::LabelNotFound(label.as_ref().into()));
+        }
+        if !force
+            && self
+                .backend
+                .has_label(&self.config_type, new_label.as_ref())?
+        {
+            return Err(RenameLabelError::LabelAlreadyExists(
+                new_label.as_ref().into(),
+            ));
+        }
+
+        // the revisions are renamed with the label, an existing `new_label` is replaced
+        let mut transaction = StorageTransaction::new();
+        transaction.rename_label(&self.config_type, label.as_ref(), new_label.as_ref());
+        self.backend.commit(transaction)?;
+
+        // a replaced `new_label` is gone, like a removed one
+        self.update_loaded_label_record(new_label.as_ref(), None)?;
+        self.update_loaded_label_record(label.as_ref(), Some(new_label.as_ref()))?;
+
+        Ok(())
+    }
+
+    /// Moves the label to the trash. The loaded label is only removed if `force` is set.
+    pub fn remove_label(
+        &self,
+        label: impl AsRef<str>,
+        force: bool,
+    ) -> Result<(), RemoveLabelError> {
+        if !self.backend.has_label(&self.config_type, label.as_ref())? {
+            return Err(RemoveLabelError::LabelNotFound(label.as_ref().into()));
+        }
+
+        if !force {
+            if let Some(record) = self
+                .read_loaded_label_record()
+                .map_err(RemoveLabelError::CouldNotDetermineLoadedLabel)?
+            {
+                if record.label() == label.as_ref() {
+                    return Err(RemoveLabelError::LabelIsLoaded(label.as_ref().into()));
+                }
+            }
+        }
+
+        let trash = Trash::new(self.directories.clone(), self.backend.clone());
+        trash.purge_expired()?;
+        trash.put_label(&self.config_type, label.as_ref())?;
+
+        self.update_loaded_label_record(label.as_ref(), None)?;
+
+        Ok(())
+    }
+
+    /// Points the loaded label record at `new_label` if it refers to `label`, or removes it if
+    /// `new_label` is `None` (i.e. the label was removed).
+    fn update_loaded_label_record(
+        &self,
+        label: &str,
+        new_label: Option<&str>,
+    ) -> Result<(), UpdateLoadedLabelRecordError> {
+        let record_path = self.directories.last_loaded_record_path(&self.config_type);
+        let Some(mut record) = self.read_loaded_label_record().map_err(|e| {
+            UpdateLoadedLabelRecordError::CouldNotReadRecord {
+                error: e,
+                path: record_path.clone(),
+            }
+        })?
+        else {
+            return Ok(());
+        };
+        if record.label() != label {
+            return Ok(());
+        }
+
+        match new_label {
+            Some(new_label) => {
+                record.set_label(new_label);
+                record.write_to_file(&record_path).map_err(|e| {
+                    UpdateLoadedLabelRecordError::CouldNotWriteRecord {
+                        error: e,
+                        path: record_path,
+                    }
+                })
+            }
+            None => std::fs::remove_file(&record_path).map_err(|e| {
+                UpdateLoadedLabelRecordError::CouldNotRemoveRecord {
+                    io_error: e,
+                    path: record_path,
+                }
+            }),
+        }
+    }
+
+    fn read_loaded_label_record(&self) -> Result<Option<LoadedLabelRecord>, ReadJsonFileError> {
+        let record_path = self.directories.last_loaded_record_path(&self.config_type);
+        if !record_path.exists() {
+            return Ok(None);
+        }
+
+        LoadedLabelRecord::from_file(&record_path).map(Some)
+    }
+
+    /// Returns the last loaded label, or `None` if no label has been loaded yet.
+    pub fn which(&self) -> Result<Option<ActiveLabel>, WhichConfigTypeError> {
+        let Some(record) = self.read_loaded_label_record().map_err(|e| {
+            WhichConfigTypeError::CouldNotReadLoadedLabelRecord {
+                error: e,
+                path: self.directories.last_loaded_record_path(&self.config_type),
+            }
+        })?
+        else {
+            return Ok(None);
+        };
+
+        let mut drifted_paths = Vec::new();
+        for (path, hash) in record.file_hashes() {
+            let live_file_path = PathBuf::from(self.variable_resolver.decode_string(path)?);
+            if !live_file_path.exists() {
+                drifted_paths.push(path.clone());
+                continue;
+            }
+
+            let live_hash =
+                hash_path(&live_file_path).map_err(|e| WhichConfigTypeError::CouldNotHashFile {
+                    io_error: e,
+                    path: live_file_path,
+                })?;
+            if &live_hash != hash {
+                drifted_paths.push(path.clone());
+            }
+        }
+
+        Ok(Some(ActiveLabel::new(record, drifted_paths)))
+    }
+
+    /// Compares the live files with `label`, or with the last loaded label (and revision) if
+    /// `label` is `None`.
+    pub fn status(&self, label: Option<&str>) -> Result<LabelStatus, StatusError> {
+        let (label, revision) = match label {
+            Some(label) => (label.to_string(), None),
+            None => {
+                let record = self
+                    .read_loaded_label_record()
+                    .map_err(|e| StatusError::CouldNotReadLoadedLabelRecord {
+                        error: e,
+                        path: self.directories.last_loaded_record_path(&self.config_type),
+                    })?
+                    .ok_or_else(|| StatusError::NoLabelLoaded(self.config_type.clone()))?;
+                (record.label().to_string(), record.revision())
+            }
+        };
+
+        if !self.backend.has_label(&self.config_type, &label)? {
+            return Err(StatusError::LabelNotFound(label));
+        }
+
+        let temp_dir_path = self.directories.create_temp_dir_path();
+        let temp_dir = TempDir::create(&temp_dir_path).map_err(|e| {
+            StatusError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: temp_dir_path,
+            }
+        })?;
+
+        let label_dir_path = temp_dir.path().join("label");
+        self.checkout_label::<StatusError>(&label, revision, &label_dir_path)?;
+
+        let files = self.compare_with_live_files(&label_dir_path, &temp_dir.path().join("live"))?;
+
+        let label = match revision {
+            Some(revision) => format!("{}@{}", label, revision),
+            None => label,
+        };
+        Ok(LabelStatus::new(label, files))
+    }
+
+    /// Shows how the files of `label` differ from `other_label`, or from the live files if
+    /// `other_label` is `None`.
+    pub fn diff(
+        &self,
+        label: impl AsRef<str>,
+        other_label: Option<&str>,
+    ) -> Result<Vec<FileDiff>, DiffError> {
+        for label in [Some(label.as_ref()), other_label].into_iter().flatten() {
+            if !self.backend.has_label(&self.config_type, label)? {
+                return Err(DiffError::LabelNotFound(label.into()));
+            }
+        }
+
+        let temp_dir_path = self.directories.create_temp_dir_path();
+        let temp_dir = TempDir::create(&temp_dir_path).map_err(|e| {
+            DiffError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: temp_dir_path,
+            }
+        })?;
+
+        let label_dir_path = temp_dir.path().join("label");
+        self.checkout_label::<DiffError>(label.as_ref(), None, &label_dir_path)?;
+
+        let other_dir_path = temp_dir.path().join("other");
+        match other_label {
+            Some(other_label) => {
+                self.checkout_label::<DiffError>(other_label, None, &other_dir_path)?
+            }
+            None => self
+                .store_live_files(&other_dir_path)
+                .map_err(|e| DiffError::CouldNotStoreLiveFiles(Box::new(e)))?,
+        }
+
+        diff_directories(
+            label.as_ref(),
+            &label_dir_path,
+            other_label.unwrap_or("live"),
+            &other_dir_path,
+        )
+    }
+
+    /// Stores the live files like a label, so they are encoded (e.g. templates) the same way as
+    /// the files of the stored labels and can be compared with them.
+    fn store_live_files(&self, dir_path: &Path) -> Result<(), StoreLabeledConfigTypeError> {
+        ensure_directory(dir_path, true)?;
+
+        LabeledConfigTypeStorage::new(
+            self.variable_resolver.clone(),
+            self.descriptor.clone(),
+            dir_path,
+        )
+        .skip_missing_files()
+        .store()
+    }
+
+    fn compare_with_live_files(
+        &self,
+        labeled_config_type_dir_path: &Path,
+        live_dir_path: &Path,
+    ) -> Result<Vec<(String, FileState)>, StatusError> {
+        self.store_live_files(live_dir_path)
+            .map_err(|e| StatusError::CouldNotStoreLiveFiles(Box::new(e)))?;
+
+        let collect = |dir: &Path| {
+            collect_files_recursive(dir)
+                .map(|files| {
+                    files
+                        .into_iter()
+                        .filter(|file| {
+                            file.as_path() != Path::new(LABEL_METADATA_FILENAME)
+                                && file.as_path() != Path::new(LABEL_FILE_ATTRIBUTES_FILENAME)
+                        })
+                        .collect::<BTreeSet<_>>()
+                })
+                .map_err(|e| StatusError::CouldNotCollectFiles {
+                    io_error: e,
+                    path: dir.to_path_buf(),
+                })
+        };
+        let label_files = collect(labeled_config_type_dir_path)?;
+        let live_files = collect(live_dir_path)?;
+
+        let hash = |path: PathBuf| {
+            hash_path(&path).map_err(|e| StatusError::CouldNotHashFile { io_error: e, path })
+        };
+
+        let mut files = Vec::new();
+        for relative_file_path in label_files.union(&live_files) {
+            let state = match (
+                label_files.contains(relative_file_path),
+                live_files.contains(relative_file_path),
+            ) {
+                (true, false) => FileState::MissingLive,
+                (false, _) => FileState::MissingInLabel,
+                (true, true) => {
+                    if hash(labeled_config_type_dir_path.join(relative_file_path))?
+                        == hash(live_dir_path.join(relative_file_path))?
+                    {
+                        FileState::Unchanged
+                    } else {
+                        FileState::Modified
+                    }
+                }
+            };
+            files.push((relative_file_path.to_string_lossy().to_string(), state));
+        }
+
+        Ok(files)
+    }
+}
+
+pub(crate) struct LabeledConfigTypeStorage {
+    variable_resolver: Arc<VariableResolver>,
+    descriptor: Arc<ConfigTypeDescriptor>,
+    directory_path: PathBuf,
+    skip_missing_files: bool,
+    removed_paths: Vec<PathBuf>,
+}
+
+impl LabeledConfigTypeStorage {
+    pub fn new(
+        variable_resolver: Arc<VariableResolver>,
+        descriptor: Arc<ConfigTypeDescriptor>,
+        directory_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            variable_resolver,
+            descriptor,
+            directory_path: directory_path.into(),
+            skip_missing_files: false,
+            removed_paths: Vec::new(),
+        }
+    }
+
+    /// Paths of the descriptor which do not exist in the source are skipped instead of failing,
+    /// used for backups, as the live files may not exist yet.
+    pub fn skip_missing_files(mut self) -> Self {
+        self.skip_missing_files = true;
+        self
+    }
+
+    /// Also removes the live files of `paths` (relative to the label, e.g. `{{HOME}}/.gitconfig`)
+    /// on load, unless the label has them, used to undo a load which created them.
+    pub fn remove_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.removed_paths = paths;
+        self
+    }
+
+    /// Returns the paths of the label which do not exist in the live file system, i.e. which are
+    /// created when it is loaded, relative to the label.
+    pub fn absent_live_paths(&self) -> Result<Vec<PathBuf>, LoadLabeledConfigTypeError> {
+        let mut absent_paths = Vec::new();
+        for (path, live_path) in self.descriptor.paths().zip(self.decode_paths()?) {
+            let Some(label_path) = label_relative_path(path.path()) else {
+                continue;
+            };
+            if self.directory_path.join(label_path).exists() && !exists(&live_path) {
+                absent_paths.push(label_path.to_path_buf());
+            }
+        }
+
+        for relative_file_path in self.stored_pattern_files()? {
+            let live_path = self
+                .variable_resolver
+                .decode_string(relative_file_path.to_string_lossy())?;
+            if !exists(Path::new(&live_path)) {
+                absent_paths.push(relative_file_path);
+            }
+        }
+
+        Ok(absent_paths)
+    }
+
+    /// Decodes every path of the descriptor, all the undefined variables are reported at once.
+    fn decode_paths(&self) -> Result<Vec<PathBuf>, DecodeStringError> {
+        let mut decoded_paths = Vec::new();
+        let mut undefined_variable_names = BTreeSet::new();
+        for path in self.descriptor.paths() {
+            match self.variable_resolver.decode_string(path.path()) {
+                Ok(decoded_path) => decoded_paths.push(PathBuf::from(decoded_path)),
+                Err(DecodeStringError::UndefinedVariables(names)) => {
+                    undefined_variable_names.extend(names)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if undefined_variable_names.is_empty() {
+            Ok(decoded_paths)
+        } else {
+            Err(DecodeStringError::UndefinedVariables(
+                undefined_variable_names.into_iter().collect(),
+            ))
+        }
+    }
+
+    /// Copies the live files into the directory, and records their attributes next to them, see
+    /// `LabelFileAttributes`.
+    pub fn store(&self) -> Result<(), StoreLabeledConfigTypeError> {
+        let mut attributes = LabelFileAttributes::new();
+        for (path, decoded_path) in self.descriptor.paths().zip(self.decode_paths()?) {
+            let src_file_path = decoded_path;
+            if self.skip_missing_files && !src_file_path.exists() {
+                continue;
+            }
+
+            let label_path = label_relative_path(path.path()).ok_or_else(|| {
+                StoreLabeledConfigTypeError::PathOutsideOfLabel(path.path().into())
+            })?;
+            let dest_file_path = self.directory_path.join(label_path);
+            if dest_file_path == src_file_path {
+                return Err(StoreLabeledConfigTypeError::SourceIsDestination(
+                    src_file_path,
+                ));
+            }
+
+            log::debug!("dest = {:?}, source = {:?}", dest_file_path, src_file_path);
+
+            let parent_path = dest_file_path.parent().ok_or_else(|| {
+                StoreLabeledConfigTypeError::InvalidParentOfFileLocationInConfigTypeDescriptor(
+                    dest_file_path.clone(),
+                )
+            })?;
+
+            ensure_directory(parent_path, true)?;
+
+            if path.is_template() {
+                self.store_template(&src_file_path, &dest_file_path)?;
+            } else if src_file_path.is_dir() {
+                copy_dir_recursive(&src_file_path, &dest_file_path).map_err(|e| {
+                    StoreLabeledConfigTypeError::CouldNotCopyDirectory {
+                        io_error: e,
+                        source_path: src_file_path.clone(),
+                        dest_path: dest_file_path,
+                    }
+                })?;
+            } else {
+                copy(&src_file_path, &dest_file_path).map_err(|e| {
+                    StoreLabeledConfigTypeError::CouldNotCopyFile {
+                        io_error: e,
+                        source_path: src_file_path.clone(),
+                        dest_path: dest_file_path,
+                    }
+                })?;
+            }
+
+            record_file_attributes(&mut attributes, label_path, &src_file_path)?;
+        }
+
+        self.store_pattern_files(&mut attributes)?;
+
+        let attributes_path = self.directory_path.join(LABEL_FILE_ATTRIBUTES_FILENAME);
+        attributes.write_to_file(&attributes_path).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotWriteFileAttributes {
+                error: e,
+                path: attributes_path,
+            }
+        })
+    }
+
+    fn store_template(
+        &self,
+        src_path: &Path,
+        dest_path: &Path,
+    ) -> Result<(), StoreLabeledConfigTypeError> {
+        if !src_path.is_dir() {
+            store_template_file(src_path, dest_path, &self.variable_resolver)?;
+            return Ok(());
+        }
+
+        let relative_file_paths = collect_files_recursive(src_path).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotCopyDirectory {
+                io_error: e,
+                source_path: src_path.to_path_buf(),
+                dest_path: dest_path.to_path_buf(),
+            }
+        })?;
+        for relative_file_path in relative_file_paths {
+            let dest_file_path = dest_path.join(&relative_file_path);
+            if let Some(parent_path) = dest_file_path.parent() {
+                ensure_directory(parent_path, true)?;
+            }
+
+            store_template_file(
+                src_path.join(&relative_file_path),
+                dest_file_path,
+                &self.variable_resolver,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn store_pattern_files(
+        &self,
+        attributes: &mut LabelFileAttributes,
+    ) -> Result<(), StoreLabeledConfigTypeError> {
+        let patterns = PathPatterns::new(self.descriptor.patterns(), &self.variable_resolver)?;
+
+        for root in patterns.roots() {
+            if !root.decoded().exists() {
+                continue;
+            }
+            let root_label_path = label_relative_path(root.encoded()).ok_or_else(|| {
+                StoreLabeledConfigTypeError::PathOutsideOfLabel(root.encoded().into())
+            })?;
+
+            let relative_file_paths = if root.decoded().is_dir() {
+                collect_files_recursive(root.decoded()).map_err(|e| {
+                    StoreLabeledConfigTypeError::CouldNotCollectPatternFiles {
+                        io_error: e,
+                        path: root.decoded().to_path_buf(),
+                    }
+                })?
+            } else {
+                vec![PathBuf::new()]
+            };
+
+            for relative_file_path in relative_file_paths {
+                let src_file_path = root.decoded().join(&relative_file_path);
+                if !patterns.is_included(&src_file_path) {
+                    continue;
+                }
+
+                let mut label_file_path = root_label_path.to_path_buf();
+                if !relative_file_path.as_os_str().is_empty() {
+                    label_file_path.push(&relative_file_path);
+                }
+                let dest_file_path = self.directory_path.join(&label_file_path);
+                if dest_file_path == src_file_path {
+                    return Err(StoreLabeledConfigTypeError::SourceIsDestination(
+                        src_file_path,
+                    ));
+                }
+
+                let parent_path = dest_file_path.parent().ok_or_else(|| {
+                    StoreLabeledConfigTypeError::InvalidParentOfFileLocationInConfigTypeDescriptor(
+                        dest_file_path.clone(),
+                    )
+                })?;
+
+                ensure_directory(parent_path, true)?;
+
+                copy(&src_file_path, &dest_file_path).map_err(|e| {
+                    StoreLabeledConfigTypeError::CouldNotCopyFile {
+                        io_error: e,
+                        source_path: src_file_path.clone(),
+                        dest_path: dest_file_path,
+                    }
+                })?;
+
+                record_file_attributes(attributes, &label_file_path, &src_file_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the files of the label which are selected by the patterns of the descriptor, as
+    /// paths relative to the label directory (i.e. with variables, like `{{HOME}}/.gitconfig`).
+    pub fn stored_pattern_files(&self) -> Result<Vec<PathBuf>, LoadLabeledConfigTypeError> {
+        let patterns = PathPatterns::new(self.descriptor.patterns(), &self.variable_resolver)?;
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut ret = Vec::new();
+        for relative_file_path in collect_files_recursive(&self.directory_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotCollectPatternFiles {
+                io_error: e,
+                path: self.directory_path.clone(),
+            }
+        })? {
+            let Ok(live_file_path) = self
+                .variable_resolver
+                .decode_string(relative_file_path.to_string_lossy())
+            else {
+                continue;
+            };
+            let live_file_path = PathBuf::from(live_file_path);
+
+            // only the files stored from the live file system have absolute paths once decoded
+            if live_file_path.is_absolute() && patterns.is_included(&live_file_path) {
+                ret.push(relative_file_path);
+            }
+        }
+
+        Ok(ret)
+    }
+
+    fn load_template_dir(
+        &self,
+        src_path: &Path,
+        dest_path: &Path,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        let relative_file_paths = collect_files_recursive(src_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotCopyDirectory {
+                io_error: e,
+                source_path: src_path.to_path_buf(),
+                dest_path: dest_path.to_path_buf(),
+            }
+        })?;
+        for relative_file_path in relative_file_paths {
+            let dest_file_path = dest_path.join(&relative_file_path);
+            if let Some(parent_path) = dest_file_path.parent() {
+                ensure_directory(parent_path, true)?;
+            }
+
+            load_template_file(
+                src_path.join(&relative_file_path),
+                dest_file_path,
+                &self.variable_resolver,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the recorded attributes of the label files, empty if the label was stored before
+    /// they were recorded.
+    fn file_attributes(&self) -> Result<LabelFileAttributes, LoadLabeledConfigTypeError> {
+        let path = self.directory_path.join(LABEL_FILE_ATTRIBUTES_FILENAME);
+        if !path.exists() {
+            return Ok(LabelFileAttributes::new());
+        }
+
+        LabelFileAttributes::from_file(&path)
+            .map_err(|e| LoadLabeledConfigTypeError::CouldNotReadFileAttributes { error: e, path })
+    }
+
+    /// Stages the files of the label in `staging_dir_path`, then replaces the live files with
+    /// them in one transaction, see `LoadTransaction`.
+    pub fn load(
+        &self,
+        staging_dir_path: impl AsRef<Path>,
+    ) -> Result<(), LoadLabeledConfigTypeError> {
+        let mut transaction = LoadTransaction::new(staging_dir_path.as_ref());
+        let attributes = self.file_attributes()?;
+
+        for (path, decoded_path) in self.descriptor.paths().zip(self.decode_paths()?) {
+            let dest_file_path = decoded_path;
+
+            let label_path = label_relative_path(path.path()).ok_or_else(|| {
+                LoadLabeledConfigTypeError::PathOutsideOfLabel(path.path().into())
+            })?;
+            let src_file_path = self.directory_path.join(label_path);
+            if !src_file_path.exists() {
+                if self.skip_missing_files {
+                    continue;
+                }
+                return Err(LoadLabeledConfigTypeError::FileNotFoundInLabel(
+                    src_file_path,
+                ));
+            }
+
+            log::debug!("dest = {:?}, source = {:?}", dest_file_path, src_file_path);
+
+            let staged_path = transaction.next_staged_path();
+            if src_file_path.is_dir() {
+                // overlay starts from the live directory, mirror only keeps what is in the label
+                if path.load_mode() == LoadMode::Overlay && dest_file_path.is_dir() {
+                    copy_dir_recursive(&dest_file_path, &staged_path).map_err(|e| {
+                        LoadLabeledConfigTypeError::CouldNotCopyDirectory {
+                            io_error: e,
+                            source_path: dest_file_path.clone(),
+                            dest_path: staged_path.clone(),
+                        }
+                    })?;
+                }
+
+                if path.is_template() {
+                    self.load_template_dir(&src_file_path, &staged_path)?;
+                } else {
+                    copy_dir_recursive(&src_file_path, &staged_path).map_err(|e| {
+                        LoadLabeledConfigTypeError::CouldNotCopyDirectory {
+                            io_error: e,
+                            source_path: src_file_path.clone(),
+                            dest_path: staged_path.clone(),
+                        }
+                    })?;
+                }
+            } else if path.is_template() {
+                load_template_file(&src_file_path, &staged_path, &self.variable_resolver)?;
+            } else {
+                copy(&src_file_path, &staged_path).map_err(|e| {
+                    LoadLabeledConfigTypeError::CouldNotCopyFile {
+                        io_error: e,
+                        source_path: src_file_path,
+                        dest_path: staged_path.clone(),
+                    }
+                })?;
+            }
+
+            restore_file_attributes(
+                &attributes,
+                label_path,
+                &staged_path,
+                &dest_file_path,
+                path.mode(),
+            )?;
+            transaction.add(staged_path, dest_file_path);
+        }
+
+        for relative_file_path in self.stored_pattern_files()? {
+            let src_file_path = self.directory_path.join(&relative_file_path);
+            let dest_file_path = PathBuf::from(
+                self.variable_resolver
+                    .decode_string(relative_file_path.to_string_lossy())?,
+            );
+            if transaction.contains_destination(&dest_file_path) {
+                continue;
+            }
+
+            let staged_path = transaction.next_staged_path();
+            copy(&src_file_path, &staged_path).map_err(|e| {
+                LoadLabeledConfigTypeError::CouldNotCopyFile {
+                    io_error: e,
+                    source_path: src_file_path,
+                    dest_path: staged_path.clone(),
+                }
+            })?;
+
+            restore_file_attributes(
+                &attributes,
+                &relative_file_path,
+                &staged_path,
+                &dest_file_path,
+                None,
+            )?;
+            transaction.add(staged_path, dest_file_path);
+        }
+
+        for relative_path in &self.removed_paths {
+            let live_path = PathBuf::from(
+                self.variable_resolver
+                    .decode_string(relative_path.to_string_lossy())?,
+            );
+            if exists(&live_path) && !transaction.contains_destination(&live_path) {
+                transaction.add_removal(live_path);
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+/// Returns whether something exists at `path`, without following a symlink.
+fn exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// Writes the live paths a load creates next to the files of a backup, see `restore_live_files`.
+fn write_absent_paths(dir_path: &Path, absent_paths: &[PathBuf]) -> Result<(), BackupError> {
+    let path = dir_path.join(BACKUP_ABSENT_PATHS_FILENAME);
+    write_json_file(&absent_paths, &path)
+        .map_err(|e| BackupError::CouldNotWriteAbsentPaths { error: e, path })
+}
+
+/// Records the attributes of the live file, or of the live directory and every file in it, under
+/// their paths in the label.
+fn record_file_attributes(
+    attributes: &mut LabelFileAttributes,
+    label_path: &Path,
+    live_path: &Path,
+) -> Result<(), StoreLabeledConfigTypeError> {
+    let read = |path: PathBuf| {
+        FileAttributes::read(&path).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotReadFileAttributes { io_error: e, path }
+        })
+    };
+
+    attributes.insert(label_path, read(live_path.to_path_buf())?);
+    if live_path.is_dir() {
+        let relative_file_paths = collect_files_recursive(live_path).map_err(|e| {
+            StoreLabeledConfigTypeError::CouldNotReadFileAttributes {
+                io_error: e,
+                path: live_path.to_path_buf(),
+            }
+        })?;
+        for relative_file_path in relative_file_paths {
+            attributes.insert(
+                label_path.join(&relative_file_path),
+                read(live_path.join(&relative_file_path))?,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Restores the recorded attributes on the staged file, or on the staged directory and every
+/// file in it, before they replace the live files. `mode` replaces the recorded modes of the
+/// files, see `PathEntry::mode`.
+fn restore_file_attributes(
+    attributes: &LabelFileAttributes,
+    label_path: &Path,
+    staged_path: &Path,
+    live_path: &Path,
+    mode: Option<u32>,
+) -> Result<(), LoadLabeledConfigTypeError> {
+    let restore = |label_path: &Path, staged_path: &Path, live_path: &Path, mode| {
+        attributes
+            .get(label_path)
+            .copied()
+            .unwrap_or_default()
+            .restore(staged_path, mode)
+            .map_err(
+                |e| LoadLabeledConfigTypeError::CouldNotRestoreFileAttributes {
+                    io_error: e,
+                    path: live_path.to_path_buf(),
+                },
+            )
+    };
+
+    if staged_path.is_dir() {
+        let relative_file_paths = collect_files_recursive(staged_path).map_err(|e| {
+            LoadLabeledConfigTypeError::CouldNotRestoreFileAttributes {
+                io_error: e,
+                path: live_path.to_path_buf(),
+            }
+        })?;
+        for relative_file_path in relative_file_paths {
+            restore(
+                &label_path.join(&relative_file_path),
+                &staged_path.join(&relative_file_path),
+                &live_path.join(&relative_file_path),
+                mode,
+            )?;
+        }
+
+        // the directory comes last, as writing the files in it changes its modification time
+        return restore(label_path, staged_path, live_path, None);
+    }
+
+    restore(label_path, staged_path, live_path, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use directories::BaseDirs;
+
+    use super::*;
+    use crate::{
+        directories::LABEL_MANIFEST_FILENAME,
+        error::{AddPathError, CreateProfileError, GetProfileError},
+        load_transaction::tests::other_filesystem_dir,
+        memory_storage_backend::MemoryStorageBackend,
+    };
+
+    /// A storage with the labels in memory, and everything else in a temp directory which also
+    /// holds the live files, under the `{{LIVE}}` variable.
+    struct TestStorage {
+        dir: TempDir,
+        root_dir: TempDir,
+        storage: ConfigStorage,
+    }
+
+    impl TestStorage {
+        fn new() -> Self {
+            Self::with_settings("{}")
+        }
+
+        fn with_settings(settings: &str) -> Self {
+            Self::with_root(settings, &std::env::temp_dir())
+        }
+
+        /// Creates the storage root in `root_parent_path`, e.g. on another filesystem than the
+        /// live files.
+        fn with_root(settings: &str, root_parent_path: &Path) -> Self {
+            Self::create(settings, root_parent_path, |_| {
+                Arc::new(MemoryStorageBackend::new())
+            })
+        }
+
+        /// Keeps the labels in the storage root too, see `FsStorageBackend`.
+        fn with_fs_backend() -> Self {
+            Self::create("{}", &std::env::temp_dir(), |root_dir_path| {
+                Arc::new(FsStorageBackend::new(root_dir_path).unwrap())
+            })
+        }
+
+        fn create(
+            settings: &str,
+            root_parent_path: &Path,
+            backend: impl FnOnce(&Path) -> Arc<dyn StorageBackend>,
+        ) -> Self {
+            let dir = TempDir::create(
+                std::env::temp_dir().join(format!("config-picker-test-{}", uuid::Uuid::new_v4())),
+            )
+            .unwrap();
+            let root_dir = TempDir::create(
+                root_parent_path.join(format!("config-picker-test-{}", uuid::Uuid::new_v4())),
+            )
+            .unwrap();
+            fs::create_dir(dir.path().join("live")).unwrap();
+            fs::write(root_dir.path().join("settings.json"), settings).unwrap();
+
+            let mut variable_resolver = VariableResolver::new(BaseDirs::new().unwrap());
+            variable_resolver.add_user_variables(BTreeMap::from([(
+                "LIVE".to_string(),
+                dir.path().join("live").to_string_lossy().to_string(),
+            )]));
+            let storage = ConfigStorage::with_backend(
+                variable_resolver,
+                root_dir.path(),
+                backend(root_dir.path()),
+            )
+            .unwrap();
+
+            Self {
+                dir,
+                root_dir,
+                storage,
+            }
+        }
+
+        fn live_path(&self, path: &str) -> PathBuf {
+            self.dir.path().join("live").join(path)
+        }
+
+        fn write_live(&self, path: &str, content: &str) {
+            let live_path = self.live_path(path);
+            fs::create_dir_all(live_path.parent().unwrap()).unwrap();
+            fs::write(live_path, content).unwrap();
+        }
+
+        fn read_live(&self, path: &str) -> String {
+            fs::read_to_string(self.live_path(path)).unwrap()
+        }
+
+        /// Creates a config type with the live file as its only path.
+        fn create_config_type(&self, config_type: &str, path: &str) -> ConfigTypeStorage {
+            self.write_live(path, "initial");
+            let mut config_type_storage = self.storage.create_config_type(config_type).unwrap();
+            config_type_storage
+                .add_path(self.live_path(path), LoadMode::Overlay, false, None)
+                .unwrap();
+            config_type_storage
+        }
+    }
+
+    #[test]
+    fn add_path_rejects_path_without_variable() {
+        let test_storage = TestStorage::new();
+        let outside_path = test_storage.dir.path().join("outside.txt");
+        fs::write(&outside_path, "content").unwrap();
+
+        let mut config_type_storage = test_storage.storage.create_config_type("t").unwrap();
+        let result = config_type_storage.add_path(&outside_path, LoadMode::Overlay, false, None);
+
+        assert!(matches!(result, Err(AddPathError::PathNotUnderVariable(_))));
+        assert_eq!(fs::read_to_string(&outside_path).unwrap(), "content");
+    }
+
+    #[test]
+    fn store_does_not_touch_absolute_descriptor_path() {
+        let test_storage = TestStorage::new();
+        let outside_path = test_storage.dir.path().join("outside.txt");
+        fs::write(&outside_path, "content").unwrap();
+
+        // e.g. written into the descriptor by hand
+        let mut config_type_storage = test_storage.storage.create_config_type("t").unwrap();
+        let mut descriptor = (*config_type_storage.descriptor).clone();
+        descriptor.add_path(PathEntry::new(
+            outside_path.to_string_lossy(),
+            LoadMode::Overlay,
+            false,
+            None,
+        ));
+        config_type_storage.set_descriptor(descriptor).unwrap();
+
+        let result = config_type_storage.store("l1", None);
+
+        assert!(matches!(
+            result,
+            Err(StoreLabeledConfigTypeError::PathOutsideOfLabel(_))
+        ));
+        assert_eq!(fs::read_to_string(&outside_path).unwrap(), "content");
+    }
+
+    #[test]
+    fn backups_of_encrypted_config_type_are_encrypted() {
+        let test_storage = TestStorage::new();
+        let mut config_type_storage = test_storage.create_config_type("t", "secret.txt");
+        config_type_storage
+            .rekey(
+                NewEncryption::KeyFile(test_storage.dir.path().join("key.txt")),
+                false,
+            )
+            .unwrap();
+        test_storage.write_live("secret.txt", "stored secret");
+        config_type_storage.store("l1", None).unwrap();
+
+        test_storage.write_live("secret.txt", "live secret");
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("secret.txt"), "stored secret");
+
+        let backups_dir_path = test_storage.storage.directories.backups_dir_path("t");
+        for path in collect_files_recursive(&backups_dir_path).unwrap() {
+            let content = fs::read(backups_dir_path.join(path)).unwrap();
+            assert!(!String::from_utf8_lossy(&content).contains("live secret"));
+        }
+
+        config_type_storage.undo().unwrap();
+        assert_eq!(test_storage.read_live("secret.txt"), "live secret");
+    }
+
+    #[test]
+    fn plaintext_label_file_of_encrypted_config_type_is_rejected() {
+        let test_storage = TestStorage::new();
+        let key_file = test_storage.dir.path().join("key.txt");
+        let mut config_type_storage = test_storage.create_config_type("t", "secret.txt");
+        config_type_storage
+            .rekey(NewEncryption::KeyFile(key_file.clone()), false)
+            .unwrap();
+        config_type_storage.store("l1", None).unwrap();
+
+        // e.g. pulled from a repository someone else can push to
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label_file("t", "l1", "{{LIVE}}/secret.txt", b"replaced".to_vec());
+        test_storage.storage.backend.commit(transaction).unwrap();
+
+        assert!(matches!(
+            config_type_storage.load("l1"),
+            Err(LoadLabeledConfigTypeError::CouldNotDecrypt(
+                EncryptionError::NotEncrypted(_)
+            ))
+        ));
+        assert_eq!(test_storage.read_live("secret.txt"), "initial");
+        assert!(matches!(
+            config_type_storage.rekey(NewEncryption::KeyFile(key_file.clone()), false),
+            Err(RekeyError::CouldNotDecrypt(EncryptionError::NotEncrypted(
+                _
+            )))
+        ));
+
+        config_type_storage
+            .rekey(NewEncryption::KeyFile(key_file), true)
+            .unwrap();
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("secret.txt"), "replaced");
+    }
+
+    #[test]
+    fn unchanged_encrypted_files_are_not_encrypted_again() {
+        let test_storage = TestStorage::new();
+        let mut config_type_storage = test_storage.create_config_type("t", "secret.txt");
+        config_type_storage
+            .rekey(
+                NewEncryption::KeyFile(test_storage.dir.path().join("key.txt")),
+                false,
+            )
+            .unwrap();
+        let read_stored = || {
+            test_storage
+                .storage
+                .backend
+                .read_label_file("t", "l1", Path::new("{{LIVE}}/secret.txt"))
+                .unwrap()
+                .unwrap()
+        };
+
+        config_type_storage.store("l1", None).unwrap();
+        let stored = read_stored();
+        config_type_storage.store("l1", None).unwrap();
+        assert_eq!(read_stored(), stored);
+
+        test_storage.write_live("secret.txt", "changed");
+        config_type_storage.store("l1", None).unwrap();
+        assert_ne!(read_stored(), stored);
+    }
+
+    #[test]
+    fn failed_profile_load_restores_members_without_backups() {
+        let test_storage = TestStorage::with_settings(r#"{"backup_retention": 0}"#);
+        let config_type_storage_a = test_storage.create_config_type("a", "a.txt");
+        config_type_storage_a.store("l1", None).unwrap();
+        test_storage.write_live("a.txt", "current");
+
+        let mut config_type_storage_b = test_storage.create_config_type("b", "b.txt");
+        config_type_storage_b
+            .rekey(
+                NewEncryption::KeyFile(test_storage.dir.path().join("key.txt")),
+                false,
+            )
+            .unwrap();
+        config_type_storage_b.store("l1", None).unwrap();
+        let mut transaction = StorageTransaction::new();
+        transaction.put_label_file("b", "l1", "{{LIVE}}/b.txt", b"replaced".to_vec());
+        test_storage.storage.backend.commit(transaction).unwrap();
+
+        test_storage
+            .storage
+            .create_profile(
+                "p",
+                BTreeMap::from([
+                    ("a".to_string(), "l1".to_string()),
+                    ("b".to_string(), "l1".to_string()),
+                ]),
+            )
+            .unwrap();
+        let result = test_storage.storage.load_profile("p");
+
+        assert!(matches!(
+            result,
+            Err(LoadProfileError::CouldNotLoadConfigType { config_type, .. }) if config_type == "b"
+        ));
+        assert_eq!(test_storage.read_live("a.txt"), "current");
+        assert!(config_type_storage_a.which().unwrap().is_none());
+    }
+
+    #[test]
+    fn profile_names_with_paths_are_rejected() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+        let members = BTreeMap::from([("t".to_string(), "l1".to_string())]);
+
+        for profile in ["../escape", "a/b", "..", ""] {
+            assert!(matches!(
+                test_storage
+                    .storage
+                    .create_profile(profile, members.clone()),
+                Err(CreateProfileError::InvalidProfileName(_))
+            ));
+            assert!(matches!(
+                test_storage.storage.get_profile(profile),
+                Err(GetProfileError::InvalidProfileName(_))
+            ));
+        }
+        test_storage
+            .storage
+            .create_profile("work.laptop", members)
+            .unwrap();
+        test_storage
+            .storage
+            .store_profile("work.laptop", Some("l2"))
+            .unwrap();
+        assert_eq!(test_storage.storage.profiles().unwrap(), ["work.laptop"]);
+        assert_eq!(
+            test_storage
+                .storage
+                .get_profile("work.laptop")
+                .unwrap()
+                .members()["t"],
+            "l2"
+        );
+
+        // the filesystem backend rejects them as well
+        let backend = FsStorageBackend::new(test_storage.root_dir.path()).unwrap();
+        let mut transaction = StorageTransaction::new();
+        transaction.put_profile("../escape", b"{}".to_vec());
+        assert!(matches!(
+            backend.commit(transaction),
+            Err(StorageBackendError::InvalidName(_))
+        ));
+        assert!(!test_storage.root_dir.path().join("escape.json").exists());
+        assert_eq!(backend.profiles().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn store_and_load_round_trip() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        test_storage.write_live("app.toml", "a = 1");
+        config_type_storage.store("l1", None).unwrap();
+        test_storage.write_live("app.toml", "a = 2");
+        config_type_storage.store("l2", None).unwrap();
+
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+        assert_eq!(
+            config_type_storage
+                .which()
+                .unwrap()
+                .unwrap()
+                .record()
+                .label(),
+            "l1"
+        );
+
+        config_type_storage.load("l2").unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 2");
+        config_type_storage.undo().unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+    }
+
+    #[test]
+    fn labels_are_loaded_and_undone_with_the_root_on_another_filesystem() {
+        let Some(other_filesystem_dir) = other_filesystem_dir() else {
+            eprintln!("skipped, there is no other filesystem for the root");
+            return;
+        };
+        let test_storage = TestStorage::with_root("{}", &other_filesystem_dir);
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        test_storage.write_live("app.toml", "a = 1");
+        config_type_storage.store("l1", None).unwrap();
+        test_storage.write_live("app.toml", "a = 2");
+        config_type_storage.store("l2", None).unwrap();
+
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+        config_type_storage.undo().unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 2");
+        assert_eq!(fs::read_dir(test_storage.live_path("")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn files_created_by_a_load_are_removed_on_undo() {
+        let test_storage = TestStorage::new();
+        let mut config_type_storage = test_storage.create_config_type("t", "app.toml");
+        test_storage.write_live("conf.d/extra.toml", "b = 1");
+        config_type_storage
+            .add_path(
+                test_storage.live_path("conf.d"),
+                LoadMode::Mirror,
+                false,
+                None,
+            )
+            .unwrap();
+        config_type_storage.store("l1", None).unwrap();
+        fs::remove_file(test_storage.live_path("app.toml")).unwrap();
+        fs::remove_dir_all(test_storage.live_path("conf.d")).unwrap();
+
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "initial");
+        assert_eq!(test_storage.read_live("conf.d/extra.toml"), "b = 1");
+        config_type_storage.undo().unwrap();
+        assert!(!test_storage.live_path("app.toml").exists());
+        assert!(!test_storage.live_path("conf.d").exists());
+    }
+
+    #[test]
+    fn revisions_are_kept_and_reverted() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        test_storage.write_live("app.toml", "a = 1");
+        config_type_storage.store("l1", Some("first")).unwrap();
+        test_storage.write_live("app.toml", "a = 2");
+        config_type_storage.store("l1", None).unwrap();
+
+        let history = config_type_storage.history("l1").unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(LabelRevision::number)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(history[0].message(), Some("first"));
+
+        config_type_storage.load_revision("l1", 1).unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+        assert!(matches!(
+            config_type_storage.load_revision("l1", 3),
+            Err(LoadLabeledConfigTypeError::RevisionNotFound { revision: 3, .. })
+        ));
+
+        assert_eq!(config_type_storage.revert("l1", 1).unwrap().number(), 3);
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+    }
+
+    #[test]
+    fn unchanged_labels_are_not_stored_again() {
+        for test_storage in [TestStorage::new(), TestStorage::with_fs_backend()] {
+            let config_type_storage = test_storage.create_config_type("t", "app.toml");
+            config_type_storage.store("l1", None).unwrap();
+            config_type_storage.store("l1", Some("again")).unwrap();
+            assert_eq!(config_type_storage.history("l1").unwrap().len(), 1);
+
+            test_storage.write_live("app.toml", "a = 1");
+            config_type_storage.store("l1", None).unwrap();
+            assert_eq!(config_type_storage.history("l1").unwrap().len(), 2);
+        }
+
+        let test_storage = TestStorage::with_fs_backend();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+        let label_dir_path = test_storage.root_dir.path().join("db").join("t").join("l1");
+        let modified = |path: PathBuf| fs::metadata(path).unwrap().modified().unwrap();
+        let manifest_modified = modified(label_dir_path.join(LABEL_MANIFEST_FILENAME));
+
+        config_type_storage.store("l1", None).unwrap();
+        assert_eq!(
+            modified(label_dir_path.join(LABEL_MANIFEST_FILENAME)),
+            manifest_modified
+        );
+    }
+
+    #[test]
+    fn removed_labels_and_config_types_are_restored_from_the_trash() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+
+        config_type_storage.remove_label("l1", false).unwrap();
+        assert!(matches!(
+            config_type_storage.load("l1"),
+            Err(LoadLabeledConfigTypeError::LabelNotFound(_))
+        ));
+        let entry = test_storage.storage.undelete("t", Some("l1")).unwrap();
+        assert_eq!(entry.label(), Some("l1"));
+        assert!(test_storage.storage.trash_entries().unwrap().is_empty());
+
+        test_storage.storage.remove_config_type("t", false).unwrap();
+        assert!(test_storage.storage.get_config_type_storage("t").is_err());
+        test_storage.storage.undelete("t", None).unwrap();
+
+        test_storage.write_live("app.toml", "changed");
+        test_storage
+            .storage
+            .get_config_type_storage("t")
+            .unwrap()
+            .load("l1")
+            .unwrap();
+        assert_eq!(test_storage.read_live("app.toml"), "initial");
+    }
+
+    #[test]
+    fn labels_are_restored_from_the_trash_with_their_history() {
+        for test_storage in [TestStorage::new(), TestStorage::with_fs_backend()] {
+            let config_type_storage = test_storage.create_config_type("t", "app.toml");
+            test_storage.write_live("app.toml", "a = 1");
+            config_type_storage.store("l1", Some("first")).unwrap();
+            test_storage.write_live("app.toml", "a = 2");
+            config_type_storage.store("l1", None).unwrap();
+            let history = |config_type_storage: &ConfigTypeStorage| {
+                config_type_storage
+                    .history("l1")
+                    .unwrap()
+                    .iter()
+                    .map(|revision| {
+                        (
+                            revision.number(),
+                            revision.created_at(),
+                            revision.message().map(String::from),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let stored_history = history(&config_type_storage);
+
+            config_type_storage.remove_label("l1", false).unwrap();
+            test_storage.storage.undelete("t", Some("l1")).unwrap();
+            assert_eq!(history(&config_type_storage), stored_history);
+
+            test_storage.storage.remove_config_type("t", false).unwrap();
+            test_storage.storage.undelete("t", None).unwrap();
+            let config_type_storage = test_storage.storage.get_config_type_storage("t").unwrap();
+            assert_eq!(history(&config_type_storage), stored_history);
+
+            config_type_storage.load_revision("l1", 1).unwrap();
+            assert_eq!(test_storage.read_live("app.toml"), "a = 1");
+            config_type_storage.load("l1").unwrap();
+            assert_eq!(test_storage.read_live("app.toml"), "a = 2");
+        }
+    }
+
+    #[test]
+    fn import_rejects_existing_labels_and_other_encryption() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+        let archive_path = test_storage.dir.path().join("t.tar.gz");
+        test_storage
+            .storage
+            .export(
+                "t",
+                &["l1".to_string()],
+                &archive_path,
+                ArchiveFormat::TarGz,
+            )
+            .unwrap();
+
+        assert!(matches!(
+            test_storage
+                .storage
+                .import(&archive_path, &BTreeMap::new(), false),
+            Err(ImportError::LabelsAlreadyExist { .. })
+        ));
+        let summary = test_storage
+            .storage
+            .import(
+                &archive_path,
+                &BTreeMap::from([("l1".to_string(), "l2".to_string())]),
+                false,
+            )
+            .unwrap();
+        assert_eq!(summary.imported_labels()["l1"], "l2");
+
+        let mut config_type_storage = test_storage.storage.get_config_type_storage("t").unwrap();
+        config_type_storage
+            .rekey(
+                NewEncryption::KeyFile(test_storage.dir.path().join("key.txt")),
+                false,
+            )
+            .unwrap();
+        assert!(matches!(
+            test_storage.storage.import(
+                &archive_path,
+                &BTreeMap::from([("l1".to_string(), "l3".to_string())]),
+                false,
+            ),
+            Err(ImportError::DifferentEncryption(_))
+        ));
+    }
+
+    #[test]
+    fn encrypted_labels_are_not_loaded_with_another_key() {
+        let test_storage = TestStorage::new();
+        let key_file = test_storage.dir.path().join("key.txt");
+        let mut config_type_storage = test_storage.create_config_type("t", "secret.txt");
+        config_type_storage
+            .rekey(NewEncryption::KeyFile(key_file.clone()), false)
+            .unwrap();
+        test_storage.write_live("secret.txt", "password");
+        config_type_storage.store("l1", None).unwrap();
+        let stored = test_storage
+            .storage
+            .backend
+            .read_label_file("t", "l1", Path::new("{{LIVE}}/secret.txt"))
+            .unwrap()
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("password"));
+
+        test_storage.write_live("secret.txt", "changed");
+        let config_type_storage = test_storage.storage.get_config_type_storage("t").unwrap();
+        config_type_storage.load("l1").unwrap();
+        assert_eq!(test_storage.read_live("secret.txt"), "password");
+
+        fs::remove_file(&key_file).unwrap();
+        LabelCipher::new_key_file(&key_file, "key.txt").unwrap();
+        test_storage.write_live("secret.txt", "changed");
+        let config_type_storage = test_storage.storage.get_config_type_storage("t").unwrap();
+        assert!(matches!(
+            config_type_storage.load("l1"),
+            Err(LoadLabeledConfigTypeError::CouldNotDecrypt(
+                EncryptionError::WrongKey { .. }
+            ))
+        ));
+        assert_eq!(test_storage.read_live("secret.txt"), "changed");
+    }
+
+    #[test]
+    fn interrupted_deletions_are_purged_from_the_trash() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+        config_type_storage.remove_label("l1", false).unwrap();
+
+        let trash_dir_path = test_storage.root_dir.path().join("trash");
+        let interrupted_dir_path = trash_dir_path.join("interrupted");
+        let running_dir_path = trash_dir_path.join("running");
+        fs::create_dir_all(interrupted_dir_path.join("data")).unwrap();
+        fs::create_dir_all(running_dir_path.join("data")).unwrap();
+        filetime::set_file_mtime(
+            &interrupted_dir_path,
+            filetime::FileTime::from_unix_time(Utc::now().timestamp() - 2 * 24 * 3600, 0),
+        )
+        .unwrap();
+
+        assert_eq!(test_storage.storage.trash_entries().unwrap().len(), 1);
+        assert!(!interrupted_dir_path.exists());
+        assert!(running_dir_path.exists());
+    }
+
+    #[test]
+    fn renaming_and_removing_the_loaded_label_updates_the_record() {
+        let test_storage = TestStorage::new();
+        let config_type_storage = test_storage.create_config_type("t", "app.toml");
+        config_type_storage.store("l1", None).unwrap();
+        config_type_storage.load("l1").unwrap();
+        let loaded_label = || {
+            config_type_storage
+                .which()
+                .unwrap()
+                .map(|active_label| active_label.record().label().to_string())
+        };
+
+        config_type_storage.rename_label("l1", "l2", false).unwrap();
+        assert_eq!(loaded_label().as_deref(), Some("l2"));
+
+        config_type_storage.store("l3", None).unwrap();
+        config_type_storage.rename_label("l3", "l2", true).unwrap();
+        assert_eq!(loaded_label(), None);
+
+        config_type_storage.load("l2").unwrap();
+        config_type_storage.remove_label("l2", true).unwrap();
+        assert_eq!(loaded_label(), None);
     }
 }
@@ -1,21 +1,151 @@
-use std::{
-    fs::File,
-    io::Write,
-    path::{Path, PathBuf},
-};
+use std::{fs::File, io::Write, path::Path};
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::{ReadConfigTypeDescriptorError, WriteConfigTypeDescriptorError};
+use crate::{
+    error::{ReadConfigTypeDescriptorError, WriteConfigTypeDescriptorError},
+    file_attributes::serde_mode,
+    label_encryption::Encryption,
+};
+
+/// How the stored copy of a directory is applied to the live directory on load.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadMode {
+    /// Files of the label are copied over the live directory, other live files are kept.
+    #[default]
+    Overlay,
+
+    /// Like overlay, but live files which are not in the label are deleted.
+    Mirror,
+}
+
+/// A file or directory of a config type.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "PathEntryRepr", into = "PathEntryRepr")]
+pub struct PathEntry {
+    path: String,
+    load_mode: LoadMode,
+    template: bool,
+    mode: Option<u32>,
+}
+
+impl PathEntry {
+    pub fn new(
+        path: impl Into<String>,
+        load_mode: LoadMode,
+        template: bool,
+        mode: Option<u32>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            load_mode,
+            template,
+            mode,
+        }
+    }
+
+    /// The location of the file or directory, may contain variables (e.g. `{{HOME}}`).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
 
+    pub fn load_mode(&self) -> LoadMode {
+        self.load_mode
+    }
+
+    /// Whether variable values in the content of the text files are replaced with variables on
+    /// store and expanded again on load.
+    pub fn is_template(&self) -> bool {
+        self.template
+    }
+
+    /// The permission bits the files are loaded with instead of the stored ones (e.g. `0o600`
+    /// for private files). For a directory, they apply to the files in it.
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+}
+
+/// Entries without options are written as plain strings, so simple descriptors stay simple.
 #[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PathEntryRepr {
+    Path(String),
+    Entry {
+        path: String,
+
+        #[serde(default)]
+        load_mode: LoadMode,
+
+        #[serde(default)]
+        template: bool,
+
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_mode")]
+        mode: Option<u32>,
+    },
+}
+
+impl From<PathEntryRepr> for PathEntry {
+    fn from(value: PathEntryRepr) -> Self {
+        match value {
+            PathEntryRepr::Path(path) => Self::new(path, LoadMode::default(), false, None),
+            PathEntryRepr::Entry {
+                path,
+                load_mode,
+                template,
+                mode,
+            } => Self::new(path, load_mode, template, mode),
+        }
+    }
+}
+
+impl From<PathEntry> for PathEntryRepr {
+    fn from(value: PathEntry) -> Self {
+        if value.load_mode == LoadMode::default() && !value.template && value.mode.is_none() {
+            Self::Path(value.path)
+        } else {
+            Self::Entry {
+                path: value.path,
+                load_mode: value.load_mode,
+                template: value.template,
+                mode: value.mode,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ConfigTypeDescriptor {
-    paths: Vec<String>,
+    paths: Vec<PathEntry>,
+
+    /// Gitignore-like patterns, see `PathPatterns`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    patterns: Vec<String>,
+
+    /// How the label files are encrypted, `None` if they are not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption: Option<Encryption>,
+
+    /// Patterns read from the `.pickerinclude` and `.pickerignore` files next to the descriptor.
+    #[serde(skip)]
+    file_patterns: Vec<String>,
+}
+
+impl Default for ConfigTypeDescriptor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConfigTypeDescriptor {
     pub fn new() -> Self {
-        Self { paths: Vec::new() }
+        Self {
+            paths: Vec::new(),
+            patterns: Vec::new(),
+            encryption: None,
+            file_patterns: Vec::new(),
+        }
     }
 
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadConfigTypeDescriptorError> {
@@ -23,6 +153,14 @@ impl ConfigTypeDescriptor {
         Ok(serde_json::from_reader(file)?)
     }
 
+    pub fn from_slice(content: &[u8]) -> Result<Self, ReadConfigTypeDescriptorError> {
+        Ok(serde_json::from_slice(content)?)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, WriteConfigTypeDescriptorError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
     pub fn write_to_file(
         &self,
         path: impl AsRef<Path>,
@@ -38,7 +176,69 @@ impl ConfigTypeDescriptor {
         Ok(())
     }
 
-    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
-        self.paths.iter().map(PathBuf::from)
+    pub fn paths(&self) -> impl Iterator<Item = &PathEntry> + '_ {
+        self.paths.iter()
+    }
+
+    /// The inline patterns followed by the patterns of the pattern files.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> + '_ {
+        self.patterns
+            .iter()
+            .chain(self.file_patterns.iter())
+            .map(|s| s.as_str())
+    }
+
+    /// Takes the patterns of the pattern files over from `other`, e.g. after the descriptor has
+    /// been edited.
+    pub fn copy_file_patterns_from(&mut self, other: &ConfigTypeDescriptor) {
+        self.file_patterns = other.file_patterns.clone();
+    }
+
+    /// Lines of the ignore file are negated, so they exclude what the include lines selected.
+    pub fn set_file_patterns(
+        &mut self,
+        include_lines: impl IntoIterator<Item = String>,
+        ignore_lines: impl IntoIterator<Item = String>,
+    ) {
+        self.file_patterns = include_lines
+            .into_iter()
+            .chain(ignore_lines.into_iter().map(|line| {
+                let trimmed_line = line.trim();
+                if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
+                    line
+                } else if let Some(pattern) = trimmed_line.strip_prefix('!') {
+                    pattern.to_string()
+                } else {
+                    format!("!{}", trimmed_line)
+                }
+            }))
+            .collect();
+    }
+
+    pub fn encryption(&self) -> Option<&Encryption> {
+        self.encryption.as_ref()
+    }
+
+    /// Only changes the descriptor, the stored label files are re-encrypted by
+    /// `ConfigTypeStorage::rekey`.
+    pub fn set_encryption(&mut self, encryption: Option<Encryption>) {
+        self.encryption = encryption;
+    }
+
+    /// Returns `false` if the path is already in the descriptor.
+    pub fn add_path(&mut self, entry: PathEntry) -> bool {
+        if self.paths.iter().any(|p| p.path == entry.path) {
+            false
+        } else {
+            self.paths.push(entry);
+            true
+        }
+    }
+
+    /// Returns `false` if the path is not in the descriptor.
+    pub fn remove_path(&mut self, path: impl AsRef<str>) -> bool {
+        let len = self.paths.len();
+        self.paths.retain(|p| p.path != path.as_ref());
+        self.paths.len() != len
     }
 }
@@ -1,11 +1,36 @@
 use std::path::{Path, PathBuf};
 
-const DESCRIPTOR_FILENAME: &str = "descriptor.json";
+pub const DESCRIPTOR_FILENAME: &str = "descriptor.json";
+pub const INCLUDE_PATTERNS_FILENAME: &str = ".pickerinclude";
+pub const IGNORE_PATTERNS_FILENAME: &str = ".pickerignore";
+/// Backups used to be kept next to the labels, so the name is still not a label.
+const LEGACY_BACKUPS_DIRNAME: &str = "_backup";
+const BACKUPS_DIRNAME: &str = "backups";
+pub const BACKUP_RECORD_FILENAME: &str = "last_loaded.json";
+/// The live paths which did not exist when a backup was made, removed again by undo.
+pub const BACKUP_ABSENT_PATHS_FILENAME: &str = "absent_paths.json";
+/// Metadata of a label, stored next to the files of the label.
+pub const LABEL_METADATA_FILENAME: &str = "label.json";
+/// Modes, times and owners of the files of a label, stored next to them.
+pub const LABEL_FILE_ATTRIBUTES_FILENAME: &str = "file_attributes.json";
+/// Lists the files of a label and the objects holding their contents.
+pub const LABEL_MANIFEST_FILENAME: &str = "manifest.json";
+/// Holds one file per revision of a label, next to its manifest.
+pub const LABEL_HISTORY_DIRNAME: &str = "history";
+const SETTINGS_FILENAME: &str = "settings.json";
+const USER_VARIABLES_FILENAME: &str = "variables.json";
+const LAST_LOADED_RECORD_FILENAME: &str = "last_loaded.json";
+const LABEL_USAGE_FILENAME: &str = "label_usage.json";
+const PROFILE_FILE_EXTENSION: &str = "json";
 
 pub struct Directories {
     root_dir: PathBuf,
     temp_dir: PathBuf,
     db_dir: PathBuf,
+    state_dir: PathBuf,
+    trash_dir: PathBuf,
+    profiles_dir: PathBuf,
+    objects_dir: PathBuf,
 }
 
 impl Directories {
@@ -18,10 +43,26 @@ impl Directories {
         let mut root_db_dir = root_dir.clone();
         root_db_dir.push("db");
 
+        let mut root_state_dir = root_dir.clone();
+        root_state_dir.push("state");
+
+        let mut root_trash_dir = root_dir.clone();
+        root_trash_dir.push("trash");
+
+        let mut root_profiles_dir = root_dir.clone();
+        root_profiles_dir.push("profiles");
+
+        let mut root_objects_dir = root_dir.clone();
+        root_objects_dir.push("objects");
+
         Self {
             root_dir,
             db_dir: root_db_dir,
             temp_dir: root_temp_dir,
+            state_dir: root_state_dir,
+            trash_dir: root_trash_dir,
+            profiles_dir: root_profiles_dir,
+            objects_dir: root_objects_dir,
         }
     }
 
@@ -29,6 +70,18 @@ impl Directories {
         &self.root_dir
     }
 
+    pub fn settings_path(&self) -> PathBuf {
+        let mut ret = self.root_dir.clone();
+        ret.push(SETTINGS_FILENAME);
+        ret
+    }
+
+    pub fn user_variables_path(&self) -> PathBuf {
+        let mut ret = self.root_dir.clone();
+        ret.push(USER_VARIABLES_FILENAME);
+        ret
+    }
+
     pub fn db_dir_path(&self) -> &Path {
         &self.db_dir
     }
@@ -37,6 +90,72 @@ impl Directories {
         &self.temp_dir
     }
 
+    pub fn state_dir_path(&self) -> &Path {
+        &self.state_dir
+    }
+
+    pub fn trash_dir_path(&self) -> &Path {
+        &self.trash_dir
+    }
+
+    pub fn profiles_dir_path(&self) -> &Path {
+        &self.profiles_dir
+    }
+
+    pub fn objects_dir_path(&self) -> &Path {
+        &self.objects_dir
+    }
+
+    /// Objects are spread over sub directories named after the first two characters of their id.
+    pub fn object_path(&self, id: impl AsRef<str>) -> PathBuf {
+        let id = id.as_ref();
+        let mut ret = self.objects_dir.clone();
+        ret.push(&id[..2]);
+        ret.push(&id[2..]);
+        ret
+    }
+
+    /// The extension is appended, so a dot in the name is kept (e.g. `work.laptop.json`).
+    pub fn profile_path(&self, profile: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.profiles_dir.clone();
+        ret.push(format!("{}.{}", profile.as_ref(), PROFILE_FILE_EXTENSION));
+        ret
+    }
+
+    /// Returns the name of the profile stored at `path`, if it is a profile file.
+    pub fn profile_name(path: impl AsRef<Path>) -> Option<String> {
+        let path = path.as_ref();
+        if path.extension()? != PROFILE_FILE_EXTENSION {
+            return None;
+        }
+
+        Some(path.file_stem()?.to_string_lossy().to_string())
+    }
+
+    pub fn trash_entry_dir_path(&self, id: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.trash_dir.clone();
+        ret.push(id.as_ref());
+        ret
+    }
+
+    pub fn config_type_state_dir_path(&self, config_type: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.state_dir.clone();
+        ret.push(config_type.as_ref());
+        ret
+    }
+
+    pub fn last_loaded_record_path(&self, config_type: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.config_type_state_dir_path(config_type);
+        ret.push(LAST_LOADED_RECORD_FILENAME);
+        ret
+    }
+
+    pub fn label_usage_path(&self, config_type: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.config_type_state_dir_path(config_type);
+        ret.push(LABEL_USAGE_FILENAME);
+        ret
+    }
+
     pub fn config_type_dir_path(&self, config_type: impl AsRef<str>) -> PathBuf {
         let mut ret = self.db_dir.clone();
         ret.push(config_type.as_ref());
@@ -54,13 +173,28 @@ impl Directories {
         ret
     }
 
-    pub fn config_type_descriptor_path(&self, config_type: impl AsRef<str>) -> PathBuf {
-        let mut ret = self.db_dir.clone();
-        ret.push(config_type.as_ref());
-        ret.push(DESCRIPTOR_FILENAME);
+    pub fn backups_dir_path(&self, config_type: impl AsRef<str>) -> PathBuf {
+        let mut ret = self.config_type_state_dir_path(config_type);
+        ret.push(BACKUPS_DIRNAME);
+        ret
+    }
+
+    pub fn backup_dir_path(
+        &self,
+        config_type: impl AsRef<str>,
+        backup: impl AsRef<str>,
+    ) -> PathBuf {
+        let mut ret = self.backups_dir_path(config_type);
+        ret.push(backup.as_ref());
         ret
     }
 
+    /// Label directories are the sub directories of the config type directory, except for the
+    /// ones used by the tool itself.
+    pub fn is_reserved_label_name(name: impl AsRef<str>) -> bool {
+        name.as_ref() == LEGACY_BACKUPS_DIRNAME
+    }
+
     pub fn create_temp_dir_path(&self) -> PathBuf {
         let mut tmp_dir = self.temp_dir.clone();
         tmp_dir.push(uuid::Uuid::new_v4().as_hyphenated().to_string());
@@ -3,7 +3,70 @@
 use std::path::PathBuf;
 
 use thiserror::Error;
-use variable_resolver::error::DecodeStringError;
+
+/// A stable code of an error, reported to scripts by the JSON output. The codes are the small
+/// vocabulary of `codes`, which does not depend on the names of the error types. An error caused
+/// by another error of the crate mostly reports the code of that one, e.g. `io_error` for a file
+/// which could not be read.
+pub trait ErrorCode {
+    fn code(&self) -> &'static str;
+}
+
+pub mod codes {
+    /// An argument is not valid, e.g. a label name with a path separator.
+    pub const INVALID_ARGUMENT: &str = "invalid_argument";
+    /// A path or a template uses a variable which is not defined.
+    pub const UNDEFINED_VARIABLE: &str = "undefined_variable";
+    pub const CONFIG_TYPE_NOT_FOUND: &str = "config_type_not_found";
+    pub const CONFIG_TYPE_ALREADY_EXISTS: &str = "config_type_already_exists";
+    pub const CONFIG_TYPE_IS_LOADED: &str = "config_type_is_loaded";
+    pub const LABEL_NOT_FOUND: &str = "label_not_found";
+    pub const LABEL_ALREADY_EXISTS: &str = "label_already_exists";
+    pub const LABEL_IS_LOADED: &str = "label_is_loaded";
+    pub const NO_LABEL_LOADED: &str = "no_label_loaded";
+    pub const REVISION_NOT_FOUND: &str = "revision_not_found";
+    pub const PROFILE_NOT_FOUND: &str = "profile_not_found";
+    pub const PROFILE_ALREADY_EXISTS: &str = "profile_already_exists";
+    /// A file or directory does not exist.
+    pub const PATH_NOT_FOUND: &str = "path_not_found";
+    /// A file or directory already exists.
+    pub const PATH_ALREADY_EXISTS: &str = "path_already_exists";
+    pub const TRASH_ENTRY_NOT_FOUND: &str = "trash_entry_not_found";
+    pub const BACKUP_NOT_FOUND: &str = "backup_not_found";
+    pub const PASSPHRASE_REQUIRED: &str = "passphrase_required";
+    /// The passphrase or the key file does not match the encrypted files.
+    pub const WRONG_KEY: &str = "wrong_key";
+    /// The config type is not encrypted.
+    pub const NOT_ENCRYPTED: &str = "not_encrypted";
+    /// Files could not be encrypted or decrypted.
+    pub const ENCRYPTION_ERROR: &str = "encryption_error";
+    /// The storage root is not a git repository.
+    pub const NOT_A_REPOSITORY: &str = "not_a_repository";
+    /// The storage root is already a git repository.
+    pub const ALREADY_A_REPOSITORY: &str = "already_a_repository";
+    /// A git command failed.
+    pub const GIT_FAILED: &str = "git_failed";
+    /// Stored or imported data cannot be read, e.g. a broken descriptor or archive.
+    pub const INVALID_DATA: &str = "invalid_data";
+    /// A load failed and the original files could not all be put back.
+    pub const ROLLBACK_FAILED: &str = "rollback_failed";
+    pub const PERMISSION_DENIED: &str = "permission_denied";
+    /// Any other error of the file system or of a terminal.
+    pub const IO_ERROR: &str = "io_error";
+    /// An error which is not caused by the input, e.g. data which cannot be serialized.
+    pub const INTERNAL_ERROR: &str = "internal_error";
+}
+
+impl ErrorCode for std::io::Error {
+    fn code(&self) -> &'static str {
+        match self.kind() {
+            std::io::ErrorKind::NotFound => codes::PATH_NOT_FOUND,
+            std::io::ErrorKind::AlreadyExists => codes::PATH_ALREADY_EXISTS,
+            std::io::ErrorKind::PermissionDenied => codes::PERMISSION_DENIED,
+            _ => codes::IO_ERROR,
+        }
+    }
+}
 
 #[derive(Debug, Error)]
 pub enum EnsureDirectoryError {
@@ -22,6 +85,16 @@ pub enum EnsureDirectoryError {
     PathDoesNotExist(PathBuf),
 }
 
+impl ErrorCode for EnsureDirectoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::PathIsNotADirectory(..) => codes::IO_ERROR,
+            Self::CouldNotCreateDirectory { error, .. } => error.code(),
+            Self::PathDoesNotExist(..) => codes::PATH_NOT_FOUND,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum CreateNewDirectoryError {
     #[error("could not create directory, path = \"{path}\"")]
@@ -36,34 +109,120 @@ pub enum CreateNewDirectoryError {
     PathAlreadyExists(PathBuf),
 }
 
+impl ErrorCode for CreateNewDirectoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotCreateDirectory { error, .. } => error.code(),
+            Self::PathAlreadyExists(..) => codes::PATH_ALREADY_EXISTS,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ConfigStorageConstructionError {
     #[error("invalid storage path = \"{0}\"")]
     InvalidStoragePath(PathBuf),
+
+    #[error("could not read settings, path = {path}, error = {error}")]
+    CouldNotReadSettings {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not write default settings, path = {path}, error = {error}")]
+    CouldNotWriteSettings {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not read user defined variables, path = {path}, error = {error}")]
+    CouldNotReadUserVariables {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for ConfigStorageConstructionError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidStoragePath(..) => codes::INVALID_ARGUMENT,
+            Self::CouldNotReadSettings { error, .. } => error.code(),
+            Self::CouldNotWriteSettings { error, .. } => error.code(),
+            Self::CouldNotReadUserVariables { error, .. } => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeStringError {
+    #[error("undefined variables, variable names = {}", .0.join(", "))]
+    UndefinedVariables(Vec<String>),
+
+    #[error("invalid template, error = {0}")]
+    InvalidTemplate(
+        #[source]
+        #[from]
+        variable_resolver::error::DecodeStringError,
+    ),
+}
+
+impl ErrorCode for DecodeStringError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::UndefinedVariables(..) => codes::UNDEFINED_VARIABLE,
+            Self::InvalidTemplate(..) => codes::INVALID_DATA,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum IterConfigTypesError {
-    #[error("io error = {0}")]
-    IoError(
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
         #[source]
         #[from]
-        std::io::Error,
+        StorageBackendError,
     ),
 }
 
+impl ErrorCode for IterConfigTypesError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum IterConfigTypeLabelsError {
-    #[error("io error = {0}")]
-    IoError(
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
         #[source]
         #[from]
-        std::io::Error,
+        StorageBackendError,
     ),
 }
 
+impl ErrorCode for IterConfigTypeLabelsError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum StoreLabeledConfigTypeError {
+    #[error("could not encrypt label files, error = {0}")]
+    CouldNotEncrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
     #[error("could not create temp directory, path = {path}, error = {io_error}")]
     CouldNotCreateTempDirectory {
         #[source]
@@ -71,25 +230,51 @@ pub enum StoreLabeledConfigTypeError {
         path: PathBuf,
     },
 
+    #[error("could not read label metadata, label = \"{label}\", error = {error}")]
+    CouldNotReadLabelMetadata {
+        #[source]
+        error: ReadJsonFileError,
+        label: String,
+    },
+
+    #[error("could not write label metadata, label = \"{label}\", error = {error}")]
+    CouldNotWriteLabelMetadata {
+        #[source]
+        error: WriteJsonFileError,
+        label: String,
+    },
+
     #[error("could not decode file location in config type descriptor, error = {0}")]
     CouldNotDecodeFileLocationInConfigTypeDescriptor(#[source] DecodeStringError),
 
     #[error("file location in config file descriptor does not contain a valid parent directory, path = {0}")]
     InvalidParentOfFileLocationInConfigTypeDescriptor(PathBuf),
 
-    #[error("could not remove old directory, path = {path}, error = {io_error}")]
-    CouldNotRemoveOldDirectory {
+    #[error("path in config type descriptor does not start with a variable (e.g. {{{{HOME}}}}), so it cannot be kept in a label, path = \"{0}\"")]
+    PathOutsideOfLabel(String),
+
+    #[error("file would be copied onto itself, path = {0}")]
+    SourceIsDestination(PathBuf),
+
+    #[error("could not read staged files, path = {path}, error = {io_error}")]
+    CouldNotReadStagedFiles {
         #[source]
         io_error: std::io::Error,
         path: PathBuf,
     },
 
-    #[error("could not rename temp directory, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
-    CouldNotRenameTempDirectory {
+    #[error("could not read file attributes, path = {path}, error = {io_error}")]
+    CouldNotReadFileAttributes {
         #[source]
         io_error: std::io::Error,
-        source_path: PathBuf,
-        dest_path: PathBuf,
+        path: PathBuf,
+    },
+
+    #[error("could not write file attributes, path = {path}, error = {error}")]
+    CouldNotWriteFileAttributes {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
     },
 
     #[error("could not copy file, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
@@ -100,8 +285,70 @@ pub enum StoreLabeledConfigTypeError {
         dest_path: PathBuf,
     },
 
+    #[error("could not copy directory, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotCopyDirectory {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("invalid path pattern in config type descriptor, error = {0}")]
+    InvalidPathPattern(
+        #[source]
+        #[from]
+        PathPatternError,
+    ),
+
+    #[error(
+        "could not collect files matching the path patterns, path = {path}, error = {io_error}"
+    )]
+    CouldNotCollectPatternFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not copy template file, error = {0}")]
+    CouldNotCopyTemplateFile(
+        #[source]
+        #[from]
+        TemplateFileError,
+    ),
+
     #[error("could not create directory, error = {0}")]
     CouldNotCreateDirectory(#[source] EnsureDirectoryError),
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for StoreLabeledConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotEncrypt(error) => error.code(),
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotReadLabelMetadata { error, .. } => error.code(),
+            Self::CouldNotWriteLabelMetadata { error, .. } => error.code(),
+            Self::CouldNotDecodeFileLocationInConfigTypeDescriptor(error) => error.code(),
+            Self::InvalidParentOfFileLocationInConfigTypeDescriptor(..) => codes::INVALID_DATA,
+            Self::PathOutsideOfLabel(..) => codes::INVALID_DATA,
+            Self::SourceIsDestination(..) => codes::INVALID_DATA,
+            Self::CouldNotReadStagedFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotReadFileAttributes { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteFileAttributes { error, .. } => error.code(),
+            Self::CouldNotCopyFile { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyDirectory { io_error, .. } => io_error.code(),
+            Self::InvalidPathPattern(error) => error.code(),
+            Self::CouldNotCollectPatternFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyTemplateFile(error) => error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
 }
 
 impl From<DecodeStringError> for StoreLabeledConfigTypeError {
@@ -118,11 +365,76 @@ impl From<EnsureDirectoryError> for StoreLabeledConfigTypeError {
 
 #[derive(Debug, Error)]
 pub enum LoadLabeledConfigTypeError {
+    #[error("could not decrypt label files, error = {0}")]
+    CouldNotDecrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
     #[error("could not decode file location in config type descriptor, error = {0}")]
     CouldNotDecodeFileLocationInConfigTypeDescriptor(#[source] DecodeStringError),
 
-    #[error("file location in config file descriptor does not contain a valid parent directory, path = {0}")]
-    InvalidParentOfFileLocationInConfigTypeDescriptor(PathBuf),
+    #[error("path in config type descriptor does not start with a variable (e.g. {{{{HOME}}}}), so it cannot be kept in a label, path = \"{0}\"")]
+    PathOutsideOfLabel(String),
+
+    #[error("could not back up the live files, error = {0}")]
+    CouldNotCreateBackup(
+        #[source]
+        #[from]
+        BackupError,
+    ),
+
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("revision not found, label = \"{label}\", revision = {revision}")]
+    RevisionNotFound { label: String, revision: u32 },
+
+    #[error("could not hash file, path = {path}, error = {io_error}")]
+    CouldNotHashFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not create directory for loaded label record, error = {0}")]
+    CouldNotCreateStateDirectory(#[source] EnsureDirectoryError),
+
+    #[error("could not write loaded label record, path = {path}, error = {error}")]
+    CouldNotWriteLoadedLabelRecord {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not read label usage, path = {path}, error = {error}")]
+    CouldNotReadLabelUsage {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not write label usage, path = {path}, error = {error}")]
+    CouldNotWriteLabelUsage {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not read file attributes of label, path = {path}, error = {error}")]
+    CouldNotReadFileAttributes {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not restore file attributes, path = {path}, error = {io_error}")]
+    CouldNotRestoreFileAttributes {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
 
     #[error("could not copy file, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
     CouldNotCopyFile {
@@ -132,8 +444,92 @@ pub enum LoadLabeledConfigTypeError {
         dest_path: PathBuf,
     },
 
+    #[error("could not copy directory, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotCopyDirectory {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("file not found in label, path = {0}")]
+    FileNotFoundInLabel(PathBuf),
+
+    #[error("could not replace the live files, error = {0}")]
+    CouldNotSwapFiles(
+        #[source]
+        #[from]
+        LoadTransactionError,
+    ),
+
+    #[error("invalid path pattern in config type descriptor, error = {0}")]
+    InvalidPathPattern(
+        #[source]
+        #[from]
+        PathPatternError,
+    ),
+
+    #[error(
+        "could not collect files matching the path patterns, path = {path}, error = {io_error}"
+    )]
+    CouldNotCollectPatternFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not copy template file, error = {0}")]
+    CouldNotCopyTemplateFile(
+        #[source]
+        #[from]
+        TemplateFileError,
+    ),
+
     #[error("could not create directory, error = {0}")]
     CouldNotCreateDirectory(#[source] EnsureDirectoryError),
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for LoadLabeledConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotDecrypt(error) => error.code(),
+            Self::CouldNotDecodeFileLocationInConfigTypeDescriptor(error) => error.code(),
+            Self::PathOutsideOfLabel(..) => codes::INVALID_DATA,
+            Self::CouldNotCreateBackup(error) => error.code(),
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::RevisionNotFound { .. } => codes::REVISION_NOT_FOUND,
+            Self::CouldNotHashFile { io_error, .. } => io_error.code(),
+            Self::CouldNotCreateStateDirectory(error) => error.code(),
+            Self::CouldNotWriteLoadedLabelRecord { error, .. } => error.code(),
+            Self::CouldNotReadLabelUsage { error, .. } => error.code(),
+            Self::CouldNotWriteLabelUsage { error, .. } => error.code(),
+            Self::CouldNotReadFileAttributes { error, .. } => error.code(),
+            Self::CouldNotRestoreFileAttributes { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyFile { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::FileNotFoundInLabel(..) => codes::PATH_NOT_FOUND,
+            Self::CouldNotSwapFiles(error) => error.code(),
+            Self::InvalidPathPattern(error) => error.code(),
+            Self::CouldNotCollectPatternFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyTemplateFile(error) => error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
 }
 
 impl From<DecodeStringError> for LoadLabeledConfigTypeError {
@@ -150,28 +546,30 @@ impl From<EnsureDirectoryError> for LoadLabeledConfigTypeError {
 
 #[derive(Debug, Error)]
 pub enum ConfigTypeDirValidationError {
-    #[error("config type dir does not exist, path = \"{path}\"")]
-    DirectoryNotFound { path: PathBuf },
+    #[error("config type not found = \"{0}\"")]
+    ConfigTypeNotFound(String),
 
-    #[error("given path is not a directory, path = \"{path}\"")]
-    GivenPathIsNotADirectory { path: PathBuf },
+    #[error("descriptor not found, location = {0}")]
+    DescriptorNotFound(String),
 
     #[error("could not read descriptor, error = {0}")]
     CouldNotReadDescriptor(ReadConfigTypeDescriptorError),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
 }
 
-impl From<EnsureDirectoryError> for ConfigTypeDirValidationError {
-    fn from(value: EnsureDirectoryError) -> Self {
-        match value {
-            EnsureDirectoryError::CouldNotCreateDirectory { path, .. } => {
-                ConfigTypeDirValidationError::GivenPathIsNotADirectory { path }
-            }
-            EnsureDirectoryError::PathDoesNotExist(path) => {
-                ConfigTypeDirValidationError::DirectoryNotFound { path }
-            }
-            EnsureDirectoryError::PathIsNotADirectory(path) => {
-                ConfigTypeDirValidationError::GivenPathIsNotADirectory { path }
-            }
+impl ErrorCode for ConfigTypeDirValidationError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::ConfigTypeNotFound(..) => codes::CONFIG_TYPE_NOT_FOUND,
+            Self::DescriptorNotFound(..) => codes::INVALID_DATA,
+            Self::CouldNotReadDescriptor(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
         }
     }
 }
@@ -195,22 +593,29 @@ pub enum GetConfigTypeError {
     },
 }
 
+impl ErrorCode for GetConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::IncorrectConfigTypeDir {
+                validation_error, ..
+            } => validation_error.code(),
+            Self::ConfigTypeNotFound { .. } => codes::CONFIG_TYPE_NOT_FOUND,
+        }
+    }
+}
+
 impl<T: Into<String>> From<(T, ConfigTypeDirValidationError)> for GetConfigTypeError {
     fn from((config_type, validation_error): (T, ConfigTypeDirValidationError)) -> Self {
         match &validation_error {
-            ConfigTypeDirValidationError::DirectoryNotFound { .. } => {
+            ConfigTypeDirValidationError::ConfigTypeNotFound(_) => {
                 GetConfigTypeError::ConfigTypeNotFound {
                     config_type: config_type.into(),
                     validation_error,
                 }
             }
-            ConfigTypeDirValidationError::GivenPathIsNotADirectory { .. } => {
-                GetConfigTypeError::IncorrectConfigTypeDir {
-                    config_type: config_type.into(),
-                    validation_error,
-                }
-            }
-            ConfigTypeDirValidationError::CouldNotReadDescriptor(_) => {
+            ConfigTypeDirValidationError::DescriptorNotFound(_)
+            | ConfigTypeDirValidationError::CouldNotReadDescriptor(_)
+            | ConfigTypeDirValidationError::CouldNotAccessStorage(_) => {
                 GetConfigTypeError::IncorrectConfigTypeDir {
                     config_type: config_type.into(),
                     validation_error,
@@ -233,18 +638,28 @@ pub enum CreateConfigTypeError {
     #[error("config type already exists, config type = \"{config_type}\"")]
     ConfigTypeAlreadyExists { config_type: String },
 
-    #[error("could not create directory, path = \"{path}\"")]
-    CouldNotCreateDirectory {
-        path: PathBuf,
-
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
         #[source]
-        new_directory_error: CreateNewDirectoryError,
-    },
+        #[from]
+        StorageBackendError,
+    ),
 
     #[error("could not write descriptor to file, error = {0}")]
     CouldNotWriteDescriptorToFile(WriteConfigTypeDescriptorError),
 }
 
+impl ErrorCode for CreateConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::IncorrectConfigTypeFound { .. } => codes::CONFIG_TYPE_ALREADY_EXISTS,
+            Self::ConfigTypeAlreadyExists { .. } => codes::CONFIG_TYPE_ALREADY_EXISTS,
+            Self::CouldNotAccessStorage(error) => error.code(),
+            Self::CouldNotWriteDescriptorToFile(error) => error.code(),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum WriteConfigTypeDescriptorError {
     #[error("could open file")]
@@ -259,6 +674,24 @@ pub enum WriteConfigTypeDescriptorError {
         #[from]
         serde_json::Error,
     ),
+
+    #[error("could not write to storage, error = {0}")]
+    CouldNotWriteToStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for WriteConfigTypeDescriptorError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotOpenFile(error) => error.code(),
+            Self::CouldNotWriteDataToFile(error) => error.code(),
+            Self::CouldNotSerializeData(..) => codes::INTERNAL_ERROR,
+            Self::CouldNotWriteToStorage(error) => error.code(),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -273,3 +706,1724 @@ pub enum ReadConfigTypeDescriptorError {
         serde_json::Error,
     ),
 }
+
+impl ErrorCode for ReadConfigTypeDescriptorError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotOpenFile(error) => error.code(),
+            Self::CouldNotDeserializeData(..) => codes::INVALID_DATA,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WriteJsonFileError {
+    #[error("could open file")]
+    CouldNotOpenFile(#[source] std::io::Error),
+
+    #[error("could not write data to file")]
+    CouldNotWriteDataToFile(#[source] std::io::Error),
+
+    #[error("could not serialize data")]
+    CouldNotSerializeData(
+        #[source]
+        #[from]
+        serde_json::Error,
+    ),
+}
+
+impl ErrorCode for WriteJsonFileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotOpenFile(error) => error.code(),
+            Self::CouldNotWriteDataToFile(error) => error.code(),
+            Self::CouldNotSerializeData(..) => codes::INTERNAL_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReadJsonFileError {
+    #[error("could open file")]
+    CouldNotOpenFile(#[source] std::io::Error),
+
+    #[error("could not deserialize data")]
+    CouldNotDeserializeData(
+        #[source]
+        #[from]
+        serde_json::Error,
+    ),
+}
+
+impl ErrorCode for ReadJsonFileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotOpenFile(error) => error.code(),
+            Self::CouldNotDeserializeData(..) => codes::INVALID_DATA,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WhichConfigTypeError {
+    #[error("could not read loaded label record, path = {path}, error = {error}")]
+    CouldNotReadLoadedLabelRecord {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not decode file location in loaded label record, error = {0}")]
+    CouldNotDecodeFileLocation(#[source] DecodeStringError),
+
+    #[error("could not hash file, path = {path}, error = {io_error}")]
+    CouldNotHashFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for WhichConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotReadLoadedLabelRecord { error, .. } => error.code(),
+            Self::CouldNotDecodeFileLocation(error) => error.code(),
+            Self::CouldNotHashFile { io_error, .. } => io_error.code(),
+        }
+    }
+}
+
+impl From<DecodeStringError> for WhichConfigTypeError {
+    fn from(value: DecodeStringError) -> Self {
+        Self::CouldNotDecodeFileLocation(value)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StatusError {
+    #[error("could not decrypt label files, error = {0}")]
+    CouldNotDecrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
+    #[error("could not read loaded label record, path = {path}, error = {error}")]
+    CouldNotReadLoadedLabelRecord {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("no label has been loaded yet, config type = \"{0}\"")]
+    NoLabelLoaded(String),
+
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not read live files, error = {0}")]
+    CouldNotStoreLiveFiles(#[source] Box<StoreLabeledConfigTypeError>),
+
+    #[error("could not collect files, path = {path}, error = {io_error}")]
+    CouldNotCollectFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not hash file, path = {path}, error = {io_error}")]
+    CouldNotHashFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for StatusError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotDecrypt(error) => error.code(),
+            Self::CouldNotReadLoadedLabelRecord { error, .. } => error.code(),
+            Self::NoLabelLoaded(..) => codes::NO_LABEL_LOADED,
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotStoreLiveFiles(error) => error.code(),
+            Self::CouldNotCollectFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotHashFile { io_error, .. } => io_error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DiffError {
+    #[error("could not decrypt label files, error = {0}")]
+    CouldNotDecrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not read live files, error = {0}")]
+    CouldNotStoreLiveFiles(#[source] Box<StoreLabeledConfigTypeError>),
+
+    #[error("could not collect files, path = {path}, error = {io_error}")]
+    CouldNotCollectFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read file, path = {path}, error = {io_error}")]
+    CouldNotReadFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for DiffError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotDecrypt(error) => error.code(),
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotStoreLiveFiles(error) => error.code(),
+            Self::CouldNotCollectFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotReadFile { io_error, .. } => io_error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CreateProfileError {
+    #[error("invalid profile name, profile = \"{0}\"")]
+    InvalidProfileName(String),
+
+    #[error("profile already exists, profile = \"{0}\"")]
+    ProfileAlreadyExists(String),
+
+    #[error("profile has no config types, profile = \"{0}\"")]
+    NoMembers(String),
+
+    #[error("could not get config type of profile, error = {0}")]
+    CouldNotGetConfigType(
+        #[source]
+        #[from]
+        GetConfigTypeError,
+    ),
+
+    #[error("could not write profile, profile = \"{profile}\", error = {error}")]
+    CouldNotWriteProfile {
+        #[source]
+        error: WriteJsonFileError,
+        profile: String,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for CreateProfileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidProfileName(..) => codes::INVALID_ARGUMENT,
+            Self::ProfileAlreadyExists(..) => codes::PROFILE_ALREADY_EXISTS,
+            Self::NoMembers(..) => codes::INVALID_ARGUMENT,
+            Self::CouldNotGetConfigType(error) => error.code(),
+            Self::CouldNotWriteProfile { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GetProfileError {
+    #[error("invalid profile name, profile = \"{0}\"")]
+    InvalidProfileName(String),
+
+    #[error("profile not found, profile = \"{0}\"")]
+    ProfileNotFound(String),
+
+    #[error("could not read profile, profile = \"{profile}\", error = {error}")]
+    CouldNotReadProfile {
+        #[source]
+        error: ReadJsonFileError,
+        profile: String,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for GetProfileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidProfileName(..) => codes::INVALID_ARGUMENT,
+            Self::ProfileNotFound(..) => codes::PROFILE_NOT_FOUND,
+            Self::CouldNotReadProfile { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ListProfilesError {
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for ListProfilesError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StoreProfileError {
+    #[error("could not get profile, error = {0}")]
+    CouldNotGetProfile(
+        #[source]
+        #[from]
+        GetProfileError,
+    ),
+
+    #[error("could not get config type of profile, error = {0}")]
+    CouldNotGetConfigType(
+        #[source]
+        #[from]
+        GetConfigTypeError,
+    ),
+
+    #[error("could not store config type, config type = \"{config_type}\", error = {error}")]
+    CouldNotStoreConfigType {
+        #[source]
+        error: StoreLabeledConfigTypeError,
+        config_type: String,
+    },
+
+    #[error("could not write profile, profile = \"{profile}\", error = {error}")]
+    CouldNotWriteProfile {
+        #[source]
+        error: WriteJsonFileError,
+        profile: String,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for StoreProfileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotGetProfile(error) => error.code(),
+            Self::CouldNotGetConfigType(error) => error.code(),
+            Self::CouldNotStoreConfigType { error, .. } => error.code(),
+            Self::CouldNotWriteProfile { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoadProfileError {
+    #[error("could not get profile, error = {0}")]
+    CouldNotGetProfile(
+        #[source]
+        #[from]
+        GetProfileError,
+    ),
+
+    #[error("could not get config type of profile, error = {0}")]
+    CouldNotGetConfigType(
+        #[source]
+        #[from]
+        GetConfigTypeError,
+    ),
+
+    #[error("label not found, config type = \"{config_type}\", label = \"{label}\"")]
+    LabelNotFound { config_type: String, label: String },
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not load config type, the config types loaded before were restored, config type = \"{config_type}\", error = {error}")]
+    CouldNotLoadConfigType {
+        #[source]
+        error: LoadLabeledConfigTypeError,
+        config_type: String,
+    },
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for LoadProfileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotGetProfile(error) => error.code(),
+            Self::CouldNotGetConfigType(error) => error.code(),
+            Self::LabelNotFound { .. } => codes::LABEL_NOT_FOUND,
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotLoadConfigType { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LabelMetadataError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("could not read label metadata, label = \"{label}\", error = {error}")]
+    CouldNotReadMetadata {
+        #[source]
+        error: ReadJsonFileError,
+        label: String,
+    },
+
+    #[error("could not write label metadata, label = \"{label}\", error = {error}")]
+    CouldNotWriteMetadata {
+        #[source]
+        error: WriteJsonFileError,
+        label: String,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for LabelMetadataError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::CouldNotReadMetadata { error, .. } => error.code(),
+            Self::CouldNotWriteMetadata { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LabelHistoryError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for LabelHistoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RevertLabelError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("revision not found, label = \"{label}\", revision = {revision}")]
+    RevisionNotFound { label: String, revision: u32 },
+
+    #[error("could not read label metadata, label = \"{label}\", error = {error}")]
+    CouldNotReadLabelMetadata {
+        #[source]
+        error: ReadJsonFileError,
+        label: String,
+    },
+
+    #[error("could not write label metadata, label = \"{label}\", error = {error}")]
+    CouldNotWriteLabelMetadata {
+        #[source]
+        error: WriteJsonFileError,
+        label: String,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for RevertLabelError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::RevisionNotFound { .. } => codes::REVISION_NOT_FOUND,
+            Self::CouldNotReadLabelMetadata { error, .. } => error.code(),
+            Self::CouldNotWriteLabelMetadata { error, .. } => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CopyLabelError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("label already exists, label = \"{0}\"")]
+    LabelAlreadyExists(String),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for CopyLabelError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::LabelAlreadyExists(..) => codes::LABEL_ALREADY_EXISTS,
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RenameLabelError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("label already exists, label = \"{0}\"")]
+    LabelAlreadyExists(String),
+
+    #[error("could not update the loaded label record, error = {0}")]
+    CouldNotUpdateLoadedLabelRecord(
+        #[source]
+        #[from]
+        UpdateLoadedLabelRecordError,
+    ),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for RenameLabelError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::LabelAlreadyExists(..) => codes::LABEL_ALREADY_EXISTS,
+            Self::CouldNotUpdateLoadedLabelRecord(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TrashError {
+    #[error("could not create trash entry directory, error = {0}")]
+    CouldNotCreateEntryDirectory(
+        #[source]
+        #[from]
+        CreateNewDirectoryError,
+    ),
+
+    #[error("could not read directory, path = {path}, error = {io_error}")]
+    CouldNotReadDirectory {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read trash entry, path = {path}, error = {error}")]
+    CouldNotReadEntry {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not write trash entry, path = {path}, error = {error}")]
+    CouldNotWriteEntry {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not move directory, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotMoveDirectory {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not remove directory, path = {path}, error = {io_error}")]
+    CouldNotRemoveDirectory {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("no deleted entry found in trash, config type = \"{config_type}\", label = {label:?}")]
+    EntryNotFound {
+        config_type: String,
+        label: Option<String>,
+    },
+
+    #[error("could not write files, path = {path}, error = {io_error}")]
+    CouldNotWriteFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("config type of the deleted label does not exist, config type = \"{0}\"")]
+    ConfigTypeNotFound(String),
+
+    #[error("config type already exists, config type = \"{0}\"")]
+    ConfigTypeAlreadyExists(String),
+
+    #[error("label already exists, config type = \"{config_type}\", label = \"{label}\"")]
+    LabelAlreadyExists { config_type: String, label: String },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for TrashError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotCreateEntryDirectory(error) => error.code(),
+            Self::CouldNotReadDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotReadEntry { error, .. } => error.code(),
+            Self::CouldNotWriteEntry { error, .. } => error.code(),
+            Self::CouldNotMoveDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotRemoveDirectory { io_error, .. } => io_error.code(),
+            Self::EntryNotFound { .. } => codes::TRASH_ENTRY_NOT_FOUND,
+            Self::CouldNotWriteFiles { io_error, .. } => io_error.code(),
+            Self::ConfigTypeNotFound(..) => codes::CONFIG_TYPE_NOT_FOUND,
+            Self::ConfigTypeAlreadyExists(..) => codes::CONFIG_TYPE_ALREADY_EXISTS,
+            Self::LabelAlreadyExists { .. } => codes::LABEL_ALREADY_EXISTS,
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UpdateLoadedLabelRecordError {
+    #[error("could not read loaded label record, path = {path}, error = {error}")]
+    CouldNotReadRecord {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not write loaded label record, path = {path}, error = {error}")]
+    CouldNotWriteRecord {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+
+    #[error("could not remove loaded label record, path = {path}, error = {io_error}")]
+    CouldNotRemoveRecord {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for UpdateLoadedLabelRecordError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotReadRecord { error, .. } => error.code(),
+            Self::CouldNotWriteRecord { error, .. } => error.code(),
+            Self::CouldNotRemoveRecord { io_error, .. } => io_error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RemoveLabelError {
+    #[error("label not found, label = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("label is currently loaded, use --force to remove it anyway, label = \"{0}\"")]
+    LabelIsLoaded(String),
+
+    #[error("could not determine the currently loaded label, error = {0}")]
+    CouldNotDetermineLoadedLabel(#[source] ReadJsonFileError),
+
+    #[error("could not move label to trash, error = {0}")]
+    CouldNotMoveToTrash(
+        #[source]
+        #[from]
+        TrashError,
+    ),
+
+    #[error("could not update the loaded label record, error = {0}")]
+    CouldNotUpdateLoadedLabelRecord(
+        #[source]
+        #[from]
+        UpdateLoadedLabelRecordError,
+    ),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for RemoveLabelError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::LabelIsLoaded(..) => codes::LABEL_IS_LOADED,
+            Self::CouldNotDetermineLoadedLabel(error) => error.code(),
+            Self::CouldNotMoveToTrash(error) => error.code(),
+            Self::CouldNotUpdateLoadedLabelRecord(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RemoveConfigTypeError {
+    #[error("could not get config type, error = {0}")]
+    CouldNotGetConfigType(
+        #[source]
+        #[from]
+        GetConfigTypeError,
+    ),
+
+    #[error("config type has a loaded label, use --force to remove it anyway, config type = \"{config_type}\", label = \"{label}\"")]
+    ConfigTypeIsLoaded { config_type: String, label: String },
+
+    #[error("could not determine the currently loaded label, error = {0}")]
+    CouldNotDetermineLoadedLabel(#[source] ReadJsonFileError),
+
+    #[error("could not move config type to trash, error = {0}")]
+    CouldNotMoveToTrash(
+        #[source]
+        #[from]
+        TrashError,
+    ),
+}
+
+impl ErrorCode for RemoveConfigTypeError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotGetConfigType(error) => error.code(),
+            Self::ConfigTypeIsLoaded { .. } => codes::CONFIG_TYPE_IS_LOADED,
+            Self::CouldNotDetermineLoadedLabel(error) => error.code(),
+            Self::CouldNotMoveToTrash(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UndeleteError {
+    #[error("could not restore from trash, error = {0}")]
+    CouldNotRestoreFromTrash(
+        #[source]
+        #[from]
+        TrashError,
+    ),
+}
+
+impl ErrorCode for UndeleteError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotRestoreFromTrash(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AddPathError {
+    #[error("path does not exist, path = {0}")]
+    PathDoesNotExist(PathBuf),
+
+    #[error("could not make path absolute, path = {path}, error = {io_error}")]
+    CouldNotMakePathAbsolute {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("path is already in the config type descriptor, path = \"{0}\"")]
+    PathAlreadyAdded(String),
+
+    #[error("path is not under a directory with a variable (e.g. {{{{HOME}}}}), define a variable for its directory, path = {0}")]
+    PathNotUnderVariable(PathBuf),
+
+    #[error("could not write descriptor to file, error = {0}")]
+    CouldNotWriteDescriptorToFile(#[source] WriteConfigTypeDescriptorError),
+}
+
+impl ErrorCode for AddPathError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::PathDoesNotExist(..) => codes::PATH_NOT_FOUND,
+            Self::CouldNotMakePathAbsolute { io_error, .. } => io_error.code(),
+            Self::PathAlreadyAdded(..) => codes::PATH_ALREADY_EXISTS,
+            Self::PathNotUnderVariable(..) => codes::INVALID_ARGUMENT,
+            Self::CouldNotWriteDescriptorToFile(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RemovePathError {
+    #[error("path is not in the config type descriptor, path = \"{0}\"")]
+    PathNotFound(String),
+
+    #[error("could not write descriptor to file, error = {0}")]
+    CouldNotWriteDescriptorToFile(#[source] WriteConfigTypeDescriptorError),
+}
+
+impl ErrorCode for RemovePathError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::PathNotFound(..) => codes::PATH_NOT_FOUND,
+            Self::CouldNotWriteDescriptorToFile(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PathPatternError {
+    #[error("could not decode pattern, pattern = \"{pattern}\", error = {error}")]
+    CouldNotDecodePattern {
+        #[source]
+        error: DecodeStringError,
+        pattern: String,
+    },
+
+    #[error("invalid pattern, pattern = \"{pattern}\", error = {error}")]
+    InvalidPattern {
+        #[source]
+        error: globset::Error,
+        pattern: String,
+    },
+}
+
+impl ErrorCode for PathPatternError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotDecodePattern { error, .. } => error.code(),
+            Self::InvalidPattern { .. } => codes::INVALID_ARGUMENT,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TemplateFileError {
+    #[error("could not read file, path = {path}, error = {io_error}")]
+    CouldNotReadFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not write file, path = {path}, error = {io_error}")]
+    CouldNotWriteFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not decode template file, path = {path}, error = {error}")]
+    CouldNotDecodeTemplate {
+        #[source]
+        error: DecodeStringError,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for TemplateFileError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotReadFile { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteFile { io_error, .. } => io_error.code(),
+            Self::CouldNotDecodeTemplate { error, .. } => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoadTransactionError {
+    #[error("destination does not have a parent directory, path = {0}")]
+    InvalidDestination(PathBuf),
+
+    #[error("destination is not writable, path = {0}")]
+    DestinationNotWritable(PathBuf),
+
+    #[error("could not read metadata, path = {path}, error = {io_error}")]
+    CouldNotReadMetadata {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not create directory, error = {0}")]
+    CouldNotCreateDirectory(
+        #[source]
+        #[from]
+        EnsureDirectoryError,
+    ),
+
+    #[error("could not move file, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotMoveFile {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not restore all original files, they are kept next to them, error = {0}")]
+    RollbackFailed(#[source] Box<LoadTransactionError>),
+}
+
+impl ErrorCode for LoadTransactionError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidDestination(..) => codes::INVALID_ARGUMENT,
+            Self::DestinationNotWritable(..) => codes::PERMISSION_DENIED,
+            Self::CouldNotReadMetadata { io_error, .. } => io_error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotMoveFile { io_error, .. } => io_error.code(),
+            Self::RollbackFailed(..) => codes::ROLLBACK_FAILED,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not store live files, error = {0}")]
+    CouldNotStoreLiveFiles(#[source] Box<StoreLabeledConfigTypeError>),
+
+    #[error("could not encrypt the backup, error = {0}")]
+    CouldNotEncrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
+    #[error("could not rewrite the backup files, path = {path}, error = {io_error}")]
+    CouldNotRewriteFiles {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not copy file, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotCopyFile {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not create directory, error = {0}")]
+    CouldNotCreateDirectory(
+        #[source]
+        #[from]
+        EnsureDirectoryError,
+    ),
+
+    #[error("could not rename temp directory, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotRenameTempDirectory {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not read backups directory, path = {path}, error = {io_error}")]
+    CouldNotReadBackupsDirectory {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not remove old backup, path = {path}, error = {io_error}")]
+    CouldNotRemoveOldBackup {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not write the absent paths, path = {path}, error = {error}")]
+    CouldNotWriteAbsentPaths {
+        #[source]
+        error: WriteJsonFileError,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for BackupError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotStoreLiveFiles(error) => error.code(),
+            Self::CouldNotEncrypt(error) => error.code(),
+            Self::CouldNotRewriteFiles { io_error, .. } => io_error.code(),
+            Self::CouldNotCopyFile { io_error, .. } => io_error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotRenameTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotReadBackupsDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotRemoveOldBackup { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteAbsentPaths { error, .. } => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UndoError {
+    #[error("no backup found, config type = \"{0}\"")]
+    NoBackupFound(String),
+
+    #[error("could not decrypt the backup, error = {0}")]
+    CouldNotDecrypt(
+        #[source]
+        #[from]
+        EncryptionError,
+    ),
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not check out the backup, path = {path}, error = {io_error}")]
+    CouldNotCheckoutBackup {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read backups directory, path = {path}, error = {io_error}")]
+    CouldNotReadBackupsDirectory {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not restore the backup, error = {0}")]
+    CouldNotLoadBackup(
+        #[source]
+        #[from]
+        LoadLabeledConfigTypeError,
+    ),
+
+    #[error("could not create directory, error = {0}")]
+    CouldNotCreateDirectory(
+        #[source]
+        #[from]
+        EnsureDirectoryError,
+    ),
+
+    #[error("could not copy file, source_path = {source_path}, dest_path = {dest_path}, error = {io_error}")]
+    CouldNotCopyFile {
+        #[source]
+        io_error: std::io::Error,
+        source_path: PathBuf,
+        dest_path: PathBuf,
+    },
+
+    #[error("could not remove file, path = {path}, error = {io_error}")]
+    CouldNotRemoveFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not remove the restored backup, path = {path}, error = {io_error}")]
+    CouldNotRemoveBackup {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read the absent paths of the backup, path = {path}, error = {error}")]
+    CouldNotReadAbsentPaths {
+        #[source]
+        error: ReadJsonFileError,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for UndoError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NoBackupFound(..) => codes::BACKUP_NOT_FOUND,
+            Self::CouldNotDecrypt(error) => error.code(),
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotCheckoutBackup { io_error, .. } => io_error.code(),
+            Self::CouldNotReadBackupsDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotLoadBackup(error) => error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotCopyFile { io_error, .. } => io_error.code(),
+            Self::CouldNotRemoveFile { io_error, .. } => io_error.code(),
+            Self::CouldNotRemoveBackup { io_error, .. } => io_error.code(),
+            Self::CouldNotReadAbsentPaths { error, .. } => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("could not run git, command = \"{command}\", error = {io_error}")]
+    CouldNotRunGit {
+        #[source]
+        io_error: std::io::Error,
+        command: String,
+    },
+
+    #[error("git command failed, command = \"{command}\", status = {status}, error = {stderr}")]
+    CommandFailed {
+        command: String,
+        status: String,
+        stderr: String,
+    },
+
+    #[error("could not write .gitignore, path = {path}, error = {io_error}")]
+    CouldNotWriteGitignore {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for GitError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotRunGit { io_error, .. } => io_error.code(),
+            Self::CommandFailed { .. } => codes::GIT_FAILED,
+            Self::CouldNotWriteGitignore { io_error, .. } => io_error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum InitGitRepositoryError {
+    #[error("storage root is already a git repository, path = {0}")]
+    AlreadyARepository(PathBuf),
+
+    #[error("could not initialize git repository, error = {0}")]
+    CouldNotRunGit(
+        #[source]
+        #[from]
+        GitError,
+    ),
+}
+
+impl ErrorCode for InitGitRepositoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::AlreadyARepository(..) => codes::ALREADY_A_REPOSITORY,
+            Self::CouldNotRunGit(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PushError {
+    #[error("storage root is not a git repository, path = {0}")]
+    NotARepository(PathBuf),
+
+    #[error("could not push, error = {0}")]
+    CouldNotRunGit(
+        #[source]
+        #[from]
+        GitError,
+    ),
+}
+
+impl ErrorCode for PushError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NotARepository(..) => codes::NOT_A_REPOSITORY,
+            Self::CouldNotRunGit(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PullError {
+    #[error("storage root is not a git repository, path = {0}")]
+    NotARepository(PathBuf),
+
+    #[error("could not pull, error = {0}")]
+    CouldNotRunGit(
+        #[source]
+        #[from]
+        GitError,
+    ),
+}
+
+impl ErrorCode for PullError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NotARepository(..) => codes::NOT_A_REPOSITORY,
+            Self::CouldNotRunGit(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("unknown archive format, expected tar.gz or zip, path = {0}")]
+    UnknownFormat(PathBuf),
+
+    #[error("archive has no manifest, path = {0}")]
+    MissingManifest(PathBuf),
+
+    #[error("invalid archive manifest, path = {path}, error = {error}")]
+    InvalidManifest {
+        #[source]
+        error: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error("unsupported archive version = {version}, path = {path}")]
+    UnsupportedVersion { version: u32, path: PathBuf },
+
+    #[error("unexpected archive entry = \"{name}\", path = {path}")]
+    UnexpectedEntry { name: String, path: PathBuf },
+
+    #[error("duplicate archive entry = \"{name}\", path = {path}")]
+    DuplicateEntry { name: String, path: PathBuf },
+
+    #[error("invalid name = \"{0}\"")]
+    InvalidName(String),
+
+    #[error("invalid path, path = {0}")]
+    InvalidPath(PathBuf),
+
+    #[error("could not serialize archive manifest, error = {0}")]
+    CouldNotSerializeManifest(#[source] serde_json::Error),
+
+    #[error("could not read archive, path = {path}, error = {io_error}")]
+    CouldNotReadArchive {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read zip archive, path = {path}, error = {zip_error}")]
+    CouldNotReadZipArchive {
+        #[source]
+        zip_error: zip::result::ZipError,
+        path: PathBuf,
+    },
+
+    #[error("could not write archive, path = {path}, error = {io_error}")]
+    CouldNotWriteArchive {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not write zip archive, path = {path}, error = {zip_error}")]
+    CouldNotWriteZipArchive {
+        #[source]
+        zip_error: zip::result::ZipError,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for ArchiveError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownFormat(..) => codes::INVALID_ARGUMENT,
+            Self::MissingManifest(..) => codes::INVALID_DATA,
+            Self::InvalidManifest { .. } => codes::INVALID_DATA,
+            Self::UnsupportedVersion { .. } => codes::INVALID_DATA,
+            Self::UnexpectedEntry { .. } => codes::INVALID_DATA,
+            Self::DuplicateEntry { .. } => codes::INVALID_DATA,
+            Self::InvalidName(..) => codes::INVALID_DATA,
+            Self::InvalidPath(..) => codes::INVALID_DATA,
+            Self::CouldNotSerializeManifest(..) => codes::INTERNAL_ERROR,
+            Self::CouldNotReadArchive { io_error, .. } => io_error.code(),
+            Self::CouldNotReadZipArchive { .. } => codes::INVALID_DATA,
+            Self::CouldNotWriteArchive { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteZipArchive { .. } => codes::IO_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("config type not found = \"{0}\"")]
+    ConfigTypeNotFound(String),
+
+    #[error("label not found = \"{0}\"")]
+    LabelNotFound(String),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+
+    #[error("could not write archive, error = {0}")]
+    CouldNotWriteArchive(
+        #[source]
+        #[from]
+        ArchiveError,
+    ),
+}
+
+impl ErrorCode for ExportError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::ConfigTypeNotFound(..) => codes::CONFIG_TYPE_NOT_FOUND,
+            Self::LabelNotFound(..) => codes::LABEL_NOT_FOUND,
+            Self::CouldNotAccessStorage(error) => error.code(),
+            Self::CouldNotWriteArchive(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("could not read archive, error = {0}")]
+    CouldNotReadArchive(
+        #[source]
+        #[from]
+        ArchiveError,
+    ),
+
+    #[error("archive has no config type descriptor")]
+    MissingDescriptor,
+
+    #[error("invalid config type descriptor in archive, error = {0}")]
+    InvalidDescriptor(#[source] ReadConfigTypeDescriptorError),
+
+    #[error("config type is encrypted differently than in the archive, rekey it first, config type = \"{0}\"")]
+    DifferentEncryption(String),
+
+    #[error("could not read config type, error = {0}")]
+    CouldNotGetConfigType(
+        #[source]
+        #[from]
+        GetConfigTypeError,
+    ),
+
+    #[error("label to rename is not in the archive = \"{0}\"")]
+    RenamedLabelNotFound(String),
+
+    #[error("several labels would be imported as = \"{0}\"")]
+    DuplicateLabel(String),
+
+    #[error("labels already exist, use --rename or --skip-existing to import anyway, config type = \"{config_type}\", labels = {labels:?}")]
+    LabelsAlreadyExist {
+        config_type: String,
+        labels: Vec<String>,
+    },
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for ImportError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotReadArchive(error) => error.code(),
+            Self::MissingDescriptor => codes::INVALID_DATA,
+            Self::InvalidDescriptor(error) => error.code(),
+            Self::DifferentEncryption(..) => codes::INVALID_ARGUMENT,
+            Self::CouldNotGetConfigType(error) => error.code(),
+            Self::RenamedLabelNotFound(..) => codes::INVALID_ARGUMENT,
+            Self::DuplicateLabel(..) => codes::INVALID_ARGUMENT,
+            Self::LabelsAlreadyExist { .. } => codes::LABEL_ALREADY_EXISTS,
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    #[error("config type is encrypted with a passphrase, but no passphrase can be asked for, config type = \"{0}\"")]
+    NoPassphrase(String),
+
+    #[error("could not read passphrase, error = {0}")]
+    CouldNotReadPassphrase(#[source] std::io::Error),
+
+    #[error("wrong passphrase, config type = \"{0}\"")]
+    WrongPassphrase(String),
+
+    #[error("key file does not hold the key the config type is encrypted for, config type = \"{config_type}\", key file = {key_file}")]
+    WrongKey {
+        config_type: String,
+        key_file: PathBuf,
+    },
+
+    #[error(
+        "file is not encrypted for the key of the key file, path = {path}, key file = {key_file}"
+    )]
+    NotEncryptedForKey { path: PathBuf, key_file: PathBuf },
+
+    #[error("file is encrypted with another method than the config type, path = {0}")]
+    EncryptedWithOtherMethod(PathBuf),
+
+    #[error("could not decrypt file, it is damaged, path = {0}")]
+    CouldNotDecrypt(PathBuf),
+
+    #[error("file was encrypted for another path of the label, path = {0}")]
+    EncryptedForOtherPath(PathBuf),
+
+    #[error("file is not encrypted although the config type is, rekey with --allow-plaintext to encrypt it, path = {0}")]
+    NotEncrypted(PathBuf),
+
+    #[error("could not encrypt file, path = {0}")]
+    CouldNotEncrypt(PathBuf),
+
+    #[error("could not derive key from passphrase, error = {0}")]
+    CouldNotDeriveKey(String),
+
+    #[error("invalid encryption settings in descriptor, config type = \"{config_type}\", reason = {reason}")]
+    InvalidSettings { config_type: String, reason: String },
+
+    #[error("could not decode key file path, error = {0}")]
+    CouldNotDecodeKeyFilePath(#[source] DecodeStringError),
+
+    #[error("could not read key file, path = {path}, error = {io_error}")]
+    CouldNotReadKeyFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("key file holds no age identity, path = {0}")]
+    InvalidKeyFile(PathBuf),
+
+    #[error("could not write key file, path = {path}, error = {io_error}")]
+    CouldNotWriteKeyFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+impl ErrorCode for EncryptionError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NoPassphrase(..) => codes::PASSPHRASE_REQUIRED,
+            Self::CouldNotReadPassphrase(error) => error.code(),
+            Self::WrongPassphrase(..) => codes::WRONG_KEY,
+            Self::WrongKey { .. } => codes::WRONG_KEY,
+            Self::NotEncryptedForKey { .. } => codes::WRONG_KEY,
+            Self::EncryptedWithOtherMethod(..) => codes::ENCRYPTION_ERROR,
+            Self::CouldNotDecrypt(..) => codes::ENCRYPTION_ERROR,
+            Self::EncryptedForOtherPath(..) => codes::ENCRYPTION_ERROR,
+            Self::NotEncrypted(..) => codes::ENCRYPTION_ERROR,
+            Self::CouldNotEncrypt(..) => codes::ENCRYPTION_ERROR,
+            Self::CouldNotDeriveKey(..) => codes::ENCRYPTION_ERROR,
+            Self::InvalidSettings { .. } => codes::INVALID_DATA,
+            Self::CouldNotDecodeKeyFilePath(error) => error.code(),
+            Self::CouldNotReadKeyFile { io_error, .. } => io_error.code(),
+            Self::InvalidKeyFile(..) => codes::INVALID_DATA,
+            Self::CouldNotWriteKeyFile { io_error, .. } => io_error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RekeyError {
+    #[error("config type is not encrypted = \"{0}\"")]
+    NotEncrypted(String),
+
+    #[error("could not decrypt with the current key, error = {0}")]
+    CouldNotDecrypt(#[source] EncryptionError),
+
+    #[error("could not encrypt with the new key, error = {0}")]
+    CouldNotEncrypt(#[source] EncryptionError),
+
+    #[error("could not make key file path absolute, path = {path}, error = {io_error}")]
+    CouldNotMakePathAbsolute {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not rekey backup of the live files, path = {path}, error = {io_error}")]
+    CouldNotRekeyBackup {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not write descriptor, error = {0}")]
+    CouldNotWriteDescriptor(
+        #[source]
+        #[from]
+        WriteConfigTypeDescriptorError,
+    ),
+
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for RekeyError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NotEncrypted(..) => codes::NOT_ENCRYPTED,
+            Self::CouldNotDecrypt(error) => error.code(),
+            Self::CouldNotEncrypt(error) => error.code(),
+            Self::CouldNotMakePathAbsolute { io_error, .. } => io_error.code(),
+            Self::CouldNotRekeyBackup { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteDescriptor(error) => error.code(),
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CollectGarbageError {
+    #[error("could not access storage, error = {0}")]
+    CouldNotAccessStorage(
+        #[source]
+        #[from]
+        StorageBackendError,
+    ),
+}
+
+impl ErrorCode for CollectGarbageError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotAccessStorage(error) => error.code(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StorageBackendError {
+    #[error("config type not found = \"{0}\"")]
+    ConfigTypeNotFound(String),
+
+    #[error("config type already exists = \"{0}\"")]
+    ConfigTypeAlreadyExists(String),
+
+    #[error("label not found, config type = \"{config_type}\", label = \"{label}\"")]
+    LabelNotFound { config_type: String, label: String },
+
+    #[error("revision not found, config type = \"{config_type}\", label = \"{label}\", revision = {revision}")]
+    RevisionNotFound {
+        config_type: String,
+        label: String,
+        revision: u32,
+    },
+
+    #[error("invalid name = \"{0}\"")]
+    InvalidName(String),
+
+    #[error("invalid path, path = {0}")]
+    InvalidPath(PathBuf),
+
+    #[error("invalid object id = \"{0}\"")]
+    InvalidObjectId(String),
+
+    #[error("could not read object, id = \"{id}\", path = {path}, error = {io_error}")]
+    CouldNotReadObject {
+        #[source]
+        io_error: std::io::Error,
+        id: String,
+        path: PathBuf,
+    },
+
+    #[error("invalid label revision, path = {path}, error = {error}")]
+    InvalidRevision {
+        #[source]
+        error: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error("invalid label manifest, path = {path}, error = {error}")]
+    InvalidManifest {
+        #[source]
+        error: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not serialize label, error = {0}")]
+    CouldNotSerializeLabel(#[source] serde_json::Error),
+
+    #[error("could not read directory, path = {path}, error = {io_error}")]
+    CouldNotReadDirectory {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not read file, path = {path}, error = {io_error}")]
+    CouldNotReadFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not write file, path = {path}, error = {io_error}")]
+    CouldNotWriteFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not remove file, path = {path}, error = {io_error}")]
+    CouldNotRemoveFile {
+        #[source]
+        io_error: std::io::Error,
+        path: PathBuf,
+    },
+
+    #[error("could not create directory, error = {0}")]
+    CouldNotCreateDirectory(
+        #[source]
+        #[from]
+        EnsureDirectoryError,
+    ),
+
+    #[error("could not create temp directory, path = {path}, error = {io_error}")]
+    CouldNotCreateTempDirectory {
+        #[source]
+        io_error: CreateNewDirectoryError,
+        path: PathBuf,
+    },
+
+    #[error("could not commit the changes, error = {0}")]
+    CouldNotCommit(
+        #[source]
+        #[from]
+        LoadTransactionError,
+    ),
+
+    #[error("changes are stored, but could not commit them to git, error = {0}")]
+    CouldNotCommitToGit(
+        #[source]
+        #[from]
+        GitError,
+    ),
+}
+
+impl ErrorCode for StorageBackendError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::ConfigTypeNotFound(..) => codes::CONFIG_TYPE_NOT_FOUND,
+            Self::ConfigTypeAlreadyExists(..) => codes::CONFIG_TYPE_ALREADY_EXISTS,
+            Self::LabelNotFound { .. } => codes::LABEL_NOT_FOUND,
+            Self::RevisionNotFound { .. } => codes::REVISION_NOT_FOUND,
+            Self::InvalidName(..) => codes::INVALID_ARGUMENT,
+            Self::InvalidPath(..) => codes::INVALID_ARGUMENT,
+            Self::InvalidObjectId(..) => codes::INVALID_DATA,
+            Self::CouldNotReadObject { io_error, .. } => io_error.code(),
+            Self::InvalidRevision { .. } => codes::INVALID_DATA,
+            Self::InvalidManifest { .. } => codes::INVALID_DATA,
+            Self::CouldNotSerializeLabel(..) => codes::INTERNAL_ERROR,
+            Self::CouldNotReadDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotReadFile { io_error, .. } => io_error.code(),
+            Self::CouldNotWriteFile { io_error, .. } => io_error.code(),
+            Self::CouldNotRemoveFile { io_error, .. } => io_error.code(),
+            Self::CouldNotCreateDirectory(error) => error.code(),
+            Self::CouldNotCreateTempDirectory { io_error, .. } => io_error.code(),
+            Self::CouldNotCommit(error) => error.code(),
+            Self::CouldNotCommitToGit(error) => error.code(),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::BTreeMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ReadJsonFileError, WriteJsonFileError},
+    utils::{read_json_file, write_json_file},
+};
+
+/// The modes, modification times and owners of the files of a label, keyed by their path
+/// relative to the label (e.g. `{{HOME}}/.ssh/config`), stored as `file_attributes.json` next to
+/// them. Labels stored before the attributes were recorded do not have it.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LabelFileAttributes {
+    files: BTreeMap<PathBuf, FileAttributes>,
+}
+
+impl LabelFileAttributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadJsonFileError> {
+        read_json_file(path)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), WriteJsonFileError> {
+        write_json_file(self, path)
+    }
+
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&FileAttributes> {
+        self.files.get(path.as_ref())
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, attributes: FileAttributes) {
+        self.files.insert(path.into(), attributes);
+    }
+}
+
+/// The attributes of a stored file or directory, restored when it is loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAttributes {
+    /// The permission bits, only recorded on unix.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_mode")]
+    mode: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified_at: Option<DateTime<Utc>>,
+
+    /// Only recorded if the file belongs to the user storing it (or the user is root), other
+    /// owners could not be restored by that user anyway.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<FileOwner>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOwner {
+    uid: u32,
+    gid: u32,
+}
+
+impl FileAttributes {
+    /// Reads the attributes of the file or directory, symlinks are followed.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Ok(Self::from_metadata(&path.as_ref().metadata()?))
+    }
+
+    #[cfg(unix)]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        let uid = current_uid();
+        Self {
+            mode: Some(metadata.mode() & 0o7777),
+            modified_at: metadata.modified().ok().map(DateTime::from),
+            owner: (uid == 0 || uid == metadata.uid()).then(|| FileOwner {
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+            }),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            mode: None,
+            modified_at: metadata.modified().ok().map(DateTime::from),
+            owner: None,
+        }
+    }
+
+    pub fn mode(&self) -> Option<u32> {
+        self.mode
+    }
+
+    pub fn modified_at(&self) -> Option<DateTime<Utc>> {
+        self.modified_at
+    }
+
+    /// Sets the recorded attributes on `path`, `mode` replaces the recorded mode (e.g. the mode
+    /// of the path in the descriptor).
+    ///
+    /// The owner is only restored by root, other users only restore the group of their own
+    /// files. A failure to restore the owner is logged instead of failing, as the user may not
+    /// be a member of the group anymore.
+    pub fn restore(&self, path: impl AsRef<Path>, mode: Option<u32>) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+
+        #[cfg(unix)]
+        {
+            use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+            // changing the owner may clear the setuid and setgid bits, so it comes first
+            if let Some(owner) = self.owner {
+                restore_owner(path, owner);
+            }
+            if let Some(mode) = mode.or(self.mode) {
+                std::fs::set_permissions(path, Permissions::from_mode(mode))?;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        if let Some(modified_at) = self.modified_at {
+            filetime::set_file_mtime(
+                path,
+                FileTime::from_system_time(SystemTime::from(modified_at)),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn restore_owner(path: &Path, owner: FileOwner) {
+    let uid = current_uid();
+    let result = if uid == 0 {
+        std::os::unix::fs::chown(path, Some(owner.uid), Some(owner.gid))
+    } else if uid == owner.uid {
+        std::os::unix::fs::chown(path, None, Some(owner.gid))
+    } else {
+        return;
+    };
+
+    if let Err(e) = result {
+        log::warn!(
+            "could not restore owner of file, path = {:?}, uid = {}, gid = {}, error = {}",
+            path,
+            owner.uid,
+            owner.gid,
+            e
+        );
+    }
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+/// Parses permission bits written in octal (e.g. `600` or `0o600`).
+pub fn parse_mode(value: &str) -> Option<u32> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+}
+
+/// Formats permission bits in octal, as `ls` and `chmod` show them (e.g. `0600`).
+pub fn format_mode(mode: u32) -> String {
+    format!("{:04o}", mode)
+}
+
+/// Modes are written in octal, as decimal permission bits cannot be read.
+pub(crate) mod serde_mode {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::{format_mode, parse_mode};
+
+    pub fn serialize<S: Serializer>(mode: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+        match mode {
+            Some(mode) => serializer.serialize_str(&format_mode(*mode)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u32>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| {
+                parse_mode(&value)
+                    .ok_or_else(|| D::Error::custom(format!("invalid mode \"{}\"", value)))
+            })
+            .transpose()
+    }
+}
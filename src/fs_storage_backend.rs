@@ -0,0 +1,992 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{remove_dir_all, File},
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    directories::{
+        Directories, LABEL_HISTORY_DIRNAME, LABEL_MANIFEST_FILENAME, LABEL_METADATA_FILENAME,
+    },
+    error::{EnsureDirectoryError, StorageBackendError},
+    git_repository::GitRepository,
+    label_manifest::LabelManifest,
+    load_transaction::LoadTransaction,
+    object_store::ObjectStore,
+    storage_backend::{
+        GarbageCollection, LabelFiles, LabelRevision, StorageBackend, StorageOperation,
+        StorageTransaction,
+    },
+    utils::{
+        collect_files_recursive, create_new_directory, ensure_directory, SubDirectoryIterator,
+    },
+};
+
+/// Keeps every config type as a directory under `<root>/db`, with the descriptor and the pattern
+/// files next to one sub directory per label, and every profile as `<root>/profiles/<name>.json`.
+///
+/// The contents of the label files are kept once in the object store under `<root>/objects`, a
+/// label directory only holds a manifest referring to them, and the manifests of its revisions
+/// under `history`. Label directories holding the files themselves, as written by earlier
+/// versions, are still read.
+///
+/// If the storage root is a git repository, every commit of a transaction is also committed to
+/// git, with a message describing the changes.
+pub struct FsStorageBackend {
+    directories: Directories,
+    git_repository: Option<GitRepository>,
+}
+
+/// A revision of a label, stored as `history/<number>.json` in the label directory.
+#[derive(Clone, Serialize, Deserialize)]
+struct RevisionFile {
+    created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    files: LabelManifest,
+}
+
+/// Everything kept in the directory of a label, rewritten as a whole on every change.
+#[derive(Clone, Default)]
+struct LabelState {
+    manifest: LabelManifest,
+    revisions: BTreeMap<u32, RevisionFile>,
+}
+
+impl LabelState {
+    fn add_revision(&mut self, message: Option<String>) {
+        let number = self
+            .revisions
+            .keys()
+            .next_back()
+            .map_or(1, |number| number + 1);
+        self.revisions.insert(
+            number,
+            RevisionFile {
+                created_at: Utc::now(),
+                message,
+                files: self.manifest.clone(),
+            },
+        );
+    }
+
+    /// Whether `manifest` has the same files as the current files and the latest revision, the
+    /// metadata of the label is left out, as it changes on every store.
+    fn is_unchanged_by(&self, manifest: &LabelManifest) -> bool {
+        let same_files = |other: &LabelManifest| {
+            let metadata_path = Path::new(LABEL_METADATA_FILENAME);
+            manifest
+                .files()
+                .iter()
+                .filter(|(path, _)| *path != metadata_path)
+                .eq(other
+                    .files()
+                    .iter()
+                    .filter(|(path, _)| *path != metadata_path))
+        };
+
+        same_files(&self.manifest)
+            && self
+                .revisions
+                .values()
+                .next_back()
+                .is_some_and(|revision| same_files(&revision.files))
+    }
+}
+
+impl FsStorageBackend {
+    pub fn new(root_dir: impl Into<PathBuf>) -> Result<Self, EnsureDirectoryError> {
+        let directories = Directories::new(root_dir);
+        ensure_directory(directories.db_dir_path(), true)?;
+        ensure_directory(directories.temp_dir_path(), true)?;
+        ensure_directory(directories.objects_dir_path(), true)?;
+        ensure_directory(directories.profiles_dir_path(), true)?;
+        let git_repository = GitRepository::open(directories.root_dir_path());
+
+        Ok(Self {
+            directories,
+            git_repository,
+        })
+    }
+
+    /// Describes the operations as a commit message, read before they are applied, as the
+    /// changed files of a label are found by comparing with its stored files.
+    fn commit_message(
+        &self,
+        operations: &[StorageOperation],
+    ) -> Result<String, StorageBackendError> {
+        let mut summaries = Vec::new();
+        let mut details = Vec::new();
+        for operation in operations {
+            match operation {
+                StorageOperation::CreateConfigType { config_type, .. } => {
+                    summaries.push(format!("Create config type {}", config_type));
+                }
+                StorageOperation::RemoveConfigType { config_type } => {
+                    summaries.push(format!("Remove config type {}", config_type));
+                }
+                StorageOperation::PutConfigTypeFile {
+                    config_type, name, ..
+                } => {
+                    summaries.push(format!("Update {} of {}", name, config_type));
+                }
+                StorageOperation::PutLabel {
+                    config_type,
+                    label,
+                    files,
+                    message,
+                } => {
+                    summaries.push(match message {
+                        Some(message) => format!("Store {}/{}: {}", config_type, label, message),
+                        None => format!("Store {}/{}", config_type, label),
+                    });
+                    details.extend(
+                        self.changed_files(config_type, label, files)?
+                            .into_iter()
+                            .map(|line| format!("{}/{} {}", config_type, label, line)),
+                    );
+                }
+                StorageOperation::PutLabelFile {
+                    config_type,
+                    label,
+                    path,
+                    ..
+                } => {
+                    summaries.push(format!(
+                        "Update {} of {}/{}",
+                        path.display(),
+                        config_type,
+                        label
+                    ));
+                }
+                StorageOperation::RestoreLabel {
+                    config_type, label, ..
+                } => {
+                    summaries.push(format!("Restore {}/{}", config_type, label));
+                }
+                StorageOperation::RewriteLabel {
+                    config_type, label, ..
+                } => {
+                    summaries.push(format!("Rewrite {}/{}", config_type, label));
+                }
+                StorageOperation::RenameLabel {
+                    config_type,
+                    label,
+                    new_label,
+                } => {
+                    summaries.push(format!("Rename {}/{} to {}", config_type, label, new_label));
+                }
+                StorageOperation::RemoveLabel { config_type, label } => {
+                    summaries.push(format!("Remove {}/{}", config_type, label));
+                }
+                StorageOperation::PutProfile { profile, .. } => {
+                    summaries.push(format!("Store profile {}", profile));
+                }
+            }
+        }
+
+        let mut message = summaries.join(", ");
+        if !details.is_empty() {
+            message.push_str("\n\n");
+            message.push_str(&details.join("\n"));
+        }
+
+        Ok(message)
+    }
+
+    /// Lists the files of the label that `files` adds, modifies or removes, e.g.
+    /// `modified: {{HOME}}/.gitconfig`. The metadata of the label is left out, as it changes on
+    /// every store.
+    fn changed_files(
+        &self,
+        config_type: &str,
+        label: &str,
+        files: &LabelFiles,
+    ) -> Result<Vec<String>, StorageBackendError> {
+        let label_dir_path = self.labeled_config_type_dir_path(config_type, label)?;
+        let stored_ids = if !label_dir_path.is_dir() {
+            BTreeMap::new()
+        } else if let Some(manifest) = self.read_manifest(&label_dir_path)? {
+            manifest.files().clone()
+        } else {
+            let mut stored_ids = BTreeMap::new();
+            for path in self.legacy_label_files(&label_dir_path)? {
+                let content = read_file_if_exists(&label_dir_path.join(&path))?.unwrap_or_default();
+                stored_ids.insert(path, ObjectStore::object_id(&content));
+            }
+            stored_ids
+        };
+
+        let metadata_path = Path::new(LABEL_METADATA_FILENAME);
+        let mut changes = Vec::new();
+        for (path, content) in files {
+            if path == metadata_path {
+                continue;
+            }
+            match stored_ids.get(path) {
+                None => changes.push(format!("added: {}", path.display())),
+                Some(id) if *id != ObjectStore::object_id(content) => {
+                    changes.push(format!("modified: {}", path.display()))
+                }
+                Some(_) => {}
+            }
+        }
+        for path in stored_ids.keys() {
+            if path != metadata_path && !files.contains_key(path) {
+                changes.push(format!("removed: {}", path.display()));
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn object_store(&self) -> ObjectStore<'_> {
+        ObjectStore::new(&self.directories)
+    }
+
+    /// Returns `None` for a label directory holding the files themselves.
+    fn read_manifest(
+        &self,
+        label_dir_path: &Path,
+    ) -> Result<Option<LabelManifest>, StorageBackendError> {
+        let manifest_path = label_dir_path.join(LABEL_MANIFEST_FILENAME);
+        let Some(content) = read_file_if_exists(&manifest_path)? else {
+            return Ok(None);
+        };
+
+        LabelManifest::from_slice(&content).map(Some).map_err(|e| {
+            StorageBackendError::InvalidManifest {
+                error: e,
+                path: manifest_path,
+            }
+        })
+    }
+
+    /// Puts the contents of `files` into the object store.
+    fn put_objects(&self, files: LabelFiles) -> Result<LabelManifest, StorageBackendError> {
+        let object_store = self.object_store();
+        let mut manifest = LabelManifest::default();
+        for (path, content) in files {
+            verify_relative_path(&path)?;
+            manifest.insert(path, object_store.put(&content)?);
+        }
+
+        Ok(manifest)
+    }
+
+    /// Returns the revisions stored in the label directory, empty for labels stored before
+    /// revisions were kept.
+    fn read_revisions(
+        &self,
+        label_dir_path: &Path,
+    ) -> Result<BTreeMap<u32, RevisionFile>, StorageBackendError> {
+        let history_dir_path = label_dir_path.join(LABEL_HISTORY_DIRNAME);
+        let mut revisions = BTreeMap::new();
+        if !history_dir_path.is_dir() {
+            return Ok(revisions);
+        }
+
+        let map_err = |e| StorageBackendError::CouldNotReadDirectory {
+            io_error: e,
+            path: history_dir_path.clone(),
+        };
+        for dir_entry in std::fs::read_dir(&history_dir_path).map_err(map_err)? {
+            let path = dir_entry.map_err(map_err)?.path();
+            let Some(number) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            let content = read_file_if_exists(&path)?.unwrap_or_default();
+            let revision = serde_json::from_slice(&content)
+                .map_err(|e| StorageBackendError::InvalidRevision { error: e, path })?;
+            revisions.insert(number, revision);
+        }
+
+        Ok(revisions)
+    }
+
+    /// Reads the directory of an existing label. The files of a label written by an earlier
+    /// version are put into the object store, and its current files become its first revision.
+    fn read_label_state(&self, label_dir_path: &Path) -> Result<LabelState, StorageBackendError> {
+        let manifest = match self.read_manifest(label_dir_path)? {
+            Some(manifest) => manifest,
+            None => {
+                let mut files = LabelFiles::new();
+                for path in self.legacy_label_files(label_dir_path)? {
+                    let content =
+                        read_file_if_exists(&label_dir_path.join(&path))?.unwrap_or_default();
+                    files.insert(path, content);
+                }
+                self.put_objects(files)?
+            }
+        };
+
+        let mut revisions = self.read_revisions(label_dir_path)?;
+        if revisions.is_empty() {
+            revisions.insert(1, initial_revision(label_dir_path, manifest.clone()));
+        }
+
+        Ok(LabelState {
+            manifest,
+            revisions,
+        })
+    }
+
+    /// Returns the state of the label as left by the earlier operations of the transaction, or as
+    /// stored. `None` if the label does not exist.
+    fn take_label_state(
+        &self,
+        label_dir_path: &Path,
+        staged_labels: &mut BTreeMap<PathBuf, LabelState>,
+    ) -> Result<Option<LabelState>, StorageBackendError> {
+        match staged_labels.remove(label_dir_path) {
+            Some(state) => Ok(Some(state)),
+            None if label_dir_path.is_dir() => self.read_label_state(label_dir_path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn legacy_label_files(
+        &self,
+        label_dir_path: &Path,
+    ) -> Result<Vec<PathBuf>, StorageBackendError> {
+        collect_files_recursive(label_dir_path).map_err(|e| {
+            StorageBackendError::CouldNotReadDirectory {
+                io_error: e,
+                path: label_dir_path.to_path_buf(),
+            }
+        })
+    }
+
+    fn profile_path(&self, profile: &str) -> Result<PathBuf, StorageBackendError> {
+        verify_name(profile)?;
+        Ok(self.directories.profile_path(profile))
+    }
+
+    fn config_type_dir_path(&self, config_type: &str) -> Result<PathBuf, StorageBackendError> {
+        verify_name(config_type)?;
+        Ok(self.directories.config_type_dir_path(config_type))
+    }
+
+    fn labeled_config_type_dir_path(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<PathBuf, StorageBackendError> {
+        verify_name(config_type)?;
+        verify_name(label)?;
+        if Directories::is_reserved_label_name(label) {
+            return Err(StorageBackendError::InvalidName(label.into()));
+        }
+        Ok(self
+            .directories
+            .labeled_config_type_dir_path(config_type, label))
+    }
+
+    fn existing_label_dir_path(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<PathBuf, StorageBackendError> {
+        let label_dir_path = self.labeled_config_type_dir_path(config_type, label)?;
+        if !label_dir_path.is_dir() {
+            return Err(StorageBackendError::LabelNotFound {
+                config_type: config_type.into(),
+                label: label.into(),
+            });
+        }
+
+        Ok(label_dir_path)
+    }
+
+    fn sub_directory_names(&self, path: &Path) -> Result<Vec<String>, StorageBackendError> {
+        let map_err = |e| StorageBackendError::CouldNotReadDirectory {
+            io_error: e,
+            path: path.to_path_buf(),
+        };
+
+        let mut names = SubDirectoryIterator::new(path)
+            .map_err(map_err)?
+            .map(|dir_entry| {
+                dir_entry.map(|dir_entry| dir_entry.file_name().to_string_lossy().to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(map_err)?;
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Prepares the result of `operation` in the staging directory and adds it to `transaction`.
+    /// The labels written by earlier operations are kept in `staged_labels`.
+    fn stage(
+        &self,
+        operation: StorageOperation,
+        transaction: &mut LoadTransaction,
+        staged_labels: &mut BTreeMap<PathBuf, LabelState>,
+    ) -> Result<(), StorageBackendError> {
+        match operation {
+            StorageOperation::CreateConfigType {
+                config_type,
+                files,
+                labels,
+            } => {
+                let dest_path = self.config_type_dir_path(&config_type)?;
+                if dest_path.exists() || transaction.contains_destination(&dest_path) {
+                    return Err(StorageBackendError::ConfigTypeAlreadyExists(config_type));
+                }
+
+                let staged_path = transaction.next_staged_path();
+                for (name, content) in files {
+                    verify_name(&name)?;
+                    write_file(&staged_path.join(name), &content)?;
+                }
+                for (label, files) in labels {
+                    let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                    let mut state = LabelState {
+                        manifest: self.put_objects(files)?,
+                        ..Default::default()
+                    };
+                    state.add_revision(None);
+                    write_label_state(&staged_path.join(label), &state)?;
+                    staged_labels.insert(label_dir_path, state);
+                }
+                ensure_directory(&staged_path, true)?;
+
+                transaction.add(staged_path, dest_path);
+            }
+            StorageOperation::RemoveConfigType { config_type } => {
+                if !self.has_config_type(&config_type)? {
+                    return Err(StorageBackendError::ConfigTypeNotFound(config_type));
+                }
+
+                transaction.add_removal(self.config_type_dir_path(&config_type)?);
+            }
+            StorageOperation::PutConfigTypeFile {
+                config_type,
+                name,
+                content,
+            } => {
+                verify_name(&name)?;
+                if !self.has_config_type(&config_type)? {
+                    return Err(StorageBackendError::ConfigTypeNotFound(config_type));
+                }
+
+                let staged_path = transaction.next_staged_path();
+                write_file(&staged_path, &content)?;
+
+                transaction.add(
+                    staged_path,
+                    self.config_type_dir_path(&config_type)?.join(name),
+                );
+            }
+            StorageOperation::PutLabel {
+                config_type,
+                label,
+                files,
+                message,
+            } => {
+                if !self.has_config_type(&config_type)? {
+                    return Err(StorageBackendError::ConfigTypeNotFound(config_type));
+                }
+
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                let mut state = self
+                    .take_label_state(&label_dir_path, staged_labels)?
+                    .unwrap_or_default();
+                let manifest = self.put_objects(files)?;
+                // storing the same files again neither adds a revision nor touches the label
+                if state.is_unchanged_by(&manifest) {
+                    staged_labels.insert(label_dir_path, state);
+                    return Ok(());
+                }
+                state.manifest = manifest;
+                state.add_revision(message);
+
+                let staged_path = transaction.next_staged_path();
+                write_label_state(&staged_path, &state)?;
+                staged_labels.insert(label_dir_path.clone(), state);
+
+                transaction.add(staged_path, label_dir_path);
+            }
+            StorageOperation::PutLabelFile {
+                config_type,
+                label,
+                path,
+                content,
+            } => {
+                verify_relative_path(&path)?;
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                let Some(mut state) = self.take_label_state(&label_dir_path, staged_labels)? else {
+                    return Err(StorageBackendError::LabelNotFound { config_type, label });
+                };
+                state
+                    .manifest
+                    .insert(path, self.object_store().put(&content)?);
+
+                // the whole label directory is replaced, as it may still hold the files themselves
+                let staged_path = transaction.next_staged_path();
+                write_label_state(&staged_path, &state)?;
+                staged_labels.insert(label_dir_path.clone(), state);
+
+                transaction.add(staged_path, label_dir_path);
+            }
+            StorageOperation::RestoreLabel {
+                config_type,
+                label,
+                files,
+                revisions,
+            } => {
+                let config_type_dir_path = self.config_type_dir_path(&config_type)?;
+                // a config type created by an earlier operation gets the label in its staged
+                // directory
+                let staged_config_type_dir_path = transaction
+                    .staged_path(&config_type_dir_path)
+                    .map(Path::to_path_buf);
+                if staged_config_type_dir_path.is_none() && !config_type_dir_path.is_dir() {
+                    return Err(StorageBackendError::ConfigTypeNotFound(config_type));
+                }
+
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                let mut state = LabelState {
+                    manifest: self.put_objects(files)?,
+                    ..Default::default()
+                };
+                for (revision, files) in revisions {
+                    state.revisions.insert(
+                        revision.number(),
+                        RevisionFile {
+                            created_at: revision.created_at(),
+                            message: revision.message().map(String::from),
+                            files: self.put_objects(files)?,
+                        },
+                    );
+                }
+                if state.revisions.is_empty() {
+                    state.add_revision(None);
+                }
+
+                if let Some(staged_config_type_dir_path) = staged_config_type_dir_path {
+                    write_label_state(&staged_config_type_dir_path.join(&label), &state)?;
+                    staged_labels.insert(label_dir_path, state);
+                    return Ok(());
+                }
+
+                let staged_path = transaction.next_staged_path();
+                write_label_state(&staged_path, &state)?;
+                staged_labels.insert(label_dir_path.clone(), state);
+
+                transaction.add(staged_path, label_dir_path);
+            }
+            StorageOperation::RewriteLabel {
+                config_type,
+                label,
+                files,
+                revisions,
+            } => {
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                let Some(mut state) = self.take_label_state(&label_dir_path, staged_labels)? else {
+                    return Err(StorageBackendError::LabelNotFound { config_type, label });
+                };
+                state.manifest = self.put_objects(files)?;
+                for (number, files) in revisions {
+                    let Some(revision) = state.revisions.get_mut(&number) else {
+                        return Err(StorageBackendError::RevisionNotFound {
+                            config_type,
+                            label,
+                            revision: number,
+                        });
+                    };
+                    revision.files = self.put_objects(files)?;
+                }
+
+                let staged_path = transaction.next_staged_path();
+                write_label_state(&staged_path, &state)?;
+                staged_labels.insert(label_dir_path.clone(), state);
+
+                transaction.add(staged_path, label_dir_path);
+            }
+            StorageOperation::RenameLabel {
+                config_type,
+                label,
+                new_label,
+            } => {
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                let new_label_dir_path =
+                    self.labeled_config_type_dir_path(&config_type, &new_label)?;
+                let Some(state) = self.take_label_state(&label_dir_path, staged_labels)? else {
+                    return Err(StorageBackendError::LabelNotFound { config_type, label });
+                };
+
+                let staged_path = transaction.next_staged_path();
+                write_label_state(&staged_path, &state)?;
+                staged_labels.insert(new_label_dir_path.clone(), state);
+
+                transaction.add(staged_path, new_label_dir_path);
+                transaction.add_removal(label_dir_path);
+            }
+            StorageOperation::RemoveLabel { config_type, label } => {
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                if !label_dir_path.is_dir() && !staged_labels.contains_key(&label_dir_path) {
+                    return Err(StorageBackendError::LabelNotFound { config_type, label });
+                }
+
+                staged_labels.remove(&label_dir_path);
+                transaction.add_removal(label_dir_path);
+            }
+            StorageOperation::PutProfile { profile, content } => {
+                let staged_path = transaction.next_staged_path();
+                write_file(&staged_path, &content)?;
+
+                transaction.add(staged_path, self.profile_path(&profile)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl StorageBackend for FsStorageBackend {
+    fn config_types(&self) -> Result<Vec<String>, StorageBackendError> {
+        self.sub_directory_names(self.directories.db_dir_path())
+    }
+
+    fn has_config_type(&self, config_type: &str) -> Result<bool, StorageBackendError> {
+        Ok(self.config_type_dir_path(config_type)?.is_dir())
+    }
+
+    fn config_type_files(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError> {
+        let config_type_dir_path = self.config_type_dir_path(config_type)?;
+        if !config_type_dir_path.is_dir() {
+            return Err(StorageBackendError::ConfigTypeNotFound(config_type.into()));
+        }
+
+        let map_err = |e| StorageBackendError::CouldNotReadDirectory {
+            io_error: e,
+            path: config_type_dir_path.clone(),
+        };
+        let mut names = Vec::new();
+        for dir_entry in std::fs::read_dir(&config_type_dir_path).map_err(map_err)? {
+            let dir_entry = dir_entry.map_err(map_err)?;
+            if dir_entry.file_type().map_err(map_err)?.is_file() {
+                names.push(dir_entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+
+        Ok(names)
+    }
+
+    fn read_config_type_file(
+        &self,
+        config_type: &str,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        verify_name(name)?;
+        let config_type_dir_path = self.config_type_dir_path(config_type)?;
+        if !config_type_dir_path.is_dir() {
+            return Err(StorageBackendError::ConfigTypeNotFound(config_type.into()));
+        }
+
+        read_file_if_exists(&config_type_dir_path.join(name))
+    }
+
+    fn config_type_file_location(&self, config_type: &str, name: &str) -> String {
+        self.directories
+            .config_type_dir_path(config_type)
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn labels(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError> {
+        let config_type_dir_path = self.config_type_dir_path(config_type)?;
+        if !config_type_dir_path.is_dir() {
+            return Err(StorageBackendError::ConfigTypeNotFound(config_type.into()));
+        }
+
+        Ok(self
+            .sub_directory_names(&config_type_dir_path)?
+            .into_iter()
+            .filter(|label| !Directories::is_reserved_label_name(label))
+            .collect())
+    }
+
+    fn has_label(&self, config_type: &str, label: &str) -> Result<bool, StorageBackendError> {
+        if Directories::is_reserved_label_name(label) {
+            return Ok(false);
+        }
+
+        Ok(self
+            .labeled_config_type_dir_path(config_type, label)?
+            .is_dir())
+    }
+
+    fn label_files(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<PathBuf>, StorageBackendError> {
+        let label_dir_path = self.existing_label_dir_path(config_type, label)?;
+        match self.read_manifest(&label_dir_path)? {
+            Some(manifest) => Ok(manifest.files().keys().cloned().collect()),
+            None => self.legacy_label_files(&label_dir_path),
+        }
+    }
+
+    fn read_label_file(
+        &self,
+        config_type: &str,
+        label: &str,
+        path: &Path,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        verify_relative_path(path)?;
+        let label_dir_path = self.existing_label_dir_path(config_type, label)?;
+        match self.read_manifest(&label_dir_path)? {
+            Some(manifest) => manifest
+                .object_id(path)
+                .map(|id| self.object_store().get(id))
+                .transpose(),
+            None => read_file_if_exists(&label_dir_path.join(path)),
+        }
+    }
+
+    fn label_revisions(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<LabelRevision>, StorageBackendError> {
+        let label_dir_path = self.existing_label_dir_path(config_type, label)?;
+        let mut revisions = self.read_revisions(&label_dir_path)?;
+        if revisions.is_empty() {
+            revisions.insert(
+                1,
+                initial_revision(&label_dir_path, LabelManifest::default()),
+            );
+        }
+
+        Ok(revisions
+            .into_iter()
+            .map(|(number, revision)| {
+                LabelRevision::new(number, revision.created_at, revision.message)
+            })
+            .collect())
+    }
+
+    fn read_label_revision(
+        &self,
+        config_type: &str,
+        label: &str,
+        revision: u32,
+    ) -> Result<LabelFiles, StorageBackendError> {
+        let label_dir_path = self.existing_label_dir_path(config_type, label)?;
+        let revisions = self.read_revisions(&label_dir_path)?;
+        if revisions.is_empty() && revision == 1 {
+            return self.read_label(config_type, label);
+        }
+
+        let Some(revision_file) = revisions.get(&revision) else {
+            return Err(StorageBackendError::RevisionNotFound {
+                config_type: config_type.into(),
+                label: label.into(),
+                revision,
+            });
+        };
+
+        let object_store = self.object_store();
+        let mut files = LabelFiles::new();
+        for (path, id) in revision_file.files.files() {
+            files.insert(path.clone(), object_store.get(id)?);
+        }
+
+        Ok(files)
+    }
+
+    fn profiles(&self) -> Result<Vec<String>, StorageBackendError> {
+        let profiles_dir_path = self.directories.profiles_dir_path();
+        let map_err = |e| StorageBackendError::CouldNotReadDirectory {
+            io_error: e,
+            path: profiles_dir_path.to_path_buf(),
+        };
+
+        let mut profiles = Vec::new();
+        for dir_entry in std::fs::read_dir(profiles_dir_path).map_err(map_err)? {
+            if let Some(profile) = Directories::profile_name(dir_entry.map_err(map_err)?.path()) {
+                profiles.push(profile);
+            }
+        }
+        profiles.sort();
+
+        Ok(profiles)
+    }
+
+    fn read_profile(&self, profile: &str) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        read_file_if_exists(&self.profile_path(profile)?)
+    }
+
+    fn commit(&self, transaction: StorageTransaction) -> Result<(), StorageBackendError> {
+        let staging_dir = self.directories.create_temp_dir_path();
+        create_new_directory(&staging_dir).map_err(|e| {
+            StorageBackendError::CouldNotCreateTempDirectory {
+                io_error: e,
+                path: staging_dir.clone(),
+            }
+        })?;
+
+        let message = match &self.git_repository {
+            Some(_) => Some(self.commit_message(transaction.operations())?),
+            None => None,
+        };
+
+        let mut load_transaction = LoadTransaction::new(&staging_dir);
+        let mut staged_labels = BTreeMap::new();
+        let result = transaction
+            .into_operations()
+            .into_iter()
+            .try_for_each(|operation| {
+                self.stage(operation, &mut load_transaction, &mut staged_labels)
+            })
+            .and_then(|_| Ok(load_transaction.commit()?));
+
+        if let Err(e) = remove_dir_all(&staging_dir) {
+            log::warn!(
+                "could not remove staging directory, path = {:?}, error = {}",
+                staging_dir,
+                e
+            );
+        }
+        result?;
+
+        if let (Some(git_repository), Some(message)) = (&self.git_repository, message) {
+            git_repository.commit_all(&message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the objects none of the label manifests refers to. Must not run while another
+    /// process stores labels, as their objects are written before the manifests.
+    fn collect_garbage(&self) -> Result<GarbageCollection, StorageBackendError> {
+        let mut referenced_ids = BTreeSet::new();
+        for config_type in self.config_types()? {
+            for label in self.labels(&config_type)? {
+                let label_dir_path = self.labeled_config_type_dir_path(&config_type, &label)?;
+                if let Some(manifest) = self.read_manifest(&label_dir_path)? {
+                    referenced_ids.extend(manifest.files().values().cloned());
+                }
+                for revision in self.read_revisions(&label_dir_path)?.into_values() {
+                    referenced_ids.extend(revision.files.files().values().cloned());
+                }
+            }
+        }
+
+        let object_store = self.object_store();
+        let mut removed_objects = 0;
+        let mut freed_bytes = 0;
+        for id in object_store.ids()?.difference(&referenced_ids) {
+            freed_bytes += object_store.remove(id)?;
+            removed_objects += 1;
+        }
+
+        if let Some(git_repository) = &self.git_repository {
+            if removed_objects > 0 {
+                git_repository.commit_all(&format!(
+                    "Collect garbage, removed {} objects",
+                    removed_objects
+                ))?;
+            }
+        }
+
+        Ok(GarbageCollection::new(removed_objects, freed_bytes))
+    }
+}
+
+/// Names of config types, labels and config type files become a single directory entry.
+fn verify_name(name: &str) -> Result<(), StorageBackendError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(StorageBackendError::InvalidName(name.into())),
+    }
+}
+
+fn verify_relative_path(path: &Path) -> Result<(), StorageBackendError> {
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(StorageBackendError::InvalidPath(path.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+fn read_file_if_exists(path: &Path) -> Result<Option<Vec<u8>>, StorageBackendError> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    std::fs::read(path)
+        .map(Some)
+        .map_err(|e| StorageBackendError::CouldNotReadFile {
+            io_error: e,
+            path: path.to_path_buf(),
+        })
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), StorageBackendError> {
+    if let Some(parent_path) = path.parent() {
+        ensure_directory(parent_path, true)?;
+    }
+
+    File::create(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| StorageBackendError::CouldNotWriteFile {
+            io_error: e,
+            path: path.to_path_buf(),
+        })
+}
+
+fn write_label_state(label_dir_path: &Path, state: &LabelState) -> Result<(), StorageBackendError> {
+    let content = state
+        .manifest
+        .to_vec()
+        .map_err(StorageBackendError::CouldNotSerializeLabel)?;
+    write_file(&label_dir_path.join(LABEL_MANIFEST_FILENAME), &content)?;
+
+    for (number, revision) in &state.revisions {
+        let content = serde_json::to_vec_pretty(revision)
+            .map_err(StorageBackendError::CouldNotSerializeLabel)?;
+        let revision_path = label_dir_path
+            .join(LABEL_HISTORY_DIRNAME)
+            .join(format!("{}.json", number));
+        write_file(&revision_path, &content)?;
+    }
+
+    Ok(())
+}
+
+/// The only revision of a label stored before revisions were kept, dated when the label directory
+/// was last written.
+fn initial_revision(label_dir_path: &Path, manifest: LabelManifest) -> RevisionFile {
+    let created_at = label_dir_path
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+
+    RevisionFile {
+        created_at,
+        message: None,
+        files: manifest,
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+use crate::error::GitError;
+
+/// Files of the storage root that only make sense on the machine they were written on.
+const GITIGNORE: &str = "/temp/\n/state/\n/trash/\n/settings.json\n/variables.json\n";
+
+/// The paths of the storage root that are committed, anything else in the work tree is left
+/// alone, even without the `.gitignore` (e.g. if it was removed or the repository was not made by
+/// `init`).
+const COMMITTED_PATHS: &[&str] = &[".gitignore", "db", "objects", "profiles"];
+
+/// The storage root as a git repository, driven through the `git` command, so the user's git
+/// configuration (e.g. credentials of the remotes) applies.
+pub struct GitRepository {
+    work_dir: PathBuf,
+}
+
+impl GitRepository {
+    /// Returns `None` if `work_dir` is not the root of a git repository.
+    pub fn open(work_dir: impl Into<PathBuf>) -> Option<Self> {
+        let work_dir = work_dir.into();
+        if !work_dir.join(".git").exists() {
+            return None;
+        }
+
+        Some(Self { work_dir })
+    }
+
+    /// Makes `work_dir` a git repository, ignoring the machine specific files.
+    pub fn init(work_dir: impl Into<PathBuf>) -> Result<Self, GitError> {
+        let repository = Self {
+            work_dir: work_dir.into(),
+        };
+        repository.run(&["init", "--quiet"])?;
+
+        let gitignore_path = repository.work_dir.join(".gitignore");
+        if !gitignore_path.exists() {
+            std::fs::write(&gitignore_path, GITIGNORE).map_err(|e| {
+                GitError::CouldNotWriteGitignore {
+                    io_error: e,
+                    path: gitignore_path,
+                }
+            })?;
+        }
+
+        Ok(repository)
+    }
+
+    /// Commits every change of the committed paths, does nothing if there is none. Returns
+    /// whether a commit was made.
+    pub fn commit_all(&self, message: &str) -> Result<bool, GitError> {
+        // git fails on paths which neither exist nor are tracked
+        let mut paths = Vec::new();
+        for path in COMMITTED_PATHS {
+            if self.work_dir.join(path).exists() || !self.run(&["ls-files", "--", path])?.is_empty()
+            {
+                paths.push(*path);
+            }
+        }
+        if paths.is_empty() {
+            return Ok(false);
+        }
+
+        self.run(&[&["add", "--all", "--"], paths.as_slice()].concat())?;
+        if self
+            .run(&[&["status", "--porcelain", "--"], paths.as_slice()].concat())?
+            .trim()
+            .is_empty()
+        {
+            return Ok(false);
+        }
+
+        let mut args = Vec::new();
+        // commits are still made on machines without a configured git identity
+        if self.run(&["config", "user.email"]).is_err() {
+            args.extend([
+                "-c",
+                "user.name=config-picker",
+                "-c",
+                "user.email=config-picker@localhost",
+            ]);
+        }
+        args.extend(["commit", "--quiet", "--message", message, "--"]);
+        args.extend(&paths);
+        self.run(&args)?;
+
+        Ok(true)
+    }
+
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<(), GitError> {
+        self.run(&["remote", "add", name, url])?;
+        Ok(())
+    }
+
+    pub fn push(&self, remote: &str) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        self.run(&["push", "--quiet", "--set-upstream", remote, &branch])?;
+        Ok(())
+    }
+
+    /// Rebases the local commits onto the branch of the remote. A pull that cannot be completed
+    /// (e.g. because of a conflict) is aborted, so the work tree is left as it was.
+    pub fn pull(&self, remote: &str) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        if let Err(e) = self.run(&["pull", "--quiet", "--rebase", remote, &branch]) {
+            if self.work_dir.join(".git").join("rebase-merge").exists()
+                || self.work_dir.join(".git").join("rebase-apply").exists()
+            {
+                if let Err(abort_error) = self.run(&["rebase", "--abort"]) {
+                    log::error!("could not abort rebase, error = {}", abort_error);
+                }
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn current_branch(&self) -> Result<String, GitError> {
+        Ok(self
+            .run(&["symbolic-ref", "--short", "HEAD"])?
+            .trim()
+            .to_string())
+    }
+
+    /// Runs git in the work tree, returns its standard output.
+    fn run(&self, args: &[&str]) -> Result<String, GitError> {
+        let command = format!("git {}", args.join(" "));
+        let Output {
+            status,
+            stdout,
+            stderr,
+        } = Command::new("git")
+            .arg("-C")
+            .arg(&self.work_dir)
+            .args(args)
+            .output()
+            .map_err(|e| GitError::CouldNotRunGit {
+                io_error: e,
+                command: command.clone(),
+            })?;
+
+        if !status.success() {
+            return Err(GitError::CommandFailed {
+                command,
+                status: status.to_string(),
+                stderr: String::from_utf8_lossy(&stderr).trim().to_string(),
+            });
+        }
+
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    fn temp_dir() -> TempDir {
+        TempDir::create(
+            std::env::temp_dir().join(format!("config-picker-test-{}", uuid::Uuid::new_v4())),
+        )
+        .unwrap()
+    }
+
+    fn write(dir: &TempDir, path: &str, content: &str) {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn git(args: &[&str]) -> String {
+        let output = Command::new("git").args(args).output().unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn only_the_committed_paths_are_pushed() {
+        let remote_dir = temp_dir();
+        git(&[
+            "init",
+            "--quiet",
+            "--bare",
+            remote_dir.path().to_str().unwrap(),
+        ]);
+        let work_dir = temp_dir();
+        let repository = GitRepository::init(work_dir.path()).unwrap();
+        repository
+            .add_remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+
+        write(&work_dir, "db/t/descriptor.json", "{}");
+        write(&work_dir, "settings.json", "{}");
+        assert!(repository.commit_all("Create t").unwrap());
+
+        // files of the machine are not committed even without the .gitignore
+        std::fs::remove_file(work_dir.path().join(".gitignore")).unwrap();
+        write(&work_dir, "state/t/last_loaded.json", "{}");
+        write(&work_dir, "notes.txt", "");
+        write(&work_dir, "profiles/work.json", "{}");
+        assert!(repository.commit_all("Create work").unwrap());
+        assert!(!repository.commit_all("Nothing").unwrap());
+        repository.push("origin").unwrap();
+
+        let pushed_files = git(&[
+            "--git-dir",
+            remote_dir.path().to_str().unwrap(),
+            "ls-tree",
+            "-r",
+            "--name-only",
+            "HEAD",
+        ]);
+        assert_eq!(
+            pushed_files.lines().collect::<Vec<_>>(),
+            ["db/t/descriptor.json", "profiles/work.json"]
+        );
+    }
+}
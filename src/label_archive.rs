@@ -0,0 +1,566 @@
+use std::{
+    collections::BTreeMap,
+    fs::{remove_file, File},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{error::ArchiveError, storage_backend::LabelFiles};
+
+const ARCHIVE_MANIFEST_FILENAME: &str = "config-picker-archive.json";
+const ARCHIVE_VERSION: u32 = 1;
+const CONFIG_TYPE_DIRNAME: &str = "config_type";
+const LABELS_DIRNAME: &str = "labels";
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ArchiveFormat {
+    /// A gzip compressed tar file.
+    #[default]
+    TarGz,
+
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Files ending with `.zip` are zip archives, anything else is a tar.gz archive.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("zip") => Self::Zip,
+            _ => Self::TarGz,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::TarGz => "tar.gz",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+/// Describes the content of an archive, stored as its first entry.
+#[derive(Serialize, Deserialize)]
+struct ArchiveManifest {
+    version: u32,
+    config_type: String,
+    labels: Vec<String>,
+    exported_at: DateTime<Utc>,
+    hostname: String,
+}
+
+/// A config type with its own files (e.g. the descriptor) and some of its labels, in a single
+/// file which can be moved to another machine.
+///
+/// The archive has a `config-picker-archive.json` manifest, the files of the config type under
+/// `config_type/` and the files of every label (including its metadata) under `labels/<label>/`.
+pub struct LabelArchive {
+    config_type: String,
+    config_type_files: BTreeMap<String, Vec<u8>>,
+    labels: BTreeMap<String, LabelFiles>,
+}
+
+impl LabelArchive {
+    pub fn new(
+        config_type: impl Into<String>,
+        config_type_files: BTreeMap<String, Vec<u8>>,
+        labels: BTreeMap<String, LabelFiles>,
+    ) -> Self {
+        Self {
+            config_type: config_type.into(),
+            config_type_files,
+            labels,
+        }
+    }
+
+    pub fn config_type(&self) -> &str {
+        &self.config_type
+    }
+
+    pub fn config_type_files(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.config_type_files
+    }
+
+    pub fn labels(&self) -> &BTreeMap<String, LabelFiles> {
+        &self.labels
+    }
+
+    pub fn into_parts(
+        self,
+    ) -> (
+        String,
+        BTreeMap<String, Vec<u8>>,
+        BTreeMap<String, LabelFiles>,
+    ) {
+        (self.config_type, self.config_type_files, self.labels)
+    }
+
+    /// Writes the archive to `path`, a partially written archive is removed.
+    pub fn write(&self, path: impl AsRef<Path>, format: ArchiveFormat) -> Result<(), ArchiveError> {
+        let path = path.as_ref();
+        let manifest = self.manifest()?;
+        let entries = self.entries(&manifest)?;
+
+        let file = File::create(path).map_err(|e| ArchiveError::CouldNotWriteArchive {
+            io_error: e,
+            path: path.to_path_buf(),
+        })?;
+        let written = match format {
+            ArchiveFormat::TarGz => write_tar_gz(path, file, &entries),
+            ArchiveFormat::Zip => write_zip(path, file, &entries),
+        };
+        if written.is_err() {
+            let _ = remove_file(path);
+        }
+
+        written
+    }
+
+    /// Reads and validates an archive, its format is recognized by its content.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ArchiveError> {
+        let path = path.as_ref();
+        let map_err = |e| ArchiveError::CouldNotReadArchive {
+            io_error: e,
+            path: path.to_path_buf(),
+        };
+
+        let mut file = File::open(path).map_err(map_err)?;
+        let mut magic = [0; 4];
+        let magic_len = file.read(&mut magic).map_err(map_err)?;
+        file.seek(SeekFrom::Start(0)).map_err(map_err)?;
+
+        let entries = if magic[..magic_len].starts_with(ZIP_MAGIC) {
+            read_zip(path, file)?
+        } else if magic[..magic_len].starts_with(GZIP_MAGIC) {
+            read_tar_gz(path, file)?
+        } else {
+            return Err(ArchiveError::UnknownFormat(path.to_path_buf()));
+        };
+
+        Self::from_entries(path, entries)
+    }
+
+    fn manifest(&self) -> Result<Vec<u8>, ArchiveError> {
+        let manifest = ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            config_type: self.config_type.clone(),
+            labels: self.labels.keys().cloned().collect(),
+            exported_at: Utc::now(),
+            hostname: gethostname::gethostname().to_string_lossy().to_string(),
+        };
+
+        serde_json::to_vec_pretty(&manifest).map_err(ArchiveError::CouldNotSerializeManifest)
+    }
+
+    /// Returns the names and contents of the entries, the manifest first.
+    fn entries<'a>(&'a self, manifest: &'a [u8]) -> Result<Vec<(String, &'a [u8])>, ArchiveError> {
+        let mut entries = vec![(ARCHIVE_MANIFEST_FILENAME.to_string(), manifest)];
+        for (name, content) in &self.config_type_files {
+            entries.push((
+                format!("{}/{}", CONFIG_TYPE_DIRNAME, name),
+                content.as_slice(),
+            ));
+        }
+        for (label, files) in &self.labels {
+            for (path, content) in files {
+                entries.push((
+                    format!("{}/{}/{}", LABELS_DIRNAME, label, entry_name(path)?),
+                    content.as_slice(),
+                ));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn from_entries(
+        path: &Path,
+        mut entries: BTreeMap<String, Vec<u8>>,
+    ) -> Result<Self, ArchiveError> {
+        let manifest = entries
+            .remove(ARCHIVE_MANIFEST_FILENAME)
+            .ok_or_else(|| ArchiveError::MissingManifest(path.to_path_buf()))?;
+        let manifest: ArchiveManifest =
+            serde_json::from_slice(&manifest).map_err(|e| ArchiveError::InvalidManifest {
+                error: e,
+                path: path.to_path_buf(),
+            })?;
+        if manifest.version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion {
+                version: manifest.version,
+                path: path.to_path_buf(),
+            });
+        }
+
+        verify_name(&manifest.config_type)?;
+        let mut labels = BTreeMap::new();
+        for label in manifest.labels {
+            verify_name(&label)?;
+            labels.insert(label, LabelFiles::new());
+        }
+
+        let mut config_type_files = BTreeMap::new();
+        for (name, content) in entries {
+            let unexpected_entry = || ArchiveError::UnexpectedEntry {
+                name: name.clone(),
+                path: path.to_path_buf(),
+            };
+
+            let mut components = name.split('/');
+            match components.next() {
+                Some(CONFIG_TYPE_DIRNAME) => {
+                    let (Some(file_name), None) = (components.next(), components.next()) else {
+                        return Err(unexpected_entry());
+                    };
+                    verify_name(file_name).map_err(|_| unexpected_entry())?;
+                    config_type_files.insert(file_name.to_string(), content);
+                }
+                Some(LABELS_DIRNAME) => {
+                    let files = components
+                        .next()
+                        .and_then(|label| labels.get_mut(label))
+                        .ok_or_else(unexpected_entry)?;
+                    let file_path: PathBuf = components.collect();
+                    if file_path.as_os_str().is_empty()
+                        || !file_path
+                            .components()
+                            .all(|component| matches!(component, Component::Normal(_)))
+                    {
+                        return Err(unexpected_entry());
+                    }
+                    files.insert(file_path, content);
+                }
+                _ => return Err(unexpected_entry()),
+            }
+        }
+
+        Ok(Self {
+            config_type: manifest.config_type,
+            config_type_files,
+            labels,
+        })
+    }
+}
+
+/// Entry names are separated by `/` on every platform.
+fn entry_name(path: &Path) -> Result<String, ArchiveError> {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => name
+                .to_str()
+                .ok_or_else(|| ArchiveError::InvalidPath(path.to_path_buf())),
+            _ => Err(ArchiveError::InvalidPath(path.to_path_buf())),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|names| names.join("/"))
+}
+
+/// Names of config types, labels and config type files become a single directory entry.
+fn verify_name(name: &str) -> Result<(), ArchiveError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(ArchiveError::InvalidName(name.into())),
+    }
+}
+
+fn write_tar_gz(path: &Path, file: File, entries: &[(String, &[u8])]) -> Result<(), ArchiveError> {
+    let map_err = |e| ArchiveError::CouldNotWriteArchive {
+        io_error: e,
+        path: path.to_path_buf(),
+    };
+
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mtime = Utc::now().timestamp().max(0) as u64;
+    for (name, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        builder
+            .append_data(&mut header, name, *content)
+            .map_err(map_err)?;
+    }
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|mut file| file.flush())
+        .map_err(map_err)
+}
+
+fn write_zip(path: &Path, file: File, entries: &[(String, &[u8])]) -> Result<(), ArchiveError> {
+    let map_err = |e| ArchiveError::CouldNotWriteZipArchive {
+        zip_error: e,
+        path: path.to_path_buf(),
+    };
+
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, content) in entries {
+        writer.start_file(name.as_str(), options).map_err(map_err)?;
+        writer.write_all(content).map_err(|e| map_err(e.into()))?;
+    }
+    writer.finish().map_err(map_err)?;
+
+    Ok(())
+}
+
+fn insert_entry(
+    path: &Path,
+    entries: &mut BTreeMap<String, Vec<u8>>,
+    name: String,
+    content: Vec<u8>,
+) -> Result<(), ArchiveError> {
+    if entries.contains_key(&name) {
+        return Err(ArchiveError::DuplicateEntry {
+            name,
+            path: path.to_path_buf(),
+        });
+    }
+    entries.insert(name, content);
+
+    Ok(())
+}
+
+fn read_tar_gz(path: &Path, file: File) -> Result<BTreeMap<String, Vec<u8>>, ArchiveError> {
+    let map_err = |e| ArchiveError::CouldNotReadArchive {
+        io_error: e,
+        path: path.to_path_buf(),
+    };
+
+    let mut entries = BTreeMap::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries().map_err(map_err)? {
+        let mut entry = entry.map_err(map_err)?;
+        let name = entry.path().map_err(map_err)?.to_string_lossy().to_string();
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            _ => {
+                return Err(ArchiveError::UnexpectedEntry {
+                    name,
+                    path: path.to_path_buf(),
+                })
+            }
+        }
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).map_err(map_err)?;
+        insert_entry(path, &mut entries, name, content)?;
+    }
+
+    Ok(entries)
+}
+
+fn read_zip(path: &Path, file: File) -> Result<BTreeMap<String, Vec<u8>>, ArchiveError> {
+    let map_err = |e| ArchiveError::CouldNotReadZipArchive {
+        zip_error: e,
+        path: path.to_path_buf(),
+    };
+
+    let mut entries = BTreeMap::new();
+    let mut archive = ZipArchive::new(file).map_err(map_err)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(map_err)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        if entry.is_symlink() {
+            return Err(ArchiveError::UnexpectedEntry {
+                name,
+                path: path.to_path_buf(),
+            });
+        }
+
+        let mut content = Vec::new();
+        entry
+            .read_to_end(&mut content)
+            .map_err(|e| map_err(e.into()))?;
+        insert_entry(path, &mut entries, name, content)?;
+    }
+
+    Ok(entries)
+}
+
+/// What `ConfigStorage::import` did with the labels of an archive.
+pub struct ImportSummary {
+    config_type: String,
+    created_config_type: bool,
+    imported_labels: BTreeMap<String, String>,
+    skipped_labels: Vec<String>,
+}
+
+impl ImportSummary {
+    pub(crate) fn new(
+        config_type: impl Into<String>,
+        created_config_type: bool,
+        imported_labels: BTreeMap<String, String>,
+        skipped_labels: Vec<String>,
+    ) -> Self {
+        Self {
+            config_type: config_type.into(),
+            created_config_type,
+            imported_labels,
+            skipped_labels,
+        }
+    }
+
+    pub fn config_type(&self) -> &str {
+        &self.config_type
+    }
+
+    /// Whether the config type did not exist and was created from the archive.
+    pub fn created_config_type(&self) -> bool {
+        self.created_config_type
+    }
+
+    /// The imported labels of the archive, with the names they are stored under.
+    pub fn imported_labels(&self) -> &BTreeMap<String, String> {
+        &self.imported_labels
+    }
+
+    /// The labels of the archive which already existed and were left as they were.
+    pub fn skipped_labels(&self) -> &[String] {
+        &self.skipped_labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    fn temp_dir() -> TempDir {
+        TempDir::create(
+            std::env::temp_dir().join(format!("config-picker-test-{}", uuid::Uuid::new_v4())),
+        )
+        .unwrap()
+    }
+
+    fn manifest(labels: &[&str]) -> Vec<u8> {
+        serde_json::to_vec(&ArchiveManifest {
+            version: ARCHIVE_VERSION,
+            config_type: "t".into(),
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            exported_at: Utc::now(),
+            hostname: "host".into(),
+        })
+        .unwrap()
+    }
+
+    /// Writes a zip archive with the entries as they are, like an archive made by another tool.
+    fn write_raw_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir();
+        let labels = BTreeMap::from([(
+            "l1".to_string(),
+            LabelFiles::from([
+                (PathBuf::from("label.json"), b"{}".to_vec()),
+                (
+                    PathBuf::from("{{HOME}}/.config/app.toml"),
+                    b"a = 1".to_vec(),
+                ),
+            ]),
+        )]);
+        let archive = LabelArchive::new(
+            "t",
+            BTreeMap::from([("descriptor.json".to_string(), b"{}".to_vec())]),
+            labels.clone(),
+        );
+
+        for format in [ArchiveFormat::TarGz, ArchiveFormat::Zip] {
+            let path = dir.path().join(format!("t.{}", format.extension()));
+            archive.write(&path, format).unwrap();
+
+            let read_archive = LabelArchive::read(&path).unwrap();
+            assert_eq!(read_archive.config_type(), "t");
+            assert_eq!(
+                read_archive.config_type_files(),
+                archive.config_type_files()
+            );
+            assert_eq!(read_archive.labels(), &labels);
+        }
+    }
+
+    #[test]
+    fn entries_outside_of_a_label_are_rejected() {
+        let dir = temp_dir();
+        let path = dir.path().join("t.zip");
+        let manifest = manifest(&["l1"]);
+        write_raw_zip(
+            &path,
+            &[
+                (ARCHIVE_MANIFEST_FILENAME, &manifest),
+                ("labels/l1/../../../.bashrc", b"evil"),
+            ],
+        );
+
+        assert!(matches!(
+            LabelArchive::read(&path),
+            Err(ArchiveError::UnexpectedEntry { .. })
+        ));
+    }
+
+    #[test]
+    fn entries_of_unlisted_labels_are_rejected() {
+        let dir = temp_dir();
+        let path = dir.path().join("t.zip");
+        let manifest = manifest(&["l1"]);
+        write_raw_zip(
+            &path,
+            &[
+                (ARCHIVE_MANIFEST_FILENAME, &manifest),
+                ("labels/l2/label.json", b"{}"),
+            ],
+        );
+
+        assert!(matches!(
+            LabelArchive::read(&path),
+            Err(ArchiveError::UnexpectedEntry { .. })
+        ));
+    }
+
+    #[test]
+    fn label_names_with_paths_are_rejected() {
+        let dir = temp_dir();
+        let path = dir.path().join("t.zip");
+        let manifest = manifest(&["../l1"]);
+        write_raw_zip(&path, &[(ARCHIVE_MANIFEST_FILENAME, &manifest)]);
+
+        assert!(matches!(
+            LabelArchive::read(&path),
+            Err(ArchiveError::InvalidName(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        let dir = temp_dir();
+        let path = dir.path().join("t.tar.gz");
+        std::fs::write(&path, "not an archive").unwrap();
+
+        assert!(matches!(
+            LabelArchive::read(&path),
+            Err(ArchiveError::UnknownFormat(_))
+        ));
+    }
+}
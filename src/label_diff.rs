@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use similar::TextDiff;
+
+use crate::{
+    directories::{LABEL_FILE_ATTRIBUTES_FILENAME, LABEL_METADATA_FILENAME},
+    error::DiffError,
+    utils::{collect_files_recursive, hash_file},
+};
+
+pub struct BinaryFileSummary {
+    size: u64,
+    hash: String,
+}
+
+impl BinaryFileSummary {
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+pub enum FileDiffContent {
+    /// Unified diff of the lines, including the `---`/`+++` header.
+    Text(String),
+    /// `None` if the file does not exist on that side.
+    Binary {
+        old: Option<BinaryFileSummary>,
+        new: Option<BinaryFileSummary>,
+    },
+}
+
+/// A file which differs between two labels, or between a label and the live files.
+pub struct FileDiff {
+    path: String,
+    content: FileDiffContent,
+}
+
+impl FileDiff {
+    /// Location of the file in the label (e.g. `{{HOME}}/.gitconfig`).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn content(&self) -> &FileDiffContent {
+        &self.content
+    }
+}
+
+/// Diffs every file under `old_dir` and `new_dir`, the names are only used in the diff headers.
+pub(crate) fn diff_directories(
+    old_name: &str,
+    old_dir: &Path,
+    new_name: &str,
+    new_dir: &Path,
+) -> Result<Vec<FileDiff>, DiffError> {
+    let collect = |dir: &Path| {
+        collect_files_recursive(dir)
+            .map(|files| {
+                files
+                    .into_iter()
+                    .filter(|file| {
+                        file.as_path() != Path::new(LABEL_METADATA_FILENAME)
+                            && file.as_path() != Path::new(LABEL_FILE_ATTRIBUTES_FILENAME)
+                    })
+                    .collect::<BTreeSet<_>>()
+            })
+            .map_err(|e| DiffError::CouldNotCollectFiles {
+                io_error: e,
+                path: dir.to_path_buf(),
+            })
+    };
+    let old_files = collect(old_dir)?;
+    let new_files = collect(new_dir)?;
+
+    let mut diffs = Vec::new();
+    for relative_file_path in old_files.union(&new_files) {
+        let old_content = read_if_contains(&old_files, old_dir, relative_file_path)?;
+        let new_content = read_if_contains(&new_files, new_dir, relative_file_path)?;
+        if old_content == new_content {
+            continue;
+        }
+
+        let path = relative_file_path.to_string_lossy().to_string();
+        let content = match (as_text(&old_content), as_text(&new_content)) {
+            (Some(old_text), Some(new_text)) => FileDiffContent::Text(
+                TextDiff::from_lines(old_text, new_text)
+                    .unified_diff()
+                    .header(
+                        &header(old_name, &path, old_content.is_some()),
+                        &header(new_name, &path, new_content.is_some()),
+                    )
+                    .to_string(),
+            ),
+            _ => FileDiffContent::Binary {
+                old: summarize(old_dir, relative_file_path, &old_content)?,
+                new: summarize(new_dir, relative_file_path, &new_content)?,
+            },
+        };
+
+        diffs.push(FileDiff { path, content });
+    }
+
+    Ok(diffs)
+}
+
+fn read_if_contains(
+    files: &BTreeSet<PathBuf>,
+    dir: &Path,
+    relative_file_path: &Path,
+) -> Result<Option<Vec<u8>>, DiffError> {
+    if !files.contains(relative_file_path) {
+        return Ok(None);
+    }
+
+    let file_path = dir.join(relative_file_path);
+    fs::read(&file_path)
+        .map(Some)
+        .map_err(|e| DiffError::CouldNotReadFile {
+            io_error: e,
+            path: file_path,
+        })
+}
+
+/// A missing file diffs as an empty text file.
+fn as_text(content: &Option<Vec<u8>>) -> Option<&str> {
+    match content {
+        Some(content) if content_inspector::inspect(content).is_binary() => None,
+        Some(content) => std::str::from_utf8(content).ok(),
+        None => Some(""),
+    }
+}
+
+fn header(name: &str, path: &str, exists: bool) -> String {
+    if exists {
+        format!("{}/{}", name, path)
+    } else {
+        "/dev/null".into()
+    }
+}
+
+fn summarize(
+    dir: &Path,
+    relative_file_path: &Path,
+    content: &Option<Vec<u8>>,
+) -> Result<Option<BinaryFileSummary>, DiffError> {
+    let Some(content) = content else {
+        return Ok(None);
+    };
+
+    let file_path = dir.join(relative_file_path);
+    let hash = hash_file(&file_path).map_err(|e| DiffError::CouldNotReadFile {
+        io_error: e,
+        path: file_path,
+    })?;
+
+    Ok(Some(BinaryFileSummary {
+        size: content.len() as u64,
+        hash,
+    }))
+}
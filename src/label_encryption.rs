@@ -0,0 +1,437 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use age::secrecy::ExposeSecret;
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{directories::DESCRIPTOR_FILENAME, error::EncryptionError};
+
+/// Encrypted label files start with one of these, so files stored before the config type was
+/// encrypted can be told apart while it is rekeyed.
+const PASSPHRASE_MAGIC: &[u8] = b"config-picker:passphrase:v1\n";
+const KEY_FILE_MAGIC: &[u8] = b"config-picker:age:v1\n";
+
+/// Encrypted with the key derived from the passphrase and kept in the descriptor, so a wrong
+/// passphrase is told apart from a damaged file.
+const KEY_CHECK: &[u8] = b"config-picker key check";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// How the files of the labels of a config type are encrypted, kept in its descriptor. The
+/// metadata of the labels (e.g. descriptions and tags) is not encrypted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Encryption {
+    /// XChaCha20-Poly1305 with a key derived from a passphrase with Argon2id.
+    Passphrase {
+        /// Hex encoded.
+        salt: String,
+
+        /// `KEY_CHECK` encrypted with the key, hex encoded.
+        key_check: String,
+    },
+
+    /// age, for the X25519 recipient of the identity in `key_file`. As anyone who knows the
+    /// recipient can encrypt for it, this keeps the files secret, but unlike the passphrase it
+    /// does not detect files replaced by someone with write access to the storage.
+    KeyFile {
+        recipient: String,
+
+        /// The path of the identity, with variables (e.g. `{{HOME}}/.config-picker.key`).
+        key_file: String,
+    },
+}
+
+/// What `ConfigTypeStorage::rekey` encrypts the label files with.
+pub enum NewEncryption {
+    /// The label files are decrypted and stored as they are.
+    None,
+
+    Passphrase(String),
+
+    /// The age identity in the file, which is generated if the file does not exist.
+    KeyFile(PathBuf),
+}
+
+/// Asks for the passphrase of a config type (given by name), e.g. on the terminal.
+pub type PassphraseProvider = Arc<dyn Fn(&str) -> std::io::Result<String> + Send + Sync>;
+
+/// Encrypts and decrypts the label files of a config type.
+pub(crate) enum LabelCipher {
+    Passphrase(XChaCha20Poly1305),
+    KeyFile {
+        recipient: age::x25519::Recipient,
+        identity: age::x25519::Identity,
+        key_file: PathBuf,
+    },
+}
+
+impl LabelCipher {
+    /// Derives the key from `passphrase` and checks it against the key check of the descriptor.
+    pub fn from_passphrase(
+        config_type: &str,
+        passphrase: &str,
+        salt: &str,
+        key_check: &str,
+    ) -> Result<Self, EncryptionError> {
+        let invalid_settings = |reason: &str| EncryptionError::InvalidSettings {
+            config_type: config_type.into(),
+            reason: reason.into(),
+        };
+        let salt = hex::decode(salt).map_err(|_| invalid_settings("salt is not hex encoded"))?;
+        let key_check =
+            hex::decode(key_check).map_err(|_| invalid_settings("key check is not hex encoded"))?;
+
+        let cipher = derive_cipher(passphrase, &salt)?;
+        match open(&cipher, Path::new(DESCRIPTOR_FILENAME), &key_check) {
+            Some(content) if content == KEY_CHECK => Ok(Self::Passphrase(cipher)),
+            _ => Err(EncryptionError::WrongPassphrase(config_type.into())),
+        }
+    }
+
+    /// Reads the identity of `key_file` and checks that it belongs to `recipient`.
+    pub fn from_key_file(
+        config_type: &str,
+        recipient: &str,
+        key_file: impl Into<PathBuf>,
+    ) -> Result<Self, EncryptionError> {
+        let key_file = key_file.into();
+        let recipient = age::x25519::Recipient::from_str(recipient).map_err(|e| {
+            EncryptionError::InvalidSettings {
+                config_type: config_type.into(),
+                reason: e.into(),
+            }
+        })?;
+
+        let identity = read_identity(&key_file)?;
+        if identity.to_public().to_string() != recipient.to_string() {
+            return Err(EncryptionError::WrongKey {
+                config_type: config_type.into(),
+                key_file,
+            });
+        }
+
+        Ok(Self::KeyFile {
+            recipient,
+            identity,
+            key_file,
+        })
+    }
+
+    /// Creates the settings and the cipher for a new passphrase, with a new salt.
+    pub fn new_passphrase(passphrase: &str) -> Result<(Encryption, Self), EncryptionError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        let cipher = derive_cipher(passphrase, &salt)?;
+        let key_check = seal(&cipher, Path::new(DESCRIPTOR_FILENAME), KEY_CHECK)?;
+
+        Ok((
+            Encryption::Passphrase {
+                salt: hex::encode(salt),
+                key_check: hex::encode(key_check),
+            },
+            Self::Passphrase(cipher),
+        ))
+    }
+
+    /// Creates the settings and the cipher for the identity in `key_file`, which is generated
+    /// (readable by the owner only) if it does not exist. `encoded_key_file` is the path as kept
+    /// in the descriptor.
+    pub fn new_key_file(
+        key_file: impl Into<PathBuf>,
+        encoded_key_file: impl Into<String>,
+    ) -> Result<(Encryption, Self), EncryptionError> {
+        let key_file = key_file.into();
+        let identity = if key_file.exists() {
+            read_identity(&key_file)?
+        } else {
+            write_new_identity(&key_file)?
+        };
+        let recipient = identity.to_public();
+
+        Ok((
+            Encryption::KeyFile {
+                recipient: recipient.to_string(),
+                key_file: encoded_key_file.into(),
+            },
+            Self::KeyFile {
+                recipient,
+                identity,
+                key_file,
+            },
+        ))
+    }
+
+    /// `path` is the path of the file in the label, the encrypted content can only be decrypted
+    /// for the same path, so encrypted files cannot be swapped.
+    pub fn encrypt(&self, path: &Path, content: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let (magic, encrypted) = match self {
+            Self::Passphrase(cipher) => (PASSPHRASE_MAGIC, seal(cipher, path, content)?),
+            Self::KeyFile { recipient, .. } => {
+                // age has no associated data, so the path is encrypted in front of the content
+                let content = [&associated_data(path), &b"\0"[..], content].concat();
+                (
+                    KEY_FILE_MAGIC,
+                    age::encrypt(recipient, &content)
+                        .map_err(|_| EncryptionError::CouldNotEncrypt(path.to_path_buf()))?,
+                )
+            }
+        };
+
+        Ok([magic, &encrypted].concat())
+    }
+
+    /// Fails on files which are not encrypted, see `is_encrypted`.
+    pub fn decrypt(&self, path: &Path, content: Vec<u8>) -> Result<Vec<u8>, EncryptionError> {
+        if let Some(encrypted) = content.strip_prefix(PASSPHRASE_MAGIC) {
+            let Self::Passphrase(cipher) = self else {
+                return Err(EncryptionError::EncryptedWithOtherMethod(
+                    path.to_path_buf(),
+                ));
+            };
+            return open(cipher, path, encrypted)
+                .ok_or_else(|| EncryptionError::CouldNotDecrypt(path.to_path_buf()));
+        }
+
+        if let Some(encrypted) = content.strip_prefix(KEY_FILE_MAGIC) {
+            let Self::KeyFile {
+                identity, key_file, ..
+            } = self
+            else {
+                return Err(EncryptionError::EncryptedWithOtherMethod(
+                    path.to_path_buf(),
+                ));
+            };
+            let decrypted = age::decrypt(identity, encrypted).map_err(|e| match e {
+                age::DecryptError::NoMatchingKeys => EncryptionError::NotEncryptedForKey {
+                    path: path.to_path_buf(),
+                    key_file: key_file.clone(),
+                },
+                _ => EncryptionError::CouldNotDecrypt(path.to_path_buf()),
+            })?;
+            return decrypted
+                .strip_prefix(associated_data(path).as_slice())
+                .and_then(|decrypted| decrypted.strip_prefix(b"\0"))
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| EncryptionError::EncryptedForOtherPath(path.to_path_buf()));
+        }
+
+        Err(EncryptionError::NotEncrypted(path.to_path_buf()))
+    }
+
+    /// Whether the content was encrypted by a cipher, files stored before the config type was
+    /// encrypted are not.
+    pub fn is_encrypted(content: &[u8]) -> bool {
+        content.starts_with(PASSPHRASE_MAGIC) || content.starts_with(KEY_FILE_MAGIC)
+    }
+}
+
+/// The path of a file in the label, with `/` separators on every platform.
+fn associated_data(path: &Path) -> Vec<u8> {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+        .into_bytes()
+}
+
+/// Encrypts with a new nonce, which is put in front of the encrypted content.
+fn seal(
+    cipher: &XChaCha20Poly1305,
+    path: &Path,
+    content: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let encrypted = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: content,
+                aad: &associated_data(path),
+            },
+        )
+        .map_err(|_| EncryptionError::CouldNotEncrypt(path.to_path_buf()))?;
+
+    Ok([nonce.as_slice(), &encrypted].concat())
+}
+
+/// Returns `None` if the content was not encrypted with the key for `path`, or was changed.
+fn open(cipher: &XChaCha20Poly1305, path: &Path, content: &[u8]) -> Option<Vec<u8>> {
+    if content.len() < NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, encrypted) = content.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: encrypted,
+                aad: &associated_data(path),
+            },
+        )
+        .ok()
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, EncryptionError> {
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| EncryptionError::CouldNotDeriveKey(e.to_string()))?;
+
+    Ok(XChaCha20Poly1305::new(key.as_ref().into()))
+}
+
+/// Reads the first identity of an age key file, as written by `age-keygen`.
+fn read_identity(key_file: &Path) -> Result<age::x25519::Identity, EncryptionError> {
+    let content = Zeroizing::new(std::fs::read_to_string(key_file).map_err(|e| {
+        EncryptionError::CouldNotReadKeyFile {
+            io_error: e,
+            path: key_file.to_path_buf(),
+        }
+    })?);
+
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .and_then(|line| age::x25519::Identity::from_str(line).ok())
+        .ok_or_else(|| EncryptionError::InvalidKeyFile(key_file.to_path_buf()))
+}
+
+fn write_new_identity(key_file: &Path) -> Result<age::x25519::Identity, EncryptionError> {
+    let identity = age::x25519::Identity::generate();
+    let content = Zeroizing::new(format!(
+        "# created: {}\n# public key: {}\n{}\n",
+        Utc::now().to_rfc3339(),
+        identity.to_public(),
+        identity.to_string().expose_secret()
+    ));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(key_file)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| EncryptionError::CouldNotWriteKeyFile {
+            io_error: e,
+            path: key_file.to_path_buf(),
+        })?;
+
+    Ok(identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempDir;
+
+    fn temp_dir() -> TempDir {
+        TempDir::create(
+            std::env::temp_dir().join(format!("config-picker-test-{}", uuid::Uuid::new_v4())),
+        )
+        .unwrap()
+    }
+
+    fn passphrase_settings(encryption: &Encryption) -> (&str, &str) {
+        match encryption {
+            Encryption::Passphrase { salt, key_check } => (salt, key_check),
+            Encryption::KeyFile { .. } => panic!("not encrypted with a passphrase"),
+        }
+    }
+
+    #[test]
+    fn passphrase_round_trip() {
+        let path = Path::new("{{HOME}}/.netrc");
+        let (encryption, cipher) = LabelCipher::new_passphrase("secret").unwrap();
+        let encrypted = cipher.encrypt(path, b"password").unwrap();
+        assert!(LabelCipher::is_encrypted(&encrypted));
+
+        let (salt, key_check) = passphrase_settings(&encryption);
+        let cipher = LabelCipher::from_passphrase("t", "secret", salt, key_check).unwrap();
+        assert_eq!(cipher.decrypt(path, encrypted).unwrap(), b"password");
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let (encryption, _) = LabelCipher::new_passphrase("secret").unwrap();
+        let (salt, key_check) = passphrase_settings(&encryption);
+
+        assert!(matches!(
+            LabelCipher::from_passphrase("t", "other", salt, key_check),
+            Err(EncryptionError::WrongPassphrase(_))
+        ));
+    }
+
+    #[test]
+    fn key_file_round_trip_and_wrong_key() {
+        let dir = temp_dir();
+        let path = Path::new("{{HOME}}/.netrc");
+        let key_file = dir.path().join("key.txt");
+        let (encryption, cipher) = LabelCipher::new_key_file(&key_file, "key.txt").unwrap();
+        let encrypted = cipher.encrypt(path, b"password").unwrap();
+
+        let Encryption::KeyFile { recipient, .. } = &encryption else {
+            panic!("not encrypted with a key file");
+        };
+        let cipher = LabelCipher::from_key_file("t", recipient, &key_file).unwrap();
+        assert_eq!(
+            cipher.decrypt(path, encrypted.clone()).unwrap(),
+            b"password"
+        );
+
+        let other_key_file = dir.path().join("other.txt");
+        let (_, other_cipher) = LabelCipher::new_key_file(&other_key_file, "other.txt").unwrap();
+        assert!(matches!(
+            LabelCipher::from_key_file("t", recipient, &other_key_file),
+            Err(EncryptionError::WrongKey { .. })
+        ));
+        assert!(matches!(
+            other_cipher.decrypt(path, encrypted),
+            Err(EncryptionError::NotEncryptedForKey { .. })
+        ));
+    }
+
+    #[test]
+    fn encrypted_files_cannot_be_swapped() {
+        let dir = temp_dir();
+        let (_, passphrase_cipher) = LabelCipher::new_passphrase("secret").unwrap();
+        let (_, key_file_cipher) =
+            LabelCipher::new_key_file(dir.path().join("key.txt"), "key.txt").unwrap();
+
+        for cipher in [passphrase_cipher, key_file_cipher] {
+            let encrypted = cipher
+                .encrypt(Path::new("{{HOME}}/.netrc"), b"password")
+                .unwrap();
+            assert!(cipher
+                .decrypt(Path::new("{{HOME}}/.gitconfig"), encrypted)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn plaintext_is_rejected() {
+        let (_, cipher) = LabelCipher::new_passphrase("secret").unwrap();
+
+        assert!(matches!(
+            cipher.decrypt(Path::new("{{HOME}}/.netrc"), b"password".to_vec()),
+            Err(EncryptionError::NotEncrypted(_))
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// The files of a label and the ids of the objects holding their contents, stored as
+/// `manifest.json` in the label directory.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LabelManifest {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl LabelManifest {
+    pub fn from_slice(content: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(content)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    pub fn files(&self) -> &BTreeMap<PathBuf, String> {
+        &self.files
+    }
+
+    pub fn object_id(&self, path: &Path) -> Option<&str> {
+        self.files.get(path).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, object_id: impl Into<String>) {
+        self.files.insert(path.into(), object_id.into());
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::BTreeSet, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ReadJsonFileError, WriteJsonFileError},
+    utils::{read_json_file, write_json_file},
+};
+
+/// Describes a label, stored as `label.json` in the label directory.
+#[derive(Serialize, Deserialize)]
+pub struct LabelMetadata {
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// The machine the label was last stored on.
+    hostname: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+}
+
+impl Default for LabelMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LabelMetadata {
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            created_at: now,
+            updated_at: now,
+            hostname: gethostname::gethostname().to_string_lossy().to_string(),
+            description: None,
+            tags: BTreeSet::new(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadJsonFileError> {
+        read_json_file(path)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), WriteJsonFileError> {
+        write_json_file(self, path)
+    }
+
+    pub fn from_slice(content: &[u8]) -> Result<Self, ReadJsonFileError> {
+        Ok(serde_json::from_slice(content)?)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, WriteJsonFileError> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: impl AsRef<str>) -> bool {
+        self.tags.contains(tag.as_ref())
+    }
+
+    /// Records that the label has been stored again on this machine.
+    pub fn touch(&mut self) {
+        self.updated_at = Utc::now();
+        self.hostname = gethostname::gethostname().to_string_lossy().to_string();
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    pub fn add_tags(&mut self, tags: impl IntoIterator<Item = String>) {
+        self.tags.extend(tags);
+    }
+
+    pub fn remove_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a String>) {
+        for tag in tags {
+            self.tags.remove(tag);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+/// How a file of a config type differs between the live file system and a label.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Unchanged,
+    Modified,
+    MissingLive,
+    MissingInLabel,
+}
+
+impl Display for FileState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            FileState::Unchanged => "unchanged",
+            FileState::Modified => "modified",
+            FileState::MissingLive => "missing live",
+            FileState::MissingInLabel => "missing in label",
+        };
+        f.write_str(state)
+    }
+}
+
+/// The state of every file of a config type compared with a label.
+pub struct LabelStatus {
+    label: String,
+    files: Vec<(String, FileState)>,
+}
+
+impl LabelStatus {
+    pub fn new(label: impl Into<String>, files: Vec<(String, FileState)>) -> Self {
+        Self {
+            label: label.into(),
+            files,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Files keyed by their location in the label (e.g. `{{HOME}}/.gitconfig`), sorted.
+    pub fn files(&self) -> &[(String, FileState)] {
+        &self.files
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.files
+            .iter()
+            .all(|(_, state)| *state == FileState::Unchanged)
+    }
+}
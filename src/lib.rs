@@ -0,0 +1,57 @@
+//! Stores named sets ("labels") of configuration files per config type and switches the live
+//! files between them.
+//!
+//! ```no_run
+//! use config_picker::{ConfigStorage, VariableResolver};
+//!
+//! let base_dirs = directories::BaseDirs::new().unwrap();
+//! let root_dir = base_dirs.home_dir().join(".config-picker");
+//! let config_storage = ConfigStorage::new(VariableResolver::new(base_dirs), root_dir)?;
+//!
+//! config_storage.get_config_type_storage("git")?.load("work")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod config_storage;
+pub mod config_type_descriptor;
+mod directories;
+pub mod error;
+pub mod file_attributes;
+pub mod fs_storage_backend;
+mod git_repository;
+pub mod label_archive;
+pub mod label_diff;
+pub mod label_encryption;
+mod label_manifest;
+pub mod label_metadata;
+pub mod label_status;
+mod load_transaction;
+pub mod loaded_label_record;
+pub mod memory_storage_backend;
+mod object_store;
+mod path_patterns;
+pub mod profile;
+pub mod settings;
+pub mod storage_backend;
+mod template;
+pub mod trash;
+mod utils;
+pub mod variable_resolver;
+
+pub use config_storage::{ConfigStorage, ConfigTypeStorage};
+pub use config_type_descriptor::ConfigTypeDescriptor;
+pub use fs_storage_backend::FsStorageBackend;
+pub use memory_storage_backend::MemoryStorageBackend;
+pub use storage_backend::StorageBackend;
+pub use variable_resolver::VariableResolver;
+
+// the storage handles are shared between threads by embedding applications
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ConfigStorage>();
+    assert_send_sync::<ConfigTypeStorage>();
+    assert_send_sync::<ConfigTypeDescriptor>();
+    assert_send_sync::<VariableResolver>();
+    assert_send_sync::<FsStorageBackend>();
+    assert_send_sync::<MemoryStorageBackend>();
+};
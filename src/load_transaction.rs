@@ -0,0 +1,425 @@
+use std::{
+    ffi::OsString,
+    fs::{copy, create_dir, read_dir, remove_dir_all, remove_file, rename, File},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    error::LoadTransactionError, file_attributes::FileAttributes, utils::ensure_directory,
+};
+
+/// Replaces live files and directories with their staged versions, all or nothing.
+///
+/// The new versions are prepared in the staging directory first and moved next to their
+/// destinations, then every destination is renamed aside and the staged version is renamed into
+/// its place. The renames stay within the directory of the destination, so they work even if
+/// the staging directory is on another filesystem. If any of the renames fails, the already
+/// replaced destinations are restored. Destinations without a staged version are only moved
+/// away, i.e. removed.
+pub struct LoadTransaction {
+    id: Uuid,
+    staging_dir_path: PathBuf,
+    operations: Vec<(Option<PathBuf>, PathBuf)>,
+}
+
+impl LoadTransaction {
+    pub fn new(staging_dir_path: impl Into<PathBuf>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            staging_dir_path: staging_dir_path.into(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// The path where the next staged file or directory has to be prepared.
+    pub fn next_staged_path(&self) -> PathBuf {
+        self.staging_dir_path
+            .join(format!("staged-{}", self.operations.len()))
+    }
+
+    pub fn contains_destination(&self, dest_path: impl AsRef<Path>) -> bool {
+        self.operations
+            .iter()
+            .any(|(_, operation_dest_path)| operation_dest_path == dest_path.as_ref())
+    }
+
+    /// The staged version of `dest_path` added so far, if any.
+    pub fn staged_path(&self, dest_path: impl AsRef<Path>) -> Option<&Path> {
+        self.operations
+            .iter()
+            .rev()
+            .find(|(_, operation_dest_path)| operation_dest_path == dest_path.as_ref())
+            .and_then(|(staged_path, _)| staged_path.as_deref())
+    }
+
+    pub fn add(&mut self, staged_path: impl Into<PathBuf>, dest_path: impl Into<PathBuf>) {
+        self.operations
+            .push((Some(staged_path.into()), dest_path.into()));
+    }
+
+    pub fn add_removal(&mut self, dest_path: impl Into<PathBuf>) {
+        self.operations.push((None, dest_path.into()));
+    }
+
+    pub fn commit(self) -> Result<(), LoadTransactionError> {
+        for (_, dest_path) in &self.operations {
+            verify_writable(dest_path)?;
+        }
+
+        for (_, dest_path) in self
+            .operations
+            .iter()
+            .filter(|(staged_path, _)| staged_path.is_some())
+        {
+            let parent_path = dest_path
+                .parent()
+                .ok_or_else(|| LoadTransactionError::InvalidDestination(dest_path.clone()))?;
+            ensure_directory(parent_path, true)?;
+        }
+
+        let mut local_staged_paths = Vec::new();
+        for (index, (staged_path, dest_path)) in self.operations.iter().enumerate() {
+            let Some(staged_path) = staged_path else {
+                local_staged_paths.push(None);
+                continue;
+            };
+
+            let local_staged_path = self.sibling_path(dest_path, "staged", index)?;
+            if let Err(e) = move_path(staged_path, &local_staged_path) {
+                local_staged_paths.into_iter().flatten().for_each(discard);
+                return Err(LoadTransactionError::CouldNotMoveFile {
+                    io_error: e,
+                    source_path: staged_path.clone(),
+                    dest_path: local_staged_path,
+                });
+            }
+            local_staged_paths.push(Some(local_staged_path));
+        }
+
+        let mut replaced = Vec::new();
+        let mut operations = self.operations.iter().zip(local_staged_paths).enumerate();
+        while let Some((index, ((_, dest_path), local_staged_path))) = operations.next() {
+            let original_path = if dest_path.symlink_metadata().is_ok() {
+                let original_path = self.sibling_path(dest_path, "original", index)?;
+                if let Err(e) = rename(dest_path, &original_path) {
+                    local_staged_path.into_iter().for_each(discard);
+                    discard_remaining(operations);
+                    return Err(rollback(
+                        replaced,
+                        LoadTransactionError::CouldNotMoveFile {
+                            io_error: e,
+                            source_path: dest_path.clone(),
+                            dest_path: original_path,
+                        },
+                    ));
+                }
+                Some(original_path)
+            } else {
+                None
+            };
+
+            let Some(local_staged_path) = local_staged_path else {
+                replaced.push((dest_path, original_path));
+                continue;
+            };
+
+            if let Err(e) = rename(&local_staged_path, dest_path) {
+                let error = LoadTransactionError::CouldNotMoveFile {
+                    io_error: e,
+                    source_path: local_staged_path.clone(),
+                    dest_path: dest_path.clone(),
+                };
+                discard(local_staged_path);
+                discard_remaining(operations);
+                if let Some(original_path) = &original_path {
+                    if !restore(dest_path, original_path) {
+                        return Err(LoadTransactionError::RollbackFailed(Box::new(error)));
+                    }
+                }
+                return Err(rollback(replaced, error));
+            }
+
+            replaced.push((dest_path, original_path));
+        }
+
+        for (_, original_path) in replaced {
+            original_path.into_iter().for_each(discard);
+        }
+
+        Ok(())
+    }
+
+    /// A path next to `dest_path` to put its staged or original version, the renames between
+    /// them then do not cross filesystems.
+    fn sibling_path(
+        &self,
+        dest_path: &Path,
+        kind: &str,
+        index: usize,
+    ) -> Result<PathBuf, LoadTransactionError> {
+        let file_name = dest_path
+            .file_name()
+            .ok_or_else(|| LoadTransactionError::InvalidDestination(dest_path.to_path_buf()))?;
+
+        let mut sibling_name = OsString::from(".");
+        sibling_name.push(file_name);
+        sibling_name.push(format!(".{}-{}.{}", self.id, index, kind));
+        Ok(dest_path.with_file_name(sibling_name))
+    }
+}
+
+/// Puts the original files back in reverse order, returns the error to report.
+fn rollback(
+    replaced: Vec<(&PathBuf, Option<PathBuf>)>,
+    error: LoadTransactionError,
+) -> LoadTransactionError {
+    let mut is_restored = true;
+    for (dest_path, original_path) in replaced.into_iter().rev() {
+        let removed = if dest_path.symlink_metadata().is_err() {
+            // nothing was put in place of a removed destination
+            Ok(())
+        } else if dest_path.is_dir() {
+            remove_dir_all(dest_path)
+        } else {
+            remove_file(dest_path)
+        };
+        if let Err(e) = removed {
+            log::error!(
+                "could not remove loaded file during rollback, path = {:?}, error = {}",
+                dest_path,
+                e
+            );
+            is_restored = false;
+            continue;
+        }
+
+        if let Some(original_path) = original_path {
+            is_restored &= restore(dest_path, &original_path);
+        }
+    }
+
+    if is_restored {
+        error
+    } else {
+        LoadTransactionError::RollbackFailed(Box::new(error))
+    }
+}
+
+fn restore(dest_path: &Path, original_path: &Path) -> bool {
+    if let Err(e) = rename(original_path, dest_path) {
+        log::error!(
+            "could not restore original file, it is kept at {:?}, path = {:?}, error = {}",
+            original_path,
+            dest_path,
+            e
+        );
+        return false;
+    }
+
+    true
+}
+
+/// Removes the staged versions which were not put in place.
+fn discard_remaining<'a>(
+    operations: impl Iterator<Item = (usize, (&'a (Option<PathBuf>, PathBuf), Option<PathBuf>))>,
+) {
+    operations
+        .filter_map(|(_, (_, local_staged_path))| local_staged_path)
+        .for_each(discard);
+}
+
+/// Removes a staged version, or an original version which was replaced.
+fn discard(path: PathBuf) {
+    let removed = if path.is_dir() {
+        remove_dir_all(&path)
+    } else {
+        remove_file(&path)
+    };
+    if let Err(e) = removed {
+        log::warn!("could not remove file, path = {:?}, error = {}", path, e);
+    }
+}
+
+/// Renames `source_path`, or copies it and removes the source if it is on another filesystem.
+fn move_path(source_path: &Path, dest_path: &Path) -> Result<(), std::io::Error> {
+    match rename(source_path, dest_path) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_path(source_path, dest_path) {
+                if dest_path.symlink_metadata().is_ok() {
+                    discard(dest_path.to_path_buf());
+                }
+                return Err(e);
+            }
+
+            if source_path.is_dir() {
+                remove_dir_all(source_path)
+            } else {
+                remove_file(source_path)
+            }
+        }
+        result => result,
+    }
+}
+
+/// Copies a staged file or directory with its attributes and syncs it to the disk, so it is
+/// complete before it replaces the destination.
+fn copy_path(source_path: &Path, dest_path: &Path) -> Result<(), std::io::Error> {
+    if source_path.is_dir() {
+        create_dir(dest_path)?;
+        for entry in read_dir(source_path)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &dest_path.join(entry.file_name()))?;
+        }
+    } else {
+        copy(source_path, dest_path)?;
+    }
+
+    FileAttributes::read(source_path)?.restore(dest_path, None)?;
+    File::open(dest_path)?.sync_all()
+}
+
+/// Checks the destination, or the closest existing ancestor if it does not exist yet.
+fn verify_writable(dest_path: &Path) -> Result<(), LoadTransactionError> {
+    let existing_path = dest_path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| LoadTransactionError::InvalidDestination(dest_path.to_path_buf()))?;
+
+    let metadata =
+        existing_path
+            .metadata()
+            .map_err(|e| LoadTransactionError::CouldNotReadMetadata {
+                io_error: e,
+                path: existing_path.to_path_buf(),
+            })?;
+    if metadata.permissions().readonly() {
+        return Err(LoadTransactionError::DestinationNotWritable(
+            existing_path.to_path_buf(),
+        ));
+    }
+
+    if existing_path != dest_path {
+        return Ok(());
+    }
+
+    // the file itself is replaced by a rename, which needs write access to its directory
+    match dest_path.parent() {
+        Some(parent_path) => verify_writable(parent_path),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{fs, time::SystemTime};
+
+    use filetime::FileTime;
+
+    use super::*;
+    use crate::utils::TempDir;
+
+    /// A directory on another filesystem than the temp directory, e.g. a tmpfs, if there is one.
+    #[cfg(unix)]
+    pub(crate) fn other_filesystem_dir() -> Option<PathBuf> {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp_dir_device = std::env::temp_dir().metadata().ok()?.dev();
+        ["/dev/shm", "/run/user", "/var/tmp"]
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|path| {
+                path.metadata()
+                    .is_ok_and(|metadata| metadata.dev() != temp_dir_device)
+                    && TempDir::create(path.join(format!("config-picker-test-{}", Uuid::new_v4())))
+                        .is_ok()
+            })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn other_filesystem_dir() -> Option<PathBuf> {
+        None
+    }
+
+    fn temp_dir(parent_path: &Path) -> TempDir {
+        TempDir::create(parent_path.join(format!("config-picker-test-{}", Uuid::new_v4()))).unwrap()
+    }
+
+    fn dir_entries(path: &Path) -> Vec<String> {
+        let mut entries = read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn staged_files_on_another_filesystem_replace_the_destinations() {
+        let Some(other_filesystem_dir) = other_filesystem_dir() else {
+            eprintln!("skipped, there is no other filesystem to stage on");
+            return;
+        };
+        let staging_dir = temp_dir(&other_filesystem_dir);
+        let live_dir = temp_dir(&std::env::temp_dir());
+        fs::write(live_dir.path().join("file"), "old").unwrap();
+        fs::create_dir(live_dir.path().join("dir")).unwrap();
+        fs::write(live_dir.path().join("dir").join("old"), "old").unwrap();
+        fs::write(live_dir.path().join("removed"), "old").unwrap();
+
+        let modified_at = FileTime::from_system_time(SystemTime::UNIX_EPOCH);
+        let mut transaction = LoadTransaction::new(staging_dir.path());
+        let staged_file_path = transaction.next_staged_path();
+        fs::write(&staged_file_path, "new").unwrap();
+        filetime::set_file_mtime(&staged_file_path, modified_at).unwrap();
+        transaction.add(&staged_file_path, live_dir.path().join("file"));
+        let staged_dir_path = transaction.next_staged_path();
+        fs::create_dir(&staged_dir_path).unwrap();
+        fs::write(staged_dir_path.join("new"), "new").unwrap();
+        transaction.add(&staged_dir_path, live_dir.path().join("dir"));
+        transaction.add_removal(live_dir.path().join("removed"));
+        transaction.commit().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(live_dir.path().join("file")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            FileTime::from_last_modification_time(
+                &live_dir.path().join("file").metadata().unwrap()
+            ),
+            modified_at
+        );
+        assert_eq!(dir_entries(&live_dir.path().join("dir")), ["new"]);
+        assert_eq!(dir_entries(live_dir.path()), ["dir", "file"]);
+        assert!(dir_entries(staging_dir.path()).is_empty());
+    }
+
+    #[test]
+    fn failed_commit_keeps_the_destinations() {
+        let staging_dir = temp_dir(&other_filesystem_dir().unwrap_or_else(std::env::temp_dir));
+        let live_dir = temp_dir(&std::env::temp_dir());
+        fs::write(live_dir.path().join("first"), "old").unwrap();
+        fs::write(live_dir.path().join("second"), "old").unwrap();
+
+        let mut transaction = LoadTransaction::new(staging_dir.path());
+        let staged_path = transaction.next_staged_path();
+        fs::write(&staged_path, "new").unwrap();
+        transaction.add(&staged_path, live_dir.path().join("first"));
+        // the staged version of the second file was never prepared
+        let missing_staged_path = transaction.next_staged_path();
+        transaction.add(&missing_staged_path, live_dir.path().join("second"));
+
+        assert!(matches!(
+            transaction.commit(),
+            Err(LoadTransactionError::CouldNotMoveFile { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(live_dir.path().join("first")).unwrap(),
+            "old"
+        );
+        assert_eq!(dir_entries(live_dir.path()), ["first", "second"]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ReadJsonFileError, WriteJsonFileError},
+    utils::{read_json_file, write_json_file},
+};
+
+/// Remembers which label was loaded last for a config type, together with the hashes of the
+/// live files right after the load, so later edits of the live files can be detected.
+#[derive(Serialize, Deserialize)]
+pub struct LoadedLabelRecord {
+    label: String,
+    /// Set if an earlier revision of the label was loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<u32>,
+    loaded_at: DateTime<Utc>,
+    file_hashes: BTreeMap<String, String>,
+}
+
+impl LoadedLabelRecord {
+    pub fn new(
+        label: impl Into<String>,
+        revision: Option<u32>,
+        file_hashes: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            revision,
+            loaded_at: Utc::now(),
+            file_hashes,
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadJsonFileError> {
+        read_json_file(path)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), WriteJsonFileError> {
+        write_json_file(self, path)
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    pub fn revision(&self) -> Option<u32> {
+        self.revision
+    }
+
+    pub fn loaded_at(&self) -> DateTime<Utc> {
+        self.loaded_at
+    }
+
+    /// Hashes of the live files keyed by their (undecoded) location in the config type descriptor.
+    pub fn file_hashes(&self) -> &BTreeMap<String, String> {
+        &self.file_hashes
+    }
+}
+
+/// When each label of a config type was loaded the last time.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LabelUsage {
+    last_loaded_at: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl LabelUsage {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadJsonFileError> {
+        read_json_file(path)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), WriteJsonFileError> {
+        write_json_file(self, path)
+    }
+
+    pub fn last_loaded_at(&self, label: impl AsRef<str>) -> Option<DateTime<Utc>> {
+        self.last_loaded_at.get(label.as_ref()).copied()
+    }
+
+    pub fn set_loaded(&mut self, label: impl Into<String>, loaded_at: DateTime<Utc>) {
+        self.last_loaded_at.insert(label.into(), loaded_at);
+    }
+}
+
+/// The last loaded label of a config type and the live files that no longer match it.
+pub struct ActiveLabel {
+    record: LoadedLabelRecord,
+    drifted_paths: Vec<String>,
+}
+
+impl ActiveLabel {
+    pub fn new(record: LoadedLabelRecord, drifted_paths: Vec<String>) -> Self {
+        Self {
+            record,
+            drifted_paths,
+        }
+    }
+
+    pub fn record(&self) -> &LoadedLabelRecord {
+        &self.record
+    }
+
+    pub fn drifted_paths(&self) -> &[String] {
+        &self.drifted_paths
+    }
+
+    pub fn has_drifted(&self) -> bool {
+        !self.drifted_paths.is_empty()
+    }
+}
@@ -1,104 +1,985 @@
 mod cli;
-mod config_storage;
-mod config_type_descriptor;
-mod directories;
-mod error;
-mod utils;
-mod variable_resolver;
+mod output;
+mod passphrase;
+mod picker;
 
-use std::process::ExitCode;
+use std::{collections::BTreeMap, path::PathBuf, process::ExitCode, sync::Arc};
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
 use ::directories::BaseDirs;
-use cli::{Cli, CreateTypeParams, ListParams, LoadParams, StoreParams};
-use config_storage::ConfigStorage;
-use variable_resolver::VariableResolver;
+use cli::{
+    AddPathParams, Cli, Command, CopyLabelParams, CreateTypeParams, DescribeParams, DiffParams,
+    ExportParams, GcParams, HistoryParams, ImportParams, InitGitParams, ListParams, LoadParams,
+    PickParams, ProfileCommand, ProfileParams, PullParams, PushParams, RekeyParams,
+    RemoveLabelParams, RemovePathParams, RemoveTypeParams, RenameLabelParams, RevertParams,
+    ShowTypeParams, StatusParams, StoreParams, TagParams, UndeleteParams, UndoParams, WhichParams,
+};
+use config_picker::{
+    config_type_descriptor::LoadMode,
+    error::codes,
+    file_attributes::format_mode,
+    label_archive::ArchiveFormat,
+    label_diff::{BinaryFileSummary, FileDiffContent},
+    label_encryption::NewEncryption,
+    label_status::FileState,
+    ConfigStorage, ConfigTypeStorage, VariableResolver,
+};
+use output::{CommandError, CommandOutput, OutputFormat};
+use passphrase::{read_passphrase, NEW_PASSPHRASE_ENV, PASSPHRASE_ENV};
+use serde_json::json;
+
+type CommandResult = Result<CommandOutput, CommandError>;
 
 fn main() -> ExitCode {
+    // the logger lets everything through, the level is limited with `log::set_max_level`, so it
+    // can be changed once the settings are read
     env_logger::Builder::new()
-        .filter_level(log::LevelFilter::Info)
+        .filter_level(log::LevelFilter::Trace)
         .init();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => return exit_with_usage_error(e),
+    };
+    let output_format = cli.output;
 
-    match app_main() {
-        Ok(_) => ExitCode::SUCCESS,
+    match app_main(cli) {
+        Ok(output) => {
+            output.print(output_format);
+            ExitCode::SUCCESS
+        }
         Err(e) => {
-            log::error!("{}", e);
+            match output_format {
+                OutputFormat::Text => log::error!("{}", e),
+                OutputFormat::Json => println!("{}", e.to_json()),
+            }
             ExitCode::FAILURE
         }
     }
 }
 
-fn app_main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+/// Reports invalid arguments as a JSON error if `--output json` could still be made out of the
+/// arguments, clap prints them (and `--help` and `--version`) otherwise.
+fn exit_with_usage_error(error: clap::Error) -> ExitCode {
+    let output_format = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches()
+        .ok()
+        .and_then(|matches| matches.get_one::<OutputFormat>("output").copied());
+    if !error.use_stderr() || output_format != Some(OutputFormat::Json) {
+        error.exit();
+    }
+
+    println!("{}", CommandError::from_usage_error(&error).to_json());
+    ExitCode::from(error.exit_code() as u8)
+}
 
+fn app_main(cli: Cli) -> CommandResult {
     let base_dirs = BaseDirs::new().unwrap();
-    let mut storage_root_dir = base_dirs.home_dir().to_path_buf();
-    storage_root_dir.push(".config-picker");
-    let config_storage = ConfigStorage::new(VariableResolver::new(base_dirs), storage_root_dir)?;
+    let storage_root_dir = cli.root.unwrap_or_else(|| {
+        let mut storage_root_dir = base_dirs.home_dir().to_path_buf();
+        storage_root_dir.push(".config-picker");
+        storage_root_dir
+    });
+    let config_storage = ConfigStorage::new(VariableResolver::new(base_dirs), storage_root_dir)?
+        .with_passphrase_provider(Arc::new(|config_type: &str| {
+            read_passphrase(
+                PASSPHRASE_ENV,
+                &format!("Passphrase of config type \"{}\"", config_type),
+                false,
+            )
+        }));
 
-    match cli {
-        Cli::Store(params) => store(config_storage, params)?,
-        Cli::Load(params) => load(config_storage, params)?,
-        Cli::List(params) => list(config_storage, params)?,
-        Cli::CreateType(params) => create_config_type(config_storage, params)?,
-        // Cli::CopyLabel(params) => todo!(),
-        // Cli::Which(params) => todo!(),
+    log::set_max_level(config_storage.settings().log_level());
+
+    match cli.command {
+        Command::Store(params) => store(config_storage, params),
+        Command::Load(params) => load(config_storage, params),
+        Command::History(params) => history(config_storage, params),
+        Command::Revert(params) => revert(config_storage, params),
+        Command::List(params) => list(config_storage, params),
+        Command::CreateType(params) => create_config_type(config_storage, params),
+        Command::CopyLabel(params) => copy_label(config_storage, params),
+        Command::RenameLabel(params) => rename_label(config_storage, params),
+        Command::Which(params) => which(config_storage, params),
+        Command::Status(params) => status(config_storage, params),
+        Command::Diff(params) => diff(config_storage, params),
+        Command::Pick(params) => pick(config_storage, params),
+        Command::Describe(params) => describe(config_storage, params),
+        Command::Tag(params) => tag(config_storage, params),
+        Command::RemoveLabel(params) => remove_label(config_storage, params),
+        Command::RemoveType(params) => remove_config_type(config_storage, params),
+        Command::Undelete(params) => undelete(config_storage, params),
+        Command::AddPath(params) => add_path(config_storage, params),
+        Command::RemovePath(params) => remove_path(config_storage, params),
+        Command::ShowType(params) => show_config_type(config_storage, params),
+        Command::Undo(params) => undo(config_storage, params),
+        Command::Profile(params) => profile(config_storage, params),
+        Command::Gc(params) => gc(config_storage, params),
+        Command::Rekey(params) => rekey(config_storage, params),
+        Command::Export(params) => export(config_storage, params),
+        Command::Import(params) => import(config_storage, params),
+        Command::InitGit(params) => init_git(config_storage, params),
+        Command::Push(params) => push(config_storage, params),
+        Command::Pull(params) => pull(config_storage, params),
     }
+}
+
+fn store(config_storage: ConfigStorage, params: StoreParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    config_type_storage.store(&params.label, params.message.as_deref())?;
 
-    Ok(())
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+    })))
 }
 
-fn store(
-    config_storage: ConfigStorage,
-    params: StoreParams,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let config_type_storage = config_storage.get_config_type_storage(params.config_type_name)?;
-    config_type_storage.store(params.label)?;
-    Ok(())
+fn load(config_storage: ConfigStorage, params: LoadParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let (label, revision) = split_label_revision(&config_type_storage, &params.label)?;
+    match revision {
+        Some(revision) => config_type_storage.load_revision(label, revision)?,
+        None => config_type_storage.load(label)?,
+    }
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": label,
+        "revision": revision,
+    })))
 }
 
-fn load(
-    config_storage: ConfigStorage,
-    params: LoadParams,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let config_type_storage = config_storage.get_config_type_storage(params.config_type_name)?;
-    config_type_storage.load(params.label)?;
-    Ok(())
+/// Splits `<label>@<revision>`, anything else is a label without a revision. A label named like
+/// that (e.g. `v@2`) is loaded as it is.
+fn split_label_revision<'a>(
+    config_type_storage: &ConfigTypeStorage,
+    label: &'a str,
+) -> Result<(&'a str, Option<u32>), CommandError> {
+    if config_type_storage.has_label(label)? {
+        return Ok((label, None));
+    }
+
+    Ok(match label.rsplit_once('@') {
+        Some((name, revision)) => match revision.parse() {
+            Ok(revision) => (name, Some(revision)),
+            Err(_) => (label, None),
+        },
+        None => (label, None),
+    })
 }
 
-fn create_config_type(
-    config_storage: ConfigStorage,
-    params: CreateTypeParams,
-) -> Result<(), Box<dyn std::error::Error>> {
+fn history(config_storage: ConfigStorage, params: HistoryParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let revisions = config_type_storage.history(&params.label)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "revisions": revisions,
+    }));
+    for revision in &revisions {
+        let created_at = revision
+            .created_at()
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S");
+        match revision.message() {
+            Some(message) => output.line(format!(
+                "{} ({}) - {}",
+                revision.number(),
+                created_at,
+                message
+            )),
+            None => output.line(format!("{} ({})", revision.number(), created_at)),
+        }
+    }
+
+    Ok(output)
+}
+
+fn revert(config_storage: ConfigStorage, params: RevertParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let new_revision = config_type_storage.revert(&params.label, params.revision)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "reverted_to": params.revision,
+        "revision": new_revision.number(),
+    }));
+    output.line(format!(
+        "Label reverted, label = \"{}\", reverted to = {}, new revision = {}",
+        params.label,
+        params.revision,
+        new_revision.number()
+    ));
+
+    Ok(output)
+}
+
+fn copy_label(config_storage: ConfigStorage, params: CopyLabelParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let force = params.force || config_storage.settings().overwrite_labels();
+    config_type_storage.copy_label(&params.label, &params.new_label, force)?;
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "new_label": params.new_label,
+    })))
+}
+
+fn rename_label(config_storage: ConfigStorage, params: RenameLabelParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let force = params.force || config_storage.settings().overwrite_labels();
+    config_type_storage.rename_label(&params.label, &params.new_label, force)?;
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "new_label": params.new_label,
+    })))
+}
+
+fn create_config_type(config_storage: ConfigStorage, params: CreateTypeParams) -> CommandResult {
     let config_type_storage = config_storage.create_config_type(&params.config_type_name)?;
 
-    println!(
-        "Config type created, config type = \"{}\", descriptor file = {:?}",
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "descriptor_file": config_type_storage.descriptor_location(),
+    }));
+    output.line(format!(
+        "Config type created, config type = \"{}\", descriptor file = {}",
         params.config_type_name,
-        config_type_storage.descriptor_path()
-    );
+        config_type_storage.descriptor_location()
+    ));
 
-    Ok(())
+    Ok(output)
 }
 
-fn list(
-    config_storage: ConfigStorage,
-    params: ListParams,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(config_type) = params.config_type_name {
-        let config_type_storage = config_storage.get_config_type_storage(config_type)?;
+fn list(config_storage: ConfigStorage, params: ListParams) -> CommandResult {
+    let config_types = match (params.config_type_name, &params.tag) {
+        (Some(config_type), _) => vec![config_type],
+        (None, Some(_)) => config_storage.iter_config_types()?.collect::<Vec<_>>(),
+        (None, None) => {
+            let config_types = config_storage.iter_config_types()?.collect::<Vec<_>>();
+
+            let mut output = CommandOutput::new(json!({ "config_types": config_types }));
+            for config_type in config_types {
+                output.line(config_type);
+            }
+
+            return Ok(output);
+        }
+    };
+
+    let mut lines = Vec::new();
+    let mut labels = Vec::new();
+    let show_config_type = config_types.len() > 1;
+    for config_type in config_types {
+        let config_type_storage = config_storage.get_config_type_storage(&config_type)?;
 
         for label in config_type_storage.iter_labels()? {
-            println!("{}", label?);
+            let metadata = config_type_storage.label_metadata(&label)?;
+            if let Some(tag) = &params.tag {
+                if !metadata
+                    .as_ref()
+                    .is_some_and(|metadata| metadata.has_tag(tag))
+                {
+                    continue;
+                }
+            }
+
+            labels.push(json!({
+                "config_type": config_type,
+                "label": label,
+                "metadata": metadata,
+            }));
+
+            let name = if show_config_type {
+                format!("{} {}", config_type, label)
+            } else {
+                label
+            };
+            let Some(metadata) = metadata else {
+                lines.push(name);
+                continue;
+            };
+
+            let mut details = vec![format!(
+                "created {}, updated {} on {}",
+                metadata
+                    .created_at()
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M"),
+                metadata
+                    .updated_at()
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M"),
+                metadata.hostname()
+            )];
+            if !metadata.tags().is_empty() {
+                let tags: Vec<&str> = metadata.tags().iter().map(String::as_str).collect();
+                details.push(format!("tags: {}", tags.join(", ")));
+            }
+            match metadata.description() {
+                Some(description) => lines.push(format!(
+                    "{} ({}) - {}",
+                    name,
+                    details.join(", "),
+                    description
+                )),
+                None => lines.push(format!("{} ({})", name, details.join(", "))),
+            }
+        }
+    }
+
+    let mut output = CommandOutput::new(json!({ "labels": labels }));
+    for line in lines {
+        output.line(line);
+    }
+
+    Ok(output)
+}
+
+fn which(config_storage: ConfigStorage, params: WhichParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+
+    let Some(active_label) = config_type_storage.which()? else {
+        let mut output = CommandOutput::new(json!({
+            "config_type": params.config_type_name,
+            "label": null,
+        }));
+        output.line(format!(
+            "No label has been loaded yet, config type = \"{}\"",
+            params.config_type_name
+        ));
+        return Ok(output);
+    };
+
+    let record = active_label.record();
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": record.label(),
+        "revision": record.revision(),
+        "loaded_at": record.loaded_at(),
+        "drifted_paths": active_label.drifted_paths(),
+    }));
+    let label = match record.revision() {
+        Some(revision) => format!("{}@{}", record.label(), revision),
+        None => record.label().to_string(),
+    };
+    output.line(format!(
+        "{} (loaded at {})",
+        label,
+        record
+            .loaded_at()
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    ));
+
+    if active_label.has_drifted() {
+        output.line("Live files have drifted from the label:");
+        for path in active_label.drifted_paths() {
+            output.line(format!("    {}", path));
+        }
+    } else {
+        output.line("Live files match the label");
+    }
+
+    Ok(output)
+}
+
+fn status(config_storage: ConfigStorage, params: StatusParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let status = config_type_storage.status(params.label.as_deref())?;
+
+    let files: Vec<_> = status
+        .files()
+        .iter()
+        .map(|(path, state)| json!({ "path": path, "state": state }))
+        .collect();
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": status.label(),
+        "files": files,
+    }));
+
+    output.line(format!("Label: {}", status.label()));
+    for (path, state) in status.files() {
+        output.line(format!("    {}: {}", state, path));
+    }
+    if status.is_clean() {
+        output.line("Live files match the label");
+    }
+
+    Ok(output)
+}
+
+fn diff(config_storage: ConfigStorage, params: DiffParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let file_diffs = config_type_storage.diff(&params.label, params.other_label.as_deref())?;
+
+    let mut files = Vec::new();
+    let mut lines = Vec::new();
+    for file_diff in file_diffs {
+        match file_diff.content() {
+            FileDiffContent::Text(unified_diff) => {
+                files.push(json!({
+                    "path": file_diff.path(),
+                    "binary": false,
+                    "diff": unified_diff,
+                }));
+                lines.extend(unified_diff.lines().map(String::from));
+            }
+            FileDiffContent::Binary { old, new } => {
+                let to_json = |summary: &Option<BinaryFileSummary>| {
+                    summary
+                        .as_ref()
+                        .map(|summary| json!({ "size": summary.size(), "sha256": summary.hash() }))
+                };
+                files.push(json!({
+                    "path": file_diff.path(),
+                    "binary": true,
+                    "old": to_json(old),
+                    "new": to_json(new),
+                }));
+
+                let describe = |summary: &Option<BinaryFileSummary>| match summary {
+                    Some(summary) => format!("{} bytes, sha256 {}", summary.size(), summary.hash()),
+                    None => "missing".to_string(),
+                };
+                lines.push(format!(
+                    "Binary file {} differs: {} -> {}",
+                    file_diff.path(),
+                    describe(old),
+                    describe(new)
+                ));
+            }
+        }
+    }
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "other_label": params.other_label,
+        "files": files,
+    }));
+    for line in lines {
+        output.line(line);
+    }
+
+    Ok(output)
+}
+
+fn pick(config_storage: ConfigStorage, params: PickParams) -> CommandResult {
+    let config_types = match params.config_type_name {
+        Some(config_type) => vec![config_type],
+        None => config_storage.iter_config_types()?.collect::<Vec<_>>(),
+    };
+
+    let mut choices = Vec::new();
+    let mut items = Vec::new();
+    for config_type in config_types {
+        let config_type_storage = config_storage.get_config_type_storage(&config_type)?;
+        let usage = config_type_storage.label_usage()?;
+
+        for label in config_type_storage.iter_labels()? {
+            let last_used = match usage.last_loaded_at(&label) {
+                Some(loaded_at) => loaded_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                None => "never loaded".into(),
+            };
+            items.push(format!("{:<16} {:<16} {}", config_type, label, last_used));
+            choices.push((config_type.clone(), label));
+        }
+    }
+
+    if choices.is_empty() {
+        let mut output = CommandOutput::new(json!({ "config_type": null, "label": null }));
+        output.line("No labels stored yet");
+        return Ok(output);
+    }
+
+    let Some(index) = picker::pick("Label to load", &items)? else {
+        return Ok(CommandOutput::new(
+            json!({ "config_type": null, "label": null }),
+        ));
+    };
+
+    // the preview is only made for the chosen label, it may have to decrypt the label
+    let (config_type, label) = &choices[index];
+    let config_type_storage = config_storage.get_config_type_storage(config_type)?;
+    let preview = match config_type_storage.status(Some(label)) {
+        Ok(status) if status.is_clean() => vec!["Matches the live files".to_string()],
+        Ok(status) => std::iter::once("Differs from the live files:".to_string())
+            .chain(
+                status
+                    .files()
+                    .iter()
+                    .filter(|(_, state)| *state != FileState::Unchanged)
+                    .map(|(path, state)| format!("    {} {}", state, path)),
+            )
+            .collect(),
+        Err(e) => vec![format!("No preview: {}", e)],
+    };
+    if !picker::confirm(
+        &format!("Load label \"{}\" of \"{}\"?", label, config_type),
+        &preview,
+    )? {
+        return Ok(CommandOutput::new(
+            json!({ "config_type": null, "label": null }),
+        ));
+    }
+    config_type_storage.load(label)?;
+
+    let mut output = CommandOutput::new(json!({ "config_type": config_type, "label": label }));
+    output.line(format!(
+        "Label loaded, config type = \"{}\", label = \"{}\"",
+        config_type, label
+    ));
+
+    Ok(output)
+}
+
+fn describe(config_storage: ConfigStorage, params: DescribeParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let metadata = config_type_storage.update_label_metadata(&params.label, |metadata| {
+        metadata.set_description(params.description)
+    })?;
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "metadata": metadata,
+    })))
+}
+
+fn tag(config_storage: ConfigStorage, params: TagParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let metadata = config_type_storage.update_label_metadata(&params.label, |metadata| {
+        if params.remove {
+            metadata.remove_tags(&params.tags)
+        } else {
+            metadata.add_tags(params.tags.iter().cloned())
+        }
+    })?;
+
+    let tags: Vec<&str> = metadata.tags().iter().map(String::as_str).collect();
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+        "metadata": metadata,
+    }));
+    output.line(format!("Tags: {}", tags.join(", ")));
+
+    Ok(output)
+}
+
+fn remove_label(config_storage: ConfigStorage, params: RemoveLabelParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    config_type_storage.remove_label(&params.label, params.force)?;
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "label": params.label,
+    })))
+}
+
+fn remove_config_type(config_storage: ConfigStorage, params: RemoveTypeParams) -> CommandResult {
+    config_storage.remove_config_type(&params.config_type_name, params.force)?;
+
+    Ok(CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+    })))
+}
+
+fn undelete(config_storage: ConfigStorage, params: UndeleteParams) -> CommandResult {
+    let Some(config_type) = params.config_type_name else {
+        let entries = config_storage.trash_entries()?;
+
+        let mut output = CommandOutput::new(json!({ "trash": entries }));
+        for entry in &entries {
+            output.line(format!(
+                "{}{} (deleted at {}, expires at {})",
+                entry.config_type(),
+                entry
+                    .label()
+                    .map(|label| format!(" {}", label))
+                    .unwrap_or_default(),
+                entry
+                    .deleted_at()
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                entry
+                    .expires_at()
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+            ));
         }
 
-        Ok(())
+        return Ok(output);
+    };
+
+    let entry = config_storage.undelete(config_type, params.label.as_deref())?;
+
+    Ok(CommandOutput::new(json!({ "restored": entry })))
+}
+
+fn add_path(config_storage: ConfigStorage, params: AddPathParams) -> CommandResult {
+    let mut config_type_storage =
+        config_storage.get_config_type_storage(&params.config_type_name)?;
+    let path = config_type_storage.add_path(
+        params.path,
+        params.load_mode,
+        params.template,
+        params.mode,
+    )?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "path": path,
+    }));
+    output.line(format!("Path added, path = \"{}\"", path));
+
+    Ok(output)
+}
+
+fn remove_path(config_storage: ConfigStorage, params: RemovePathParams) -> CommandResult {
+    let mut config_type_storage =
+        config_storage.get_config_type_storage(&params.config_type_name)?;
+    let path = config_type_storage.remove_path(params.path)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "path": path,
+    }));
+    output.line(format!("Path removed, path = \"{}\"", path));
+
+    Ok(output)
+}
+
+fn show_config_type(config_storage: ConfigStorage, params: ShowTypeParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(params.config_type_name)?;
+
+    let paths: Vec<_> = config_type_storage
+        .descriptor()
+        .paths()
+        .map(|path| {
+            json!({
+                "path": path.path(),
+                "load_mode": path.load_mode(),
+                "template": path.is_template(),
+                "mode": path.mode().map(format_mode),
+            })
+        })
+        .collect();
+    let patterns: Vec<&str> = config_type_storage.descriptor().patterns().collect();
+    let mut output = CommandOutput::new(json!({
+        "config_type": config_type_storage.config_type(),
+        "descriptor_file": config_type_storage.descriptor_location(),
+        "paths": paths,
+        "patterns": patterns,
+    }));
+
+    output.line(format!(
+        "Config type: {}",
+        config_type_storage.config_type()
+    ));
+    output.line(format!(
+        "Descriptor file: {}",
+        config_type_storage.descriptor_location()
+    ));
+    output.line("Paths:");
+    for path in config_type_storage.descriptor().paths() {
+        let mut options = Vec::new();
+        if path.load_mode() == LoadMode::Mirror {
+            options.push("mirror".to_string());
+        }
+        if path.is_template() {
+            options.push("template".to_string());
+        }
+        if let Some(mode) = path.mode() {
+            options.push(format!("mode {}", format_mode(mode)));
+        }
+
+        if options.is_empty() {
+            output.line(format!("    {}", path.path()));
+        } else {
+            output.line(format!("    {} ({})", path.path(), options.join(", ")));
+        }
+    }
+
+    if !patterns.is_empty() {
+        output.line("Patterns:");
+        for pattern in patterns {
+            output.line(format!("    {}", pattern));
+        }
+    }
+
+    Ok(output)
+}
+
+fn undo(config_storage: ConfigStorage, params: UndoParams) -> CommandResult {
+    let config_type_storage = config_storage.get_config_type_storage(&params.config_type_name)?;
+    let backup = config_type_storage.undo()?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "backup": backup,
+    }));
+    output.line(format!("Backup restored, backup = \"{}\"", backup));
+
+    Ok(output)
+}
+
+fn gc(config_storage: ConfigStorage, _params: GcParams) -> CommandResult {
+    let garbage_collection = config_storage.collect_garbage()?;
+
+    let mut output = CommandOutput::new(json!({
+        "removed_objects": garbage_collection.removed_objects(),
+        "freed_bytes": garbage_collection.freed_bytes(),
+    }));
+    output.line(format!(
+        "Garbage collected, removed objects = {}, freed bytes = {}",
+        garbage_collection.removed_objects(),
+        garbage_collection.freed_bytes()
+    ));
+
+    Ok(output)
+}
+
+fn rekey(config_storage: ConfigStorage, params: RekeyParams) -> CommandResult {
+    let mut config_type_storage =
+        config_storage.get_config_type_storage(&params.config_type_name)?;
+
+    let (new_encryption, method) = if params.passphrase {
+        let passphrase = read_passphrase(
+            NEW_PASSPHRASE_ENV,
+            &format!(
+                "New passphrase of config type \"{}\"",
+                params.config_type_name
+            ),
+            true,
+        )
+        .map_err(|e| {
+            CommandError::new(
+                codes::IO_ERROR,
+                format!("could not read passphrase, error = {}", e),
+            )
+        })?;
+        (NewEncryption::Passphrase(passphrase), "passphrase")
+    } else if let Some(key_file) = params.key_file {
+        (NewEncryption::KeyFile(key_file), "key_file")
     } else {
-        for config_type in config_storage.iter_config_types()? {
-            println!("{}", config_type?);
+        (NewEncryption::None, "none")
+    };
+
+    let labels = config_type_storage.rekey(new_encryption, params.allow_plaintext)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "encryption": method,
+        "labels": labels,
+    }));
+    output.line(format!(
+        "Rekey done, config type = \"{}\", encryption = {}, labels = {}",
+        params.config_type_name, method, labels
+    ));
+
+    Ok(output)
+}
+
+fn export(config_storage: ConfigStorage, params: ExportParams) -> CommandResult {
+    let format = params.format.unwrap_or_else(|| {
+        params
+            .file
+            .as_ref()
+            .map(ArchiveFormat::from_path)
+            .unwrap_or_default()
+    });
+    let file = params.file.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}.{}",
+            params.config_type_name,
+            format.extension()
+        ))
+    });
+
+    let labels = config_storage.export(&params.config_type_name, &params.labels, &file, format)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": params.config_type_name,
+        "labels": labels,
+        "file": file,
+    }));
+    output.line(format!(
+        "Export done, config type = \"{}\", labels = {}, file = {}",
+        params.config_type_name,
+        labels.len(),
+        file.display()
+    ));
+
+    Ok(output)
+}
+
+fn import(config_storage: ConfigStorage, params: ImportParams) -> CommandResult {
+    let mut renames = BTreeMap::new();
+    for rename in &params.rename {
+        let Some((label, new_label)) = rename.split_once('=') else {
+            return Err(CommandError::new(
+                codes::INVALID_ARGUMENT,
+                format!(
+                    "invalid rename, expected <label>=<new label>, rename = \"{}\"",
+                    rename
+                ),
+            ));
+        };
+        renames.insert(label.to_string(), new_label.to_string());
+    }
+
+    let summary = config_storage.import(&params.archive, &renames, params.skip_existing)?;
+
+    let mut output = CommandOutput::new(json!({
+        "config_type": summary.config_type(),
+        "created_config_type": summary.created_config_type(),
+        "imported_labels": summary.imported_labels(),
+        "skipped_labels": summary.skipped_labels(),
+    }));
+    if summary.created_config_type() {
+        output.line(format!(
+            "Config type created, config type = \"{}\"",
+            summary.config_type()
+        ));
+    }
+    for (label, new_label) in summary.imported_labels() {
+        if label == new_label {
+            output.line(format!("Label imported, label = \"{}\"", label));
+        } else {
+            output.line(format!(
+                "Label imported, label = \"{}\", stored as = \"{}\"",
+                label, new_label
+            ));
+        }
+    }
+    for label in summary.skipped_labels() {
+        output.line(format!(
+            "Label skipped, already exists, label = \"{}\"",
+            label
+        ));
+    }
+
+    Ok(output)
+}
+
+fn init_git(config_storage: ConfigStorage, params: InitGitParams) -> CommandResult {
+    config_storage.init_git_repository(params.remote.as_deref())?;
+
+    let mut output = CommandOutput::new(json!({ "remote": params.remote }));
+    output.line("Git repository initialized in the storage root");
+
+    Ok(output)
+}
+
+fn push(config_storage: ConfigStorage, params: PushParams) -> CommandResult {
+    config_storage.push(&params.remote)?;
+
+    let mut output = CommandOutput::new(json!({ "remote": params.remote }));
+    output.line(format!("Pushed, remote = \"{}\"", params.remote));
+
+    Ok(output)
+}
+
+fn pull(config_storage: ConfigStorage, params: PullParams) -> CommandResult {
+    config_storage.pull(&params.remote)?;
+
+    let mut output = CommandOutput::new(json!({ "remote": params.remote }));
+    output.line(format!("Pulled, remote = \"{}\"", params.remote));
+
+    Ok(output)
+}
+
+fn profile(config_storage: ConfigStorage, params: ProfileParams) -> CommandResult {
+    match params.command {
+        ProfileCommand::Create(params) => {
+            let members = params
+                .members
+                .iter()
+                .map(|member| match member.split_once('=') {
+                    Some((config_type, label)) => (config_type.to_string(), label.to_string()),
+                    None => (member.clone(), params.profile_name.clone()),
+                })
+                .collect();
+            let profile = config_storage.create_profile(&params.profile_name, members)?;
+
+            let mut output = CommandOutput::new(json!({
+                "profile": params.profile_name,
+                "members": profile.members(),
+            }));
+            output.line(format!(
+                "Profile created, profile = \"{}\"",
+                params.profile_name
+            ));
+
+            Ok(output)
+        }
+        ProfileCommand::Store(params) => {
+            let profile =
+                config_storage.store_profile(&params.profile_name, params.label.as_deref())?;
+
+            let mut output = CommandOutput::new(json!({
+                "profile": params.profile_name,
+                "members": profile.members(),
+            }));
+            for (config_type, label) in profile.members() {
+                output.line(format!(
+                    "Stored, config type = \"{}\", label = \"{}\"",
+                    config_type, label
+                ));
+            }
+
+            Ok(output)
         }
+        ProfileCommand::Load(params) => {
+            let profile = config_storage.load_profile(&params.profile_name)?;
 
-        Ok(())
+            let mut output = CommandOutput::new(json!({
+                "profile": params.profile_name,
+                "members": profile.members(),
+            }));
+            for (config_type, label) in profile.members() {
+                output.line(format!(
+                    "Loaded, config type = \"{}\", label = \"{}\"",
+                    config_type, label
+                ));
+            }
+
+            Ok(output)
+        }
+        ProfileCommand::List(_) => {
+            let mut profiles = Vec::new();
+            let mut lines = Vec::new();
+            for profile_name in config_storage.profiles()? {
+                let profile = config_storage.get_profile(&profile_name)?;
+                let members = profile
+                    .members()
+                    .iter()
+                    .map(|(config_type, label)| format!("{}={}", config_type, label))
+                    .collect::<Vec<_>>();
+                lines.push(format!("{} ({})", profile_name, members.join(", ")));
+                profiles.push(json!({ "profile": profile_name, "members": profile.members() }));
+            }
+
+            let mut output = CommandOutput::new(json!({ "profiles": profiles }));
+            for line in lines {
+                output.line(line);
+            }
+
+            Ok(output)
+        }
     }
 }
@@ -0,0 +1,384 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use chrono::Utc;
+
+use crate::{
+    directories::LABEL_METADATA_FILENAME,
+    error::StorageBackendError,
+    storage_backend::{
+        LabelFiles, LabelRevision, StorageBackend, StorageOperation, StorageTransaction,
+    },
+};
+
+#[derive(Clone, Default)]
+struct MemoryConfigType {
+    files: BTreeMap<String, Vec<u8>>,
+    labels: BTreeMap<String, MemoryLabel>,
+}
+
+#[derive(Clone, Default)]
+struct MemoryLabel {
+    files: LabelFiles,
+    revisions: Vec<(LabelRevision, LabelFiles)>,
+}
+
+impl MemoryLabel {
+    /// Adds `files` as a new revision, unless they are the same as the current files and the
+    /// latest revision apart from the metadata of the label, like the fs backend.
+    fn put(&mut self, files: LabelFiles, message: Option<String>) {
+        let same_files = |other: &LabelFiles| {
+            let metadata_path = Path::new(LABEL_METADATA_FILENAME);
+            files
+                .iter()
+                .filter(|(path, _)| *path != metadata_path)
+                .eq(other.iter().filter(|(path, _)| *path != metadata_path))
+        };
+        if same_files(&self.files)
+            && self
+                .revisions
+                .last()
+                .is_some_and(|(_, revision_files)| same_files(revision_files))
+        {
+            return;
+        }
+
+        let number = self.revisions.len() as u32 + 1;
+        self.revisions.push((
+            LabelRevision::new(number, Utc::now(), message),
+            files.clone(),
+        ));
+        self.files = files;
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemoryState {
+    config_types: BTreeMap<String, MemoryConfigType>,
+    profiles: BTreeMap<String, Vec<u8>>,
+}
+
+/// Keeps everything in memory, e.g. for tests of embedding applications. Nothing is persisted.
+#[derive(Default)]
+pub struct MemoryStorageBackend {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryStorageBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // the state is only replaced as a whole, so it is consistent even if a holder panicked
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn with_label<T>(
+        &self,
+        config_type: &str,
+        label: &str,
+        f: impl FnOnce(&MemoryLabel) -> T,
+    ) -> Result<T, StorageBackendError> {
+        let state = self.lock();
+        let memory_label = state
+            .config_types
+            .get(config_type)
+            .ok_or_else(|| StorageBackendError::ConfigTypeNotFound(config_type.into()))?
+            .labels
+            .get(label)
+            .ok_or_else(|| StorageBackendError::LabelNotFound {
+                config_type: config_type.into(),
+                label: label.into(),
+            })?;
+
+        Ok(f(memory_label))
+    }
+
+    fn with_config_type<T>(
+        &self,
+        config_type: &str,
+        f: impl FnOnce(&MemoryConfigType) -> T,
+    ) -> Result<T, StorageBackendError> {
+        let state = self.lock();
+        let config_type = state
+            .config_types
+            .get(config_type)
+            .ok_or_else(|| StorageBackendError::ConfigTypeNotFound(config_type.into()))?;
+
+        Ok(f(config_type))
+    }
+}
+
+impl StorageBackend for MemoryStorageBackend {
+    fn config_types(&self) -> Result<Vec<String>, StorageBackendError> {
+        Ok(self.lock().config_types.keys().cloned().collect())
+    }
+
+    fn has_config_type(&self, config_type: &str) -> Result<bool, StorageBackendError> {
+        Ok(self.lock().config_types.contains_key(config_type))
+    }
+
+    fn config_type_files(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError> {
+        self.with_config_type(config_type, |config_type| {
+            config_type.files.keys().cloned().collect()
+        })
+    }
+
+    fn read_config_type_file(
+        &self,
+        config_type: &str,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        self.with_config_type(config_type, |config_type| {
+            config_type.files.get(name).cloned()
+        })
+    }
+
+    fn config_type_file_location(&self, config_type: &str, name: &str) -> String {
+        format!("memory:{}/{}", config_type, name)
+    }
+
+    fn labels(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError> {
+        self.with_config_type(config_type, |config_type| {
+            config_type.labels.keys().cloned().collect()
+        })
+    }
+
+    fn has_label(&self, config_type: &str, label: &str) -> Result<bool, StorageBackendError> {
+        Ok(self
+            .lock()
+            .config_types
+            .get(config_type)
+            .is_some_and(|config_type| config_type.labels.contains_key(label)))
+    }
+
+    fn label_files(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<PathBuf>, StorageBackendError> {
+        self.with_label(config_type, label, |memory_label| {
+            memory_label.files.keys().cloned().collect()
+        })
+    }
+
+    fn read_label_file(
+        &self,
+        config_type: &str,
+        label: &str,
+        path: &Path,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        self.with_label(config_type, label, |memory_label| {
+            memory_label.files.get(path).cloned()
+        })
+    }
+
+    fn label_revisions(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<LabelRevision>, StorageBackendError> {
+        self.with_label(config_type, label, |memory_label| {
+            memory_label
+                .revisions
+                .iter()
+                .map(|(revision, _)| revision.clone())
+                .collect()
+        })
+    }
+
+    fn read_label_revision(
+        &self,
+        config_type: &str,
+        label: &str,
+        revision: u32,
+    ) -> Result<LabelFiles, StorageBackendError> {
+        self.with_label(config_type, label, |memory_label| {
+            memory_label
+                .revisions
+                .iter()
+                .find(|(stored_revision, _)| stored_revision.number() == revision)
+                .map(|(_, files)| files.clone())
+        })?
+        .ok_or_else(|| StorageBackendError::RevisionNotFound {
+            config_type: config_type.into(),
+            label: label.into(),
+            revision,
+        })
+    }
+
+    fn read_label(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<LabelFiles, StorageBackendError> {
+        self.with_label(config_type, label, |memory_label| {
+            memory_label.files.clone()
+        })
+    }
+
+    fn profiles(&self) -> Result<Vec<String>, StorageBackendError> {
+        Ok(self.lock().profiles.keys().cloned().collect())
+    }
+
+    fn read_profile(&self, profile: &str) -> Result<Option<Vec<u8>>, StorageBackendError> {
+        Ok(self.lock().profiles.get(profile).cloned())
+    }
+
+    fn commit(&self, transaction: StorageTransaction) -> Result<(), StorageBackendError> {
+        let mut state = self.lock();
+
+        // the operations are applied to a copy, which only replaces the state if all succeeded
+        let mut new_state = state.clone();
+        for operation in transaction.into_operations() {
+            apply(&mut new_state, operation)?;
+        }
+        *state = new_state;
+
+        Ok(())
+    }
+}
+
+fn apply(state: &mut MemoryState, operation: StorageOperation) -> Result<(), StorageBackendError> {
+    let config_types = &mut state.config_types;
+    match operation {
+        StorageOperation::CreateConfigType {
+            config_type,
+            files,
+            labels,
+        } => {
+            if config_types.contains_key(&config_type) {
+                return Err(StorageBackendError::ConfigTypeAlreadyExists(config_type));
+            }
+            let labels = labels
+                .into_iter()
+                .map(|(label, files)| {
+                    let mut memory_label = MemoryLabel::default();
+                    memory_label.put(files, None);
+                    (label, memory_label)
+                })
+                .collect();
+            config_types.insert(config_type, MemoryConfigType { files, labels });
+        }
+        StorageOperation::RemoveConfigType { config_type } => {
+            if config_types.remove(&config_type).is_none() {
+                return Err(StorageBackendError::ConfigTypeNotFound(config_type));
+            }
+        }
+        StorageOperation::PutConfigTypeFile {
+            config_type,
+            name,
+            content,
+        } => {
+            config_type_mut(config_types, &config_type)?
+                .files
+                .insert(name, content);
+        }
+        StorageOperation::PutLabel {
+            config_type,
+            label,
+            files,
+            message,
+        } => {
+            config_type_mut(config_types, &config_type)?
+                .labels
+                .entry(label)
+                .or_default()
+                .put(files, message);
+        }
+        StorageOperation::PutLabelFile {
+            config_type,
+            label,
+            path,
+            content,
+        } => {
+            let labels = &mut config_type_mut(config_types, &config_type)?.labels;
+            labels
+                .get_mut(&label)
+                .ok_or(StorageBackendError::LabelNotFound { config_type, label })?
+                .files
+                .insert(path, content);
+        }
+        StorageOperation::RestoreLabel {
+            config_type,
+            label,
+            files,
+            revisions,
+        } => {
+            let mut memory_label = MemoryLabel::default();
+            if revisions.is_empty() {
+                memory_label.put(files, None);
+            } else {
+                memory_label = MemoryLabel { files, revisions };
+            }
+            config_type_mut(config_types, &config_type)?
+                .labels
+                .insert(label, memory_label);
+        }
+        StorageOperation::RewriteLabel {
+            config_type,
+            label,
+            files,
+            mut revisions,
+        } => {
+            let labels = &mut config_type_mut(config_types, &config_type)?.labels;
+            let memory_label =
+                labels
+                    .get_mut(&label)
+                    .ok_or_else(|| StorageBackendError::LabelNotFound {
+                        config_type: config_type.clone(),
+                        label: label.clone(),
+                    })?;
+            for (revision, revision_files) in &mut memory_label.revisions {
+                if let Some(files) = revisions.remove(&revision.number()) {
+                    *revision_files = files;
+                }
+            }
+            if let Some(&revision) = revisions.keys().next() {
+                return Err(StorageBackendError::RevisionNotFound {
+                    config_type,
+                    label,
+                    revision,
+                });
+            }
+            memory_label.files = files;
+        }
+        StorageOperation::RenameLabel {
+            config_type,
+            label,
+            new_label,
+        } => {
+            let labels = &mut config_type_mut(config_types, &config_type)?.labels;
+            let memory_label = labels
+                .remove(&label)
+                .ok_or(StorageBackendError::LabelNotFound { config_type, label })?;
+            labels.insert(new_label, memory_label);
+        }
+        StorageOperation::RemoveLabel { config_type, label } => {
+            let labels = &mut config_type_mut(config_types, &config_type)?.labels;
+            if labels.remove(&label).is_none() {
+                return Err(StorageBackendError::LabelNotFound { config_type, label });
+            }
+        }
+        StorageOperation::PutProfile { profile, content } => {
+            state.profiles.insert(profile, content);
+        }
+    }
+
+    Ok(())
+}
+
+fn config_type_mut<'a>(
+    config_types: &'a mut BTreeMap<String, MemoryConfigType>,
+    config_type: &str,
+) -> Result<&'a mut MemoryConfigType, StorageBackendError> {
+    config_types
+        .get_mut(config_type)
+        .ok_or_else(|| StorageBackendError::ConfigTypeNotFound(config_type.into()))
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::BTreeSet,
+    fs::{create_dir_all, remove_file, rename, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{directories::Directories, error::StorageBackendError, utils::SubDirectoryIterator};
+
+/// Keeps file contents once, under `<root>/objects`, named after the SHA-256 hash of the content.
+pub struct ObjectStore<'a> {
+    directories: &'a Directories,
+}
+
+impl<'a> ObjectStore<'a> {
+    pub fn new(directories: &'a Directories) -> Self {
+        Self { directories }
+    }
+
+    pub fn object_id(content: &[u8]) -> String {
+        hex::encode(Sha256::digest(content))
+    }
+
+    fn object_path(&self, id: &str) -> Result<PathBuf, StorageBackendError> {
+        if id.len() != 64 || !id.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(StorageBackendError::InvalidObjectId(id.into()));
+        }
+
+        Ok(self.directories.object_path(id))
+    }
+
+    /// Stores `content` and returns its id. Nothing is written if the object already exists.
+    pub fn put(&self, content: &[u8]) -> Result<String, StorageBackendError> {
+        let id = Self::object_id(content);
+        let object_path = self.object_path(&id)?;
+        if object_path.is_file() {
+            return Ok(id);
+        }
+
+        // the object is written next to its final place and renamed, so a crash does not leave a
+        // truncated object behind
+        let object_dir_path = object_path.parent().unwrap_or(&object_path).to_path_buf();
+        create_dir_all(&object_dir_path).map_err(|e| StorageBackendError::CouldNotWriteFile {
+            io_error: e,
+            path: object_dir_path.clone(),
+        })?;
+
+        let temp_path = object_dir_path.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        let written = File::create(&temp_path)
+            .and_then(|mut file| file.write_all(content))
+            .and_then(|_| rename(&temp_path, &object_path));
+        if let Err(e) = written {
+            let _ = remove_file(&temp_path);
+            return Err(StorageBackendError::CouldNotWriteFile {
+                io_error: e,
+                path: object_path,
+            });
+        }
+
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Vec<u8>, StorageBackendError> {
+        let object_path = self.object_path(id)?;
+        std::fs::read(&object_path).map_err(|e| StorageBackendError::CouldNotReadObject {
+            io_error: e,
+            id: id.into(),
+            path: object_path,
+        })
+    }
+
+    /// Returns the ids of every stored object.
+    pub fn ids(&self) -> Result<BTreeSet<String>, StorageBackendError> {
+        let objects_dir_path = self.directories.objects_dir_path();
+        let map_err = |path: PathBuf| {
+            move |e| StorageBackendError::CouldNotReadDirectory { io_error: e, path }
+        };
+
+        let mut ids = BTreeSet::new();
+        if !objects_dir_path.is_dir() {
+            return Ok(ids);
+        }
+
+        for dir_entry in SubDirectoryIterator::new(objects_dir_path)
+            .map_err(map_err(objects_dir_path.to_path_buf()))?
+        {
+            let dir_entry = dir_entry.map_err(map_err(objects_dir_path.to_path_buf()))?;
+            let prefix = dir_entry.file_name().to_string_lossy().to_string();
+            for object_entry in
+                std::fs::read_dir(dir_entry.path()).map_err(map_err(dir_entry.path()))?
+            {
+                let object_entry = object_entry.map_err(map_err(dir_entry.path()))?;
+                let id = format!("{}{}", prefix, object_entry.file_name().to_string_lossy());
+                if self.object_path(&id).is_ok() {
+                    ids.insert(id);
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Removes the object, returns its size.
+    pub fn remove(&self, id: &str) -> Result<u64, StorageBackendError> {
+        let object_path = self.object_path(id)?;
+        let map_err = |e| StorageBackendError::CouldNotRemoveFile {
+            io_error: e,
+            path: object_path.clone(),
+        };
+
+        let size = object_path.metadata().map_err(map_err)?.len();
+        remove_file(&object_path).map_err(map_err)?;
+        if let Some(object_dir_path) = object_path.parent() {
+            // only succeeds once the last object of the directory is gone
+            let _ = std::fs::remove_dir(object_dir_path);
+        }
+
+        Ok(size)
+    }
+}
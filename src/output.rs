@@ -0,0 +1,109 @@
+use std::{error::Error, fmt::Display};
+
+use config_picker::error::{codes, ErrorCode};
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// What a command reports, as lines for people and as a JSON value for scripts.
+pub struct CommandOutput {
+    lines: Vec<String>,
+    data: Value,
+}
+
+impl CommandOutput {
+    pub fn new(data: Value) -> Self {
+        Self {
+            lines: Vec::new(),
+            data,
+        }
+    }
+
+    pub fn line(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    pub fn print(&self, output_format: OutputFormat) {
+        match output_format {
+            OutputFormat::Text => {
+                for line in &self.lines {
+                    println!("{}", line);
+                }
+            }
+            OutputFormat::Json => println!("{}", json!({ "ok": true, "data": self.data })),
+        }
+    }
+}
+
+/// An error of a command, with the stable code of the error, see `ErrorCode`.
+pub struct CommandError {
+    code: String,
+    message: String,
+    causes: Vec<String>,
+}
+
+impl CommandError {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            causes: Vec::new(),
+        }
+    }
+
+    /// The error of invalid command line arguments, reported as `invalid_argument`.
+    pub fn from_usage_error(error: &clap::Error) -> Self {
+        // the first paragraph is the error, the rest is the usage and a hint to `--help`
+        let rendered = error.render().to_string();
+        let paragraph = rendered.split("\n\n").next().unwrap_or_default();
+        let message = paragraph
+            .strip_prefix("error: ")
+            .unwrap_or(paragraph)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self::new(codes::INVALID_ARGUMENT, message)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "ok": false,
+            "error": {
+                "code": self.code,
+                "message": self.message,
+                "causes": self.causes,
+            },
+        })
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl<E: Error + ErrorCode> From<E> for CommandError {
+    fn from(error: E) -> Self {
+        let code = error.code().to_string();
+
+        let mut causes = Vec::new();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+
+        Self {
+            code,
+            message: error.to_string(),
+            causes,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::io::{stderr, stdin, ErrorKind, IsTerminal, Write};
+
+use dialoguer::{theme::ColorfulTheme, Password};
+
+/// Holds the passphrase of encrypted config types, so scripts do not have to be asked.
+pub const PASSPHRASE_ENV: &str = "CONFIG_PICKER_PASSPHRASE";
+/// Holds the new passphrase for `rekey --passphrase`.
+pub const NEW_PASSPHRASE_ENV: &str = "CONFIG_PICKER_NEW_PASSPHRASE";
+
+/// Returns the passphrase in the environment variable `env_name`, or asks for it, twice if it is
+/// a `new` passphrase.
+///
+/// Asks without echo on a terminal, and reads a line from stdin otherwise (e.g. the passphrase is
+/// piped in).
+pub fn read_passphrase(env_name: &str, prompt: &str, new: bool) -> std::io::Result<String> {
+    if let Ok(passphrase) = std::env::var(env_name) {
+        return Ok(passphrase);
+    }
+
+    if stdin().is_terminal() && stderr().is_terminal() {
+        let theme = ColorfulTheme::default();
+        let mut password = Password::with_theme(&theme).with_prompt(prompt);
+        if new {
+            password = password.with_confirmation("Repeat passphrase", "passphrases do not match");
+        }
+        return password.interact().map_err(|e| match e {
+            dialoguer::Error::IO(e) => e,
+        });
+    }
+
+    let mut stderr = stderr();
+    write!(stderr, "{}: ", prompt)?;
+    stderr.flush()?;
+
+    let mut line = String::new();
+    stdin().read_line(&mut line)?;
+    let passphrase = line.trim_end_matches(['\r', '\n']);
+    if passphrase.is_empty() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "empty passphrase",
+        ));
+    }
+
+    Ok(passphrase.to_string())
+}
@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::{error::PathPatternError, variable_resolver::VariableResolver};
+
+struct PathPatternRule {
+    matcher: GlobMatcher,
+    is_negated: bool,
+}
+
+/// The directory (or file) a pattern can match in, i.e. the part of the pattern before the first
+/// component with a wildcard.
+pub struct PatternRoot {
+    encoded: String,
+    decoded: PathBuf,
+}
+
+impl PatternRoot {
+    /// The root as written in the pattern, may contain variables (e.g. `{{HOME}}`).
+    pub fn encoded(&self) -> &str {
+        &self.encoded
+    }
+
+    pub fn decoded(&self) -> &Path {
+        &self.decoded
+    }
+}
+
+/// Gitignore-like list of patterns selecting files of a config type.
+///
+/// - empty lines and lines starting with `#` are ignored
+/// - a line starting with `!` excludes the files matched by the rest of the line
+/// - `*` and `?` do not match `/`, `**` matches any number of directories
+/// - a pattern matching a directory matches every file below it
+/// - a pattern without `/` matches file and directory names at any depth
+/// - later patterns override earlier ones
+pub struct PathPatterns {
+    rules: Vec<PathPatternRule>,
+    roots: Vec<PatternRoot>,
+}
+
+impl PathPatterns {
+    pub fn new(
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+        variable_resolver: &VariableResolver,
+    ) -> Result<Self, PathPatternError> {
+        let mut rules = Vec::new();
+        let mut roots: Vec<PatternRoot> = Vec::new();
+
+        for line in lines {
+            let line = line.as_ref().trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (is_negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let pattern = pattern.trim_end_matches('/');
+
+            let pattern = if pattern.contains('/') {
+                pattern.to_string()
+            } else {
+                format!("**/{}", pattern)
+            };
+
+            let decoded_pattern = variable_resolver
+                .decode_glob_pattern(&pattern)
+                .map_err(|e| PathPatternError::CouldNotDecodePattern {
+                    pattern: line.into(),
+                    error: e,
+                })?;
+
+            let matcher = GlobBuilder::new(&decoded_pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| PathPatternError::InvalidPattern {
+                    pattern: line.into(),
+                    error: e,
+                })?
+                .compile_matcher();
+
+            rules.push(PathPatternRule {
+                matcher,
+                is_negated,
+            });
+
+            if !is_negated {
+                let encoded_root = literal_prefix(&pattern);
+                if !encoded_root.is_empty()
+                    && !roots.iter().any(|root| root.encoded == encoded_root)
+                {
+                    let decoded_root =
+                        variable_resolver
+                            .decode_string(&encoded_root)
+                            .map_err(|e| PathPatternError::CouldNotDecodePattern {
+                                pattern: line.into(),
+                                error: e,
+                            })?;
+                    roots.push(PatternRoot {
+                        encoded: encoded_root,
+                        decoded: PathBuf::from(decoded_root),
+                    });
+                }
+            }
+        }
+
+        Ok(Self { rules, roots })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The locations which have to be searched for matching files.
+    pub fn roots(&self) -> &[PatternRoot] {
+        &self.roots
+    }
+
+    pub fn is_included(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+
+        let mut is_included = false;
+        for rule in &self.rules {
+            if path
+                .ancestors()
+                .any(|ancestor| rule.matcher.is_match(ancestor))
+            {
+                is_included = !rule.is_negated;
+            }
+        }
+
+        is_included
+    }
+}
+
+/// Returns the leading components of the pattern which do not contain wildcards.
+fn literal_prefix(pattern: &str) -> String {
+    let mut prefix = Vec::new();
+    for component in pattern.split('/') {
+        let mut without_variables = component.to_string();
+        while let Some(start) = without_variables.find("{{") {
+            match without_variables[start..].find("}}") {
+                Some(end) => without_variables.replace_range(start..start + end + 2, ""),
+                None => break,
+            }
+        }
+
+        if without_variables.contains(['*', '?', '[', ']', '{', '}']) {
+            break;
+        }
+        prefix.push(component);
+    }
+
+    if prefix == [""] {
+        "/".into()
+    } else {
+        prefix.join("/")
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{stderr, stdin, IsTerminal, Write};
+
+use config_picker::error::{codes, ErrorCode};
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PickError {
+    #[error("could not show picker, error = {0}")]
+    CouldNotShowPicker(
+        #[source]
+        #[from]
+        dialoguer::Error,
+    ),
+
+    #[error("could not show items, error = {0}")]
+    CouldNotShowItems(#[source] std::io::Error),
+
+    #[error("could not read selection, error = {0}")]
+    CouldNotReadSelection(#[source] std::io::Error),
+
+    #[error("invalid selection = \"{0}\"")]
+    InvalidSelection(String),
+
+    #[error("invalid answer = \"{0}\"")]
+    InvalidAnswer(String),
+}
+
+impl ErrorCode for PickError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::CouldNotShowPicker(..) => codes::IO_ERROR,
+            Self::CouldNotShowItems(error) => error.code(),
+            Self::CouldNotReadSelection(error) => error.code(),
+            Self::InvalidSelection(..) => codes::INVALID_ARGUMENT,
+            Self::InvalidAnswer(..) => codes::INVALID_ARGUMENT,
+        }
+    }
+}
+
+/// Lets the user choose one of `items`, returns `None` if nothing was chosen.
+///
+/// Shows a fuzzy-searchable list on a terminal, and falls back to a numbered prompt when stdin is
+/// not a terminal (e.g. the selection is piped in).
+pub fn pick(prompt: &str, items: &[String]) -> Result<Option<usize>, PickError> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+
+    if stdin().is_terminal() && stderr().is_terminal() {
+        return Ok(FuzzySelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .items(items)
+            .default(0)
+            .interact_opt()?);
+    }
+
+    pick_numbered(prompt, items)
+}
+
+/// Shows `details` and asks whether to go on, Enter (or the end of the input) answers yes.
+pub fn confirm(prompt: &str, details: &[String]) -> Result<bool, PickError> {
+    let mut stderr = stderr();
+    for line in details {
+        writeln!(stderr, "{}", line).map_err(PickError::CouldNotShowItems)?;
+    }
+
+    if stdin().is_terminal() && stderr.is_terminal() {
+        return Ok(Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(true)
+            .interact_opt()?
+            .unwrap_or(false));
+    }
+
+    write!(stderr, "{} [Y/n]: ", prompt).map_err(PickError::CouldNotShowItems)?;
+    stderr.flush().map_err(PickError::CouldNotShowItems)?;
+
+    let mut line = String::new();
+    stdin()
+        .read_line(&mut line)
+        .map_err(PickError::CouldNotReadSelection)?;
+
+    match line.trim().to_lowercase().as_str() {
+        "" | "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        answer => Err(PickError::InvalidAnswer(answer.into())),
+    }
+}
+
+fn pick_numbered(prompt: &str, items: &[String]) -> Result<Option<usize>, PickError> {
+    let mut stderr = stderr();
+    for (index, item) in items.iter().enumerate() {
+        writeln!(stderr, "{:>3}) {}", index + 1, item).map_err(PickError::CouldNotShowItems)?;
+    }
+    write!(stderr, "{} [1-{}]: ", prompt, items.len()).map_err(PickError::CouldNotShowItems)?;
+    stderr.flush().map_err(PickError::CouldNotShowItems)?;
+
+    let mut line = String::new();
+    stdin()
+        .read_line(&mut line)
+        .map_err(PickError::CouldNotReadSelection)?;
+
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    match line.parse::<usize>() {
+        Ok(number) if (1..=items.len()).contains(&number) => Ok(Some(number - 1)),
+        _ => Err(PickError::InvalidSelection(line.into())),
+    }
+}
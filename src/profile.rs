@@ -0,0 +1,50 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ReadJsonFileError, WriteJsonFileError};
+
+/// A set of config types which are switched together, each with the label it uses.
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    members: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn new(members: BTreeMap<String, String>) -> Self {
+        Self { members }
+    }
+
+    /// Profile names become a single file name, so they cannot be empty, `.` or `..`, or
+    /// contain a path separator.
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut components = Path::new(name).components();
+        matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+    }
+
+    pub fn from_slice(content: &[u8]) -> Result<Self, ReadJsonFileError> {
+        Ok(serde_json::from_slice(content)?)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, WriteJsonFileError> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Labels keyed by config type.
+    pub fn members(&self) -> &BTreeMap<String, String> {
+        &self.members
+    }
+
+    /// Makes every member use `label`.
+    pub fn set_label(&mut self, label: impl AsRef<str>) {
+        for member_label in self.members.values_mut() {
+            *member_label = label.as_ref().into();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ReadJsonFileError, WriteJsonFileError},
+    utils::{read_json_file, write_json_file},
+};
+
+/// Tool-wide preferences, stored in `settings.json` in the storage root. Missing fields take
+/// their default values.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Whether commands replace an existing label without `--force`.
+    overwrite_labels: bool,
+
+    /// Number of backups of the live files kept per config type, 0 disables the backups.
+    backup_retention: usize,
+
+    log_level: LevelFilter,
+
+    /// Command for editing files. Reserved for a later command, no command uses it yet, it is
+    /// only kept so the settings files which set it stay valid.
+    editor: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            overwrite_labels: false,
+            backup_retention: 10,
+            log_level: LevelFilter::Info,
+            editor: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ReadJsonFileError> {
+        read_json_file(path)
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), WriteJsonFileError> {
+        write_json_file(self, path)
+    }
+
+    pub fn overwrite_labels(&self) -> bool {
+        self.overwrite_labels
+    }
+
+    pub fn backup_retention(&self) -> usize {
+        self.backup_retention
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::StorageBackendError;
+
+/// The files of a label, keyed by their path relative to the label (e.g. `{{HOME}}/.gitconfig`).
+pub type LabelFiles = BTreeMap<PathBuf, Vec<u8>>;
+
+/// Where the config types, their descriptors, their labels and the profiles are kept.
+///
+/// Every change goes through a `StorageTransaction`, which a backend applies all or nothing, so
+/// the command logic does not depend on how (or where) the data is laid out.
+pub trait StorageBackend: Send + Sync {
+    /// Returns the names of the config types, sorted.
+    fn config_types(&self) -> Result<Vec<String>, StorageBackendError>;
+
+    fn has_config_type(&self, config_type: &str) -> Result<bool, StorageBackendError>;
+
+    /// Returns the names of the files of the config type itself (e.g. the descriptor), sorted.
+    fn config_type_files(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError>;
+
+    /// Returns `None` if the config type does not have the file.
+    fn read_config_type_file(
+        &self,
+        config_type: &str,
+        name: &str,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError>;
+
+    /// A human readable location of a file of a config type, used in messages.
+    fn config_type_file_location(&self, config_type: &str, name: &str) -> String;
+
+    /// Returns the names of the labels of the config type, sorted.
+    fn labels(&self, config_type: &str) -> Result<Vec<String>, StorageBackendError>;
+
+    fn has_label(&self, config_type: &str, label: &str) -> Result<bool, StorageBackendError>;
+
+    /// Returns the paths of the files of the label, relative to the label and sorted.
+    fn label_files(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<PathBuf>, StorageBackendError>;
+
+    /// Returns `None` if the label does not have the file.
+    fn read_label_file(
+        &self,
+        config_type: &str,
+        label: &str,
+        path: &Path,
+    ) -> Result<Option<Vec<u8>>, StorageBackendError>;
+
+    /// Returns the revisions of the label, oldest first. Every `PutLabel` adds a revision.
+    fn label_revisions(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<Vec<LabelRevision>, StorageBackendError>;
+
+    /// Reads every file of the label as it was in `revision`.
+    fn read_label_revision(
+        &self,
+        config_type: &str,
+        label: &str,
+        revision: u32,
+    ) -> Result<LabelFiles, StorageBackendError>;
+
+    /// Returns the names of the profiles, sorted.
+    fn profiles(&self) -> Result<Vec<String>, StorageBackendError>;
+
+    /// Returns `None` if the profile does not exist.
+    fn read_profile(&self, profile: &str) -> Result<Option<Vec<u8>>, StorageBackendError>;
+
+    /// Applies every operation of the transaction, or none of them.
+    fn commit(&self, transaction: StorageTransaction) -> Result<(), StorageBackendError>;
+
+    /// Frees the space of file contents no label refers to anymore. Backends without shared
+    /// contents have nothing to collect.
+    fn collect_garbage(&self) -> Result<GarbageCollection, StorageBackendError> {
+        Ok(GarbageCollection::default())
+    }
+
+    /// Reads every file of the label.
+    fn read_label(
+        &self,
+        config_type: &str,
+        label: &str,
+    ) -> Result<LabelFiles, StorageBackendError> {
+        if !self.has_label(config_type, label)? {
+            return Err(StorageBackendError::LabelNotFound {
+                config_type: config_type.into(),
+                label: label.into(),
+            });
+        }
+
+        let mut files = LabelFiles::new();
+        for path in self.label_files(config_type, label)? {
+            if let Some(content) = self.read_label_file(config_type, label, &path)? {
+                files.insert(path, content);
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// A stored version of a label, numbered from 1.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LabelRevision {
+    number: u32,
+    created_at: DateTime<Utc>,
+    message: Option<String>,
+}
+
+impl LabelRevision {
+    pub fn new(number: u32, created_at: DateTime<Utc>, message: Option<String>) -> Self {
+        Self {
+            number,
+            created_at,
+            message,
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// What `StorageBackend::collect_garbage` removed.
+#[derive(Clone, Copy, Default)]
+pub struct GarbageCollection {
+    removed_objects: usize,
+    freed_bytes: u64,
+}
+
+impl GarbageCollection {
+    pub fn new(removed_objects: usize, freed_bytes: u64) -> Self {
+        Self {
+            removed_objects,
+            freed_bytes,
+        }
+    }
+
+    pub fn removed_objects(&self) -> usize {
+        self.removed_objects
+    }
+
+    pub fn freed_bytes(&self) -> u64 {
+        self.freed_bytes
+    }
+}
+
+pub enum StorageOperation {
+    /// Creates a config type with its own files (e.g. the descriptor) and its labels.
+    CreateConfigType {
+        config_type: String,
+        files: BTreeMap<String, Vec<u8>>,
+        labels: BTreeMap<String, LabelFiles>,
+    },
+    RemoveConfigType {
+        config_type: String,
+    },
+    /// Creates or replaces a file of an existing config type.
+    PutConfigTypeFile {
+        config_type: String,
+        name: String,
+        content: Vec<u8>,
+    },
+    /// Creates a label, or replaces every file of an existing one, as a new revision. Nothing is
+    /// written if the files, apart from the metadata of the label, are the same as before.
+    PutLabel {
+        config_type: String,
+        label: String,
+        files: LabelFiles,
+        message: Option<String>,
+    },
+    /// Creates or replaces a single file of an existing label, without a new revision.
+    PutLabelFile {
+        config_type: String,
+        label: String,
+        path: PathBuf,
+        content: Vec<u8>,
+    },
+    /// Creates a label, or replaces an existing one, with the given revisions instead of adding
+    /// one, their numbers, dates and messages are kept (e.g. a label restored from the trash).
+    /// Without revisions, the files become the first revision.
+    RestoreLabel {
+        config_type: String,
+        label: String,
+        files: LabelFiles,
+        revisions: Vec<(LabelRevision, LabelFiles)>,
+    },
+    /// Replaces the files of an existing label and of its revisions without adding a revision,
+    /// the numbers, dates and messages of the revisions are kept (e.g. after re-encryption).
+    /// Revisions missing from `revisions` are left as they are.
+    RewriteLabel {
+        config_type: String,
+        label: String,
+        files: LabelFiles,
+        revisions: BTreeMap<u32, LabelFiles>,
+    },
+    /// Renames a label together with its revisions, replacing `new_label` if it exists.
+    RenameLabel {
+        config_type: String,
+        label: String,
+        new_label: String,
+    },
+    RemoveLabel {
+        config_type: String,
+        label: String,
+    },
+    /// Creates or replaces a profile.
+    PutProfile {
+        profile: String,
+        content: Vec<u8>,
+    },
+}
+
+/// Changes committed together by `StorageBackend::commit`, applied in the order they were added.
+#[derive(Default)]
+pub struct StorageTransaction {
+    operations: Vec<StorageOperation>,
+}
+
+impl StorageTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn operations(&self) -> &[StorageOperation] {
+        &self.operations
+    }
+
+    pub fn into_operations(self) -> Vec<StorageOperation> {
+        self.operations
+    }
+
+    pub fn create_config_type(
+        &mut self,
+        config_type: impl Into<String>,
+        files: BTreeMap<String, Vec<u8>>,
+        labels: BTreeMap<String, LabelFiles>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::CreateConfigType {
+            config_type: config_type.into(),
+            files,
+            labels,
+        });
+        self
+    }
+
+    pub fn remove_config_type(&mut self, config_type: impl Into<String>) -> &mut Self {
+        self.operations.push(StorageOperation::RemoveConfigType {
+            config_type: config_type.into(),
+        });
+        self
+    }
+
+    pub fn put_config_type_file(
+        &mut self,
+        config_type: impl Into<String>,
+        name: impl Into<String>,
+        content: Vec<u8>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::PutConfigTypeFile {
+            config_type: config_type.into(),
+            name: name.into(),
+            content,
+        });
+        self
+    }
+
+    pub fn put_label(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+        files: LabelFiles,
+        message: Option<String>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::PutLabel {
+            config_type: config_type.into(),
+            label: label.into(),
+            files,
+            message,
+        });
+        self
+    }
+
+    pub fn put_label_file(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+        path: impl Into<PathBuf>,
+        content: Vec<u8>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::PutLabelFile {
+            config_type: config_type.into(),
+            label: label.into(),
+            path: path.into(),
+            content,
+        });
+        self
+    }
+
+    pub fn restore_label(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+        files: LabelFiles,
+        revisions: Vec<(LabelRevision, LabelFiles)>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::RestoreLabel {
+            config_type: config_type.into(),
+            label: label.into(),
+            files,
+            revisions,
+        });
+        self
+    }
+
+    pub fn rewrite_label(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+        files: LabelFiles,
+        revisions: BTreeMap<u32, LabelFiles>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::RewriteLabel {
+            config_type: config_type.into(),
+            label: label.into(),
+            files,
+            revisions,
+        });
+        self
+    }
+
+    pub fn rename_label(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+        new_label: impl Into<String>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::RenameLabel {
+            config_type: config_type.into(),
+            label: label.into(),
+            new_label: new_label.into(),
+        });
+        self
+    }
+
+    pub fn remove_label(
+        &mut self,
+        config_type: impl Into<String>,
+        label: impl Into<String>,
+    ) -> &mut Self {
+        self.operations.push(StorageOperation::RemoveLabel {
+            config_type: config_type.into(),
+            label: label.into(),
+        });
+        self
+    }
+
+    pub fn put_profile(&mut self, profile: impl Into<String>, content: Vec<u8>) -> &mut Self {
+        self.operations.push(StorageOperation::PutProfile {
+            profile: profile.into(),
+            content,
+        });
+        self
+    }
+}
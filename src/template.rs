@@ -0,0 +1,65 @@
+use std::{fs, path::Path};
+
+use crate::{error::TemplateFileError, variable_resolver::VariableResolver};
+
+/// Copies a file into a label, replacing the variable values (e.g. the home directory) in its
+/// content with variables. Binary files are copied as they are.
+pub fn store_template_file(
+    src_file_path: impl AsRef<Path>,
+    dest_file_path: impl AsRef<Path>,
+    variable_resolver: &VariableResolver,
+) -> Result<(), TemplateFileError> {
+    let content = read_file(&src_file_path)?;
+
+    let content = match as_text(&content) {
+        Some(text) => variable_resolver.encode_text(text).into_bytes(),
+        None => content,
+    };
+
+    write_file(dest_file_path, content)
+}
+
+/// Copies a file from a label, replacing the variables in its content with their values on this
+/// machine. Binary files are copied as they are.
+pub fn load_template_file(
+    src_file_path: impl AsRef<Path>,
+    dest_file_path: impl AsRef<Path>,
+    variable_resolver: &VariableResolver,
+) -> Result<(), TemplateFileError> {
+    let content = read_file(&src_file_path)?;
+
+    let content = match as_text(&content) {
+        Some(text) => variable_resolver
+            .decode_string(text)
+            .map_err(|e| TemplateFileError::CouldNotDecodeTemplate {
+                error: e,
+                path: src_file_path.as_ref().to_path_buf(),
+            })?
+            .into_bytes(),
+        None => content,
+    };
+
+    write_file(dest_file_path, content)
+}
+
+fn as_text(content: &[u8]) -> Option<&str> {
+    if content_inspector::inspect(content).is_binary() {
+        None
+    } else {
+        std::str::from_utf8(content).ok()
+    }
+}
+
+fn read_file(path: impl AsRef<Path>) -> Result<Vec<u8>, TemplateFileError> {
+    fs::read(&path).map_err(|e| TemplateFileError::CouldNotReadFile {
+        io_error: e,
+        path: path.as_ref().to_path_buf(),
+    })
+}
+
+fn write_file(path: impl AsRef<Path>, content: Vec<u8>) -> Result<(), TemplateFileError> {
+    fs::write(&path, content).map_err(|e| TemplateFileError::CouldNotWriteFile {
+        io_error: e,
+        path: path.as_ref().to_path_buf(),
+    })
+}
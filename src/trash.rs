@@ -0,0 +1,454 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fs::{remove_dir_all, rename},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    directories::Directories,
+    error::TrashError,
+    storage_backend::{LabelFiles, LabelRevision, StorageBackend, StorageTransaction},
+    utils::{
+        create_new_directory, read_files_recursive, read_json_file, write_files_recursive,
+        write_json_file,
+    },
+};
+
+/// Deleted entries older than this are purged from the trash.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+const TRASH_ENTRY_FILENAME: &str = "entry.json";
+const TRASH_DATA_DIRNAME: &str = "data";
+const TRASH_STATE_DIRNAME: &str = "state";
+const TRASH_REVISIONS_DIRNAME: &str = "revisions";
+const TRASH_REVISIONS_FILENAME: &str = "revisions.json";
+
+/// Entry directories without an entry file older than this are leftovers of interrupted
+/// deletions.
+const INCOMPLETE_ENTRY_GRACE_HOURS: i64 = 24;
+
+/// A deleted config type (`label` is `None`) or a deleted label of a config type.
+#[derive(Serialize, Deserialize)]
+pub struct TrashEntry {
+    #[serde(skip)]
+    id: String,
+    config_type: String,
+    label: Option<String>,
+    deleted_at: DateTime<Utc>,
+}
+
+impl TrashEntry {
+    pub fn config_type(&self) -> &str {
+        &self.config_type
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn deleted_at(&self) -> DateTime<Utc> {
+        self.deleted_at
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.deleted_at + Duration::days(TRASH_RETENTION_DAYS)
+    }
+}
+
+/// Keeps deleted config types and labels under `<root>/trash/<id>`, so they can be restored.
+///
+/// The files are taken out of the storage backend, a label is kept as a directory of its files,
+/// a config type as a directory of its own files and one sub directory per label. The revisions
+/// of a label are kept under `revisions` (of a config type under `revisions/<label>`), as one
+/// directory of files per revision and `revisions.json` listing them, so a label is restored with
+/// its history.
+pub(crate) struct Trash {
+    directories: Arc<Directories>,
+    backend: Arc<dyn StorageBackend>,
+}
+
+impl Trash {
+    pub fn new(directories: Arc<Directories>, backend: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            directories,
+            backend,
+        }
+    }
+
+    pub fn put_label(
+        &self,
+        config_type: impl AsRef<str>,
+        label: impl AsRef<str>,
+    ) -> Result<(), TrashError> {
+        let entry_dir_path = self.create_entry_dir()?;
+
+        let mut transaction = StorageTransaction::new();
+        transaction.remove_label(config_type.as_ref(), label.as_ref());
+        self.commit_removal(&entry_dir_path, transaction, || {
+            self.write_label(
+                config_type.as_ref(),
+                label.as_ref(),
+                &entry_dir_path.join(TRASH_DATA_DIRNAME),
+                &entry_dir_path.join(TRASH_REVISIONS_DIRNAME),
+            )?;
+            write_entry(&entry_dir_path, config_type.as_ref(), Some(label.as_ref()))
+        })
+    }
+
+    pub fn put_config_type(&self, config_type: impl AsRef<str>) -> Result<(), TrashError> {
+        let entry_dir_path = self.create_entry_dir()?;
+
+        let mut transaction = StorageTransaction::new();
+        transaction.remove_config_type(config_type.as_ref());
+        self.commit_removal(&entry_dir_path, transaction, || {
+            let data_dir_path = entry_dir_path.join(TRASH_DATA_DIRNAME);
+            let mut files = BTreeMap::new();
+            for name in self.backend.config_type_files(config_type.as_ref())? {
+                if let Some(content) = self
+                    .backend
+                    .read_config_type_file(config_type.as_ref(), &name)?
+                {
+                    files.insert(PathBuf::from(name), content);
+                }
+            }
+            write_dir(&data_dir_path, &files)?;
+            for label in self.backend.labels(config_type.as_ref())? {
+                self.write_label(
+                    config_type.as_ref(),
+                    &label,
+                    &data_dir_path.join(&label),
+                    &entry_dir_path.join(TRASH_REVISIONS_DIRNAME).join(&label),
+                )?;
+            }
+            write_entry(&entry_dir_path, config_type.as_ref(), None)
+        })?;
+
+        let state_dir_path = self
+            .directories
+            .config_type_state_dir_path(config_type.as_ref());
+        if state_dir_path.exists() {
+            move_dir(state_dir_path, entry_dir_path.join(TRASH_STATE_DIRNAME))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the entries of the trash, the most recently deleted first.
+    pub fn entries(&self) -> Result<Vec<TrashEntry>, TrashError> {
+        let mut entries = Vec::new();
+
+        let read_dir = std::fs::read_dir(self.directories.trash_dir_path()).map_err(|e| {
+            TrashError::CouldNotReadDirectory {
+                io_error: e,
+                path: self.directories.trash_dir_path().to_path_buf(),
+            }
+        })?;
+        for dir_entry in read_dir {
+            let dir_entry = dir_entry.map_err(|e| TrashError::CouldNotReadDirectory {
+                io_error: e,
+                path: self.directories.trash_dir_path().to_path_buf(),
+            })?;
+
+            let entry_file_path = dir_entry.path().join(TRASH_ENTRY_FILENAME);
+            if !entry_file_path.is_file() {
+                // the entry is incomplete, e.g. the deletion was interrupted
+                continue;
+            }
+
+            let mut entry: TrashEntry =
+                read_json_file(&entry_file_path).map_err(|e| TrashError::CouldNotReadEntry {
+                    error: e,
+                    path: entry_file_path,
+                })?;
+            entry.id = dir_entry.file_name().to_string_lossy().into();
+            entries.push(entry);
+        }
+
+        entries.sort_by_key(|entry| Reverse(entry.deleted_at));
+
+        Ok(entries)
+    }
+
+    /// Restores the most recently deleted entry matching `config_type` and `label`.
+    pub fn restore(
+        &self,
+        config_type: impl AsRef<str>,
+        label: Option<&str>,
+    ) -> Result<TrashEntry, TrashError> {
+        let entry = self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.config_type == config_type.as_ref() && entry.label() == label)
+            .ok_or_else(|| TrashError::EntryNotFound {
+                config_type: config_type.as_ref().into(),
+                label: label.map(String::from),
+            })?;
+
+        let entry_dir_path = self.directories.trash_entry_dir_path(&entry.id);
+        let data_dir_path = entry_dir_path.join(TRASH_DATA_DIRNAME);
+
+        let mut transaction = StorageTransaction::new();
+        match &entry.label {
+            Some(label) => {
+                if !self.backend.has_config_type(&entry.config_type)? {
+                    return Err(TrashError::ConfigTypeNotFound(entry.config_type.clone()));
+                }
+                if self.backend.has_label(&entry.config_type, label)? {
+                    return Err(TrashError::LabelAlreadyExists {
+                        config_type: entry.config_type.clone(),
+                        label: label.clone(),
+                    });
+                }
+
+                transaction.restore_label(
+                    &entry.config_type,
+                    label,
+                    read_dir(&data_dir_path)?,
+                    read_revisions(&entry_dir_path.join(TRASH_REVISIONS_DIRNAME))?,
+                );
+            }
+            None => {
+                if self.backend.has_config_type(&entry.config_type)? {
+                    return Err(TrashError::ConfigTypeAlreadyExists(
+                        entry.config_type.clone(),
+                    ));
+                }
+
+                let (files, labels) = read_config_type_dir(&data_dir_path)?;
+                transaction.create_config_type(&entry.config_type, files, BTreeMap::new());
+                for (label, files) in labels {
+                    let revisions =
+                        read_revisions(&entry_dir_path.join(TRASH_REVISIONS_DIRNAME).join(&label))?;
+                    transaction.restore_label(&entry.config_type, label, files, revisions);
+                }
+            }
+        }
+        self.backend.commit(transaction)?;
+
+        let trashed_state_dir_path = entry_dir_path.join(TRASH_STATE_DIRNAME);
+        let state_dir_path = self
+            .directories
+            .config_type_state_dir_path(&entry.config_type);
+        if trashed_state_dir_path.exists() && !state_dir_path.exists() {
+            move_dir(trashed_state_dir_path, state_dir_path)?;
+        }
+
+        remove_entry_dir(entry_dir_path)?;
+
+        Ok(entry)
+    }
+
+    /// Removes the entries which are older than the retention window, and the directories of
+    /// deletions which were interrupted before their entry file was written.
+    pub fn purge_expired(&self) -> Result<(), TrashError> {
+        let now = Utc::now();
+        for entry in self.entries()? {
+            if entry.expires_at() < now {
+                remove_entry_dir(self.directories.trash_entry_dir_path(&entry.id))?;
+            }
+        }
+
+        for entry_dir_path in self.incomplete_entry_dirs()? {
+            // a deletion running in another process is given time to finish
+            let modified_at = entry_dir_path
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(DateTime::<Utc>::from)
+                .unwrap_or(now);
+            if modified_at + Duration::hours(INCOMPLETE_ENTRY_GRACE_HOURS) < now {
+                remove_entry_dir(entry_dir_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn incomplete_entry_dirs(&self) -> Result<Vec<PathBuf>, TrashError> {
+        let map_err = |e| TrashError::CouldNotReadDirectory {
+            io_error: e,
+            path: self.directories.trash_dir_path().to_path_buf(),
+        };
+
+        let mut entry_dir_paths = Vec::new();
+        for dir_entry in std::fs::read_dir(self.directories.trash_dir_path()).map_err(map_err)? {
+            let entry_dir_path = dir_entry.map_err(map_err)?.path();
+            if entry_dir_path.is_dir() && !entry_dir_path.join(TRASH_ENTRY_FILENAME).is_file() {
+                entry_dir_paths.push(entry_dir_path);
+            }
+        }
+
+        Ok(entry_dir_paths)
+    }
+
+    /// Writes the entry with `write`, then removes what it holds from the storage with
+    /// `transaction`. If either fails, the entry is removed again, so it cannot be restored over
+    /// the config type or label which is still stored.
+    fn commit_removal(
+        &self,
+        entry_dir_path: &Path,
+        transaction: StorageTransaction,
+        write: impl FnOnce() -> Result<(), TrashError>,
+    ) -> Result<(), TrashError> {
+        let result = write().and_then(|()| Ok(self.backend.commit(transaction)?));
+        if result.is_err() {
+            if let Err(e) = remove_dir_all(entry_dir_path) {
+                log::warn!(
+                    "could not remove trash entry, path = {:?}, error = {}",
+                    entry_dir_path,
+                    e
+                );
+            }
+        }
+
+        result
+    }
+
+    /// Writes the current files of the label to `data_dir_path`, and its revisions to
+    /// `revisions_dir_path`.
+    fn write_label(
+        &self,
+        config_type: &str,
+        label: &str,
+        data_dir_path: &Path,
+        revisions_dir_path: &Path,
+    ) -> Result<(), TrashError> {
+        write_dir(data_dir_path, &self.backend.read_label(config_type, label)?)?;
+
+        let revisions = self.backend.label_revisions(config_type, label)?;
+        for revision in &revisions {
+            let files = self
+                .backend
+                .read_label_revision(config_type, label, revision.number())?;
+            write_dir(
+                revisions_dir_path.join(revision.number().to_string()),
+                &files,
+            )?;
+        }
+
+        let revisions_file_path = revisions_dir_path.join(TRASH_REVISIONS_FILENAME);
+        write_json_file(&revisions, &revisions_file_path).map_err(|e| {
+            TrashError::CouldNotWriteEntry {
+                error: e,
+                path: revisions_file_path,
+            }
+        })
+    }
+
+    fn create_entry_dir(&self) -> Result<PathBuf, TrashError> {
+        let id = uuid::Uuid::new_v4().as_hyphenated().to_string();
+        let entry_dir_path = self.directories.trash_entry_dir_path(id);
+
+        create_new_directory(&entry_dir_path)?;
+
+        Ok(entry_dir_path)
+    }
+}
+
+/// The entry file is written once the data is, before the config type or label is removed from
+/// the storage, entries without it are ignored and eventually purged.
+fn write_entry(
+    entry_dir_path: &Path,
+    config_type: &str,
+    label: Option<&str>,
+) -> Result<(), TrashError> {
+    let entry = TrashEntry {
+        id: String::new(),
+        config_type: config_type.into(),
+        label: label.map(String::from),
+        deleted_at: Utc::now(),
+    };
+
+    let entry_file_path = entry_dir_path.join(TRASH_ENTRY_FILENAME);
+    write_json_file(&entry, &entry_file_path).map_err(|e| TrashError::CouldNotWriteEntry {
+        error: e,
+        path: entry_file_path,
+    })
+}
+
+fn write_dir(dir_path: impl AsRef<Path>, files: &LabelFiles) -> Result<(), TrashError> {
+    write_files_recursive(&dir_path, files).map_err(|e| TrashError::CouldNotWriteFiles {
+        io_error: e,
+        path: dir_path.as_ref().to_path_buf(),
+    })
+}
+
+fn read_dir(dir_path: &Path) -> Result<LabelFiles, TrashError> {
+    read_files_recursive(dir_path).map_err(|e| TrashError::CouldNotReadDirectory {
+        io_error: e,
+        path: dir_path.to_path_buf(),
+    })
+}
+
+/// Reads the revisions of a trashed label, empty for labels trashed before their revisions were
+/// kept.
+fn read_revisions(
+    revisions_dir_path: &Path,
+) -> Result<Vec<(LabelRevision, LabelFiles)>, TrashError> {
+    let revisions_file_path = revisions_dir_path.join(TRASH_REVISIONS_FILENAME);
+    if !revisions_file_path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let revisions: Vec<LabelRevision> =
+        read_json_file(&revisions_file_path).map_err(|e| TrashError::CouldNotReadEntry {
+            error: e,
+            path: revisions_file_path,
+        })?;
+    revisions
+        .into_iter()
+        .map(|revision| {
+            let files = read_dir(&revisions_dir_path.join(revision.number().to_string()))?;
+            Ok((revision, files))
+        })
+        .collect()
+}
+
+/// Splits a trashed config type into its own files and its labels.
+type ConfigTypeLabels = BTreeMap<String, LabelFiles>;
+
+fn read_config_type_dir(
+    dir_path: &Path,
+) -> Result<(BTreeMap<String, Vec<u8>>, ConfigTypeLabels), TrashError> {
+    let map_err = |e| TrashError::CouldNotReadDirectory {
+        io_error: e,
+        path: dir_path.to_path_buf(),
+    };
+
+    let mut files = BTreeMap::new();
+    let mut labels = BTreeMap::new();
+    for dir_entry in std::fs::read_dir(dir_path).map_err(map_err)? {
+        let dir_entry = dir_entry.map_err(map_err)?;
+        let name = dir_entry.file_name().to_string_lossy().to_string();
+        if dir_entry.file_type().map_err(map_err)?.is_dir() {
+            // backups were kept in the config type directory before, they are not labels
+            if !Directories::is_reserved_label_name(&name) {
+                labels.insert(name, read_dir(&dir_entry.path())?);
+            }
+        } else {
+            let content = std::fs::read(dir_entry.path()).map_err(map_err)?;
+            files.insert(name, content);
+        }
+    }
+
+    Ok((files, labels))
+}
+
+fn move_dir(source_path: impl AsRef<Path>, dest_path: impl AsRef<Path>) -> Result<(), TrashError> {
+    rename(&source_path, &dest_path).map_err(|e| TrashError::CouldNotMoveDirectory {
+        io_error: e,
+        source_path: source_path.as_ref().to_path_buf(),
+        dest_path: dest_path.as_ref().to_path_buf(),
+    })
+}
+
+fn remove_entry_dir(path: impl AsRef<Path>) -> Result<(), TrashError> {
+    remove_dir_all(&path).map_err(|e| TrashError::CouldNotRemoveDirectory {
+        io_error: e,
+        path: path.as_ref().to_path_buf(),
+    })
+}
@@ -1,9 +1,16 @@
 use std::{
-    fs::{create_dir, DirEntry, ReadDir},
-    path::{Path, PathBuf},
+    collections::BTreeMap,
+    fs::{create_dir, create_dir_all, remove_dir_all, DirEntry, File, ReadDir},
+    io::Write,
+    path::{Component, Path, PathBuf},
 };
 
-use crate::error::{CreateNewDirectoryError, EnsureDirectoryError};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{
+    CreateNewDirectoryError, EnsureDirectoryError, ReadJsonFileError, WriteJsonFileError,
+};
 
 pub fn ensure_directory(
     path: impl AsRef<Path>,
@@ -19,9 +26,11 @@ pub fn ensure_directory(
                 return Err(EnsureDirectoryError::PathIsNotADirectory(ancestor_path));
             }
         } else if create_if_not_exists {
-            create_dir(&path).map_err(|e| EnsureDirectoryError::CouldNotCreateDirectory {
-                path: path.clone(),
-                error: e,
+            create_dir(&ancestor_path).map_err(|e| {
+                EnsureDirectoryError::CouldNotCreateDirectory {
+                    path: ancestor_path.clone(),
+                    error: e,
+                }
             })?;
         } else {
             return Err(EnsureDirectoryError::PathDoesNotExist(ancestor_path));
@@ -48,6 +57,43 @@ pub fn create_new_directory(path: impl AsRef<Path>) -> Result<(), CreateNewDirec
     }
 }
 
+/// A directory which is removed with its content when dropped, e.g. a checkout of a label.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self, CreateNewDirectoryError> {
+        let path = path.into();
+        create_new_directory(&path)?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the directory instead of removing it, e.g. once it has been renamed to its final
+    /// place.
+    pub fn keep(mut self) -> PathBuf {
+        let path = std::mem::take(&mut self.path);
+        std::mem::forget(self);
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = remove_dir_all(&self.path) {
+            log::warn!(
+                "could not remove temp directory, path = {:?}, error = {}",
+                self.path,
+                e
+            );
+        }
+    }
+}
+
 pub struct SubDirectoryIterator {
     paths: ReadDir,
 }
@@ -79,3 +125,173 @@ impl Iterator for SubDirectoryIterator {
         None
     }
 }
+
+pub fn hash_file(path: impl AsRef<Path>) -> Result<String, std::io::Error> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn read_json_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ReadJsonFileError> {
+    let file = File::open(path).map_err(ReadJsonFileError::CouldNotOpenFile)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+pub fn write_json_file<T: Serialize>(
+    value: &T,
+    path: impl AsRef<Path>,
+) -> Result<(), WriteJsonFileError> {
+    let serialized = serde_json::to_string_pretty(value)?;
+
+    let mut file = File::create(path).map_err(WriteJsonFileError::CouldNotOpenFile)?;
+
+    file.write_all(serialized.as_bytes())
+        .map_err(WriteJsonFileError::CouldNotWriteDataToFile)?;
+
+    Ok(())
+}
+
+pub fn copy_dir_recursive(
+    src_dir: impl AsRef<Path>,
+    dest_dir: impl AsRef<Path>,
+) -> Result<(), std::io::Error> {
+    let dest_dir = dest_dir.as_ref();
+    if !dest_dir.exists() {
+        create_dir(dest_dir)?;
+    }
+
+    for dir_entry in std::fs::read_dir(src_dir)? {
+        let dir_entry = dir_entry?;
+        let mut dest_path = dest_dir.to_path_buf();
+        dest_path.push(dir_entry.file_name());
+
+        if dir_entry.file_type()?.is_dir() {
+            copy_dir_recursive(dir_entry.path(), &dest_path)?;
+        } else {
+            std::fs::copy(dir_entry.path(), &dest_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Hashes a file, or a directory tree including the relative paths of its files.
+pub fn hash_path(path: impl AsRef<Path>) -> Result<String, std::io::Error> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return hash_file(path);
+    }
+
+    let mut hasher = Sha256::new();
+    for relative_file_path in collect_files_recursive(path)? {
+        let mut file_path = path.to_path_buf();
+        file_path.push(&relative_file_path);
+
+        hasher.update(relative_file_path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(hash_file(&file_path)?.as_bytes());
+        hasher.update([b'\n']);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Returns the paths of all the files under `dir`, relative to `dir` and sorted.
+pub fn collect_files_recursive(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, std::io::Error> {
+    fn collect(
+        dir: &Path,
+        relative_dir: &Path,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<(), std::io::Error> {
+        for dir_entry in std::fs::read_dir(dir)? {
+            let dir_entry = dir_entry?;
+            let relative_path = relative_dir.join(dir_entry.file_name());
+            if dir_entry.file_type()?.is_dir() {
+                collect(&dir_entry.path(), &relative_path, files)?;
+            } else {
+                files.push(relative_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    collect(dir.as_ref(), Path::new(""), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// Reads every file under `dir`, keyed by their paths relative to `dir`.
+pub fn read_files_recursive(
+    dir: impl AsRef<Path>,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>, std::io::Error> {
+    let mut files = BTreeMap::new();
+    for relative_file_path in collect_files_recursive(&dir)? {
+        let content = std::fs::read(dir.as_ref().join(&relative_file_path))?;
+        files.insert(relative_file_path, content);
+    }
+
+    Ok(files)
+}
+
+/// Returns the path as it is kept in a label directory, `None` if it would end up outside of it,
+/// i.e. it is absolute (e.g. a descriptor path which does not start with a variable) or goes up
+/// with `..`.
+pub fn label_relative_path(path: &(impl AsRef<Path> + ?Sized)) -> Option<&Path> {
+    let path = path.as_ref();
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        .then_some(path)
+}
+
+/// Writes `files`, keyed by their paths relative to `dir`, creating `dir` and the sub directories.
+/// Fails on paths which would end up outside of `dir`.
+pub fn write_files_recursive<'a>(
+    dir: impl AsRef<Path>,
+    files: impl IntoIterator<Item = (&'a PathBuf, &'a Vec<u8>)>,
+) -> Result<(), std::io::Error> {
+    create_dir_all(&dir)?;
+    for (relative_file_path, content) in files {
+        let relative_file_path = label_relative_path(relative_file_path).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("path is outside of the directory: {:?}", relative_file_path),
+            )
+        })?;
+        let file_path = dir.as_ref().join(relative_file_path);
+        if let Some(parent_path) = file_path.parent() {
+            create_dir_all(parent_path)?;
+        }
+        std::fs::write(file_path, content)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_directory_creates_missing_ancestors() {
+        let dir_path =
+            std::env::temp_dir().join(format!("config-picker-test-{}", uuid::Uuid::new_v4()));
+        let nested_dir_path = dir_path.join("a").join("b").join("c");
+
+        assert!(matches!(
+            ensure_directory(&nested_dir_path, false),
+            Err(EnsureDirectoryError::PathDoesNotExist(_))
+        ));
+        ensure_directory(&nested_dir_path, true).unwrap();
+        assert!(nested_dir_path.is_dir());
+
+        std::fs::write(dir_path.join("file"), "").unwrap();
+        assert!(matches!(
+            ensure_directory(dir_path.join("file").join("d"), true),
+            Err(EnsureDirectoryError::PathIsNotADirectory(_))
+        ));
+
+        std::fs::remove_dir_all(&dir_path).unwrap();
+    }
+}
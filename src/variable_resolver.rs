@@ -1,27 +1,215 @@
-use std::collections::BTreeMap;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
 
-use directories::BaseDirs;
-use variable_resolver::error::DecodeStringError;
+use directories::{BaseDirs, ProjectDirs, UserDirs};
 
+use crate::error::DecodeStringError;
+
+const ENV_VARIABLE_PREFIX: &str = "env:";
+const DEFAULT_VALUE_SEPARATOR: &str = ":-";
+
+/// Resolves the variables of descriptors and templates, e.g. `{{HOME}}`, `{{CONFIG_DIR}}`,
+/// `{{env:EDITOR}}` or `{{EDITOR:-vim}}` with a default value.
 pub struct VariableResolver {
     variables: BTreeMap<String, String>,
+
+    /// Only these variables are used for replacing values with variables, the others (e.g.
+    /// `{{USER}}`) would match unrelated text or differ between platforms.
+    encodable_variable_names: BTreeSet<String>,
 }
 
 impl VariableResolver {
     pub fn new(base_dirs: BaseDirs) -> Self {
+        let mut variables = BTreeMap::new();
+
+        let mut add_dir = |name: &str, path: Option<&Path>| {
+            if let Some(path) = path {
+                variables.insert(name.to_string(), path.to_string_lossy().to_string());
+            }
+        };
+
+        add_dir("HOME", Some(base_dirs.home_dir()));
+        add_dir("CACHE_DIR", Some(base_dirs.cache_dir()));
+        add_dir("CONFIG_DIR", Some(base_dirs.config_dir()));
+        add_dir("CONFIG_LOCAL_DIR", Some(base_dirs.config_local_dir()));
+        add_dir("DATA_DIR", Some(base_dirs.data_dir()));
+        add_dir("DATA_LOCAL_DIR", Some(base_dirs.data_local_dir()));
+        add_dir("EXECUTABLE_DIR", base_dirs.executable_dir());
+        add_dir("PREFERENCE_DIR", Some(base_dirs.preference_dir()));
+        add_dir("RUNTIME_DIR", base_dirs.runtime_dir());
+        add_dir("STATE_DIR", base_dirs.state_dir());
+
+        if let Some(user_dirs) = UserDirs::new() {
+            add_dir("AUDIO", user_dirs.audio_dir());
+            add_dir("DESKTOP", user_dirs.desktop_dir());
+            add_dir("DOCUMENTS", user_dirs.document_dir());
+            add_dir("DOWNLOADS", user_dirs.download_dir());
+            add_dir("FONTS", user_dirs.font_dir());
+            add_dir("PICTURES", user_dirs.picture_dir());
+            add_dir("PUBLIC", user_dirs.public_dir());
+            add_dir("TEMPLATES", user_dirs.template_dir());
+            add_dir("VIDEOS", user_dirs.video_dir());
+        }
+
+        if let Some(project_dirs) = ProjectDirs::from("", "", "config-picker") {
+            add_dir("PROJECT_CACHE_DIR", Some(project_dirs.cache_dir()));
+            add_dir("PROJECT_CONFIG_DIR", Some(project_dirs.config_dir()));
+            add_dir("PROJECT_DATA_DIR", Some(project_dirs.data_dir()));
+        }
+
+        if let Ok(user) = std::env::var("USER").or_else(|_| std::env::var("USERNAME")) {
+            variables.insert("USER".into(), user);
+        }
+        variables.insert(
+            "HOSTNAME".into(),
+            gethostname::gethostname().to_string_lossy().to_string(),
+        );
+
         Self {
-            variables: BTreeMap::from([(
-                "HOME".to_string(),
-                base_dirs.home_dir().to_string_lossy().to_string(),
-            )]),
+            variables,
+            encodable_variable_names: BTreeSet::from(["HOME".to_string()]),
         }
     }
 
-    fn resolve_variable(&self, varname: impl AsRef<str>) -> Option<&str> {
-        self.variables.get(varname.as_ref()).map(|s| s.as_str())
+    /// Adds variables defined by the user, they override the built-in variables and are also
+    /// used for replacing values with variables.
+    pub fn add_user_variables(&mut self, user_variables: BTreeMap<String, String>) {
+        for (name, value) in user_variables {
+            self.encodable_variable_names.insert(name.clone());
+            self.variables.insert(name, value);
+        }
+    }
+
+    /// Resolves a variable, `name` may contain a default value after `:-`.
+    fn resolve_variable(&self, name: &str) -> Option<String> {
+        let (name, default_value) = match name.split_once(DEFAULT_VALUE_SEPARATOR) {
+            Some((name, default_value)) => (name, Some(default_value)),
+            None => (name, None),
+        };
+
+        let value = match name.strip_prefix(ENV_VARIABLE_PREFIX) {
+            Some(env_name) => std::env::var(env_name).ok(),
+            None => self.variables.get(name).cloned(),
+        };
+
+        value.or_else(|| default_value.map(String::from))
     }
 
     pub fn decode_string(&self, text: impl AsRef<str>) -> Result<String, DecodeStringError> {
-        variable_resolver::decode_string(text, |name| self.resolve_variable(name))
+        self.decode(text, |value| value)
+    }
+
+    /// Like `decode_string`, but the variable values are escaped, so they are matched literally
+    /// when the result is used as a glob pattern.
+    pub fn decode_glob_pattern(&self, text: impl AsRef<str>) -> Result<String, DecodeStringError> {
+        self.decode(text, |value| globset::escape(&value))
     }
+
+    /// Resolves every variable of the text first, so all the undefined variables are reported
+    /// at once.
+    fn decode(
+        &self,
+        text: impl AsRef<str>,
+        map_value: impl Fn(String) -> String,
+    ) -> Result<String, DecodeStringError> {
+        let names = RefCell::new(BTreeSet::new());
+        variable_resolver::decode_string(text.as_ref(), |name| {
+            names.borrow_mut().insert(name.to_string());
+            Some("")
+        })?;
+
+        let mut values = BTreeMap::new();
+        let mut undefined_variable_names = Vec::new();
+        for name in names.into_inner() {
+            match self.resolve_variable(&name) {
+                Some(value) => {
+                    values.insert(name, map_value(value));
+                }
+                None => undefined_variable_names.push(name),
+            }
+        }
+
+        if !undefined_variable_names.is_empty() {
+            return Err(DecodeStringError::UndefinedVariables(
+                undefined_variable_names,
+            ));
+        }
+
+        Ok(variable_resolver::decode_string(text, |name| {
+            values.get(name).map(|s| s.as_str())
+        })?)
+    }
+
+    /// Replaces the longest variable value the path starts with by the variable, e.g.
+    /// `/home/user/.gitconfig` becomes `{{HOME}}/.gitconfig`.
+    pub fn encode_path(&self, path: impl AsRef<Path>) -> String {
+        let path = path.as_ref();
+
+        let best_match = self
+            .encodable_variables()
+            .filter(|(_, value)| !value.is_empty() && path.starts_with(value))
+            .max_by_key(|(_, value)| value.len());
+
+        match best_match {
+            Some((name, value)) => {
+                let rest = path.strip_prefix(value).unwrap_or(path);
+                if rest.as_os_str().is_empty() {
+                    format!("{{{{{}}}}}", name)
+                } else {
+                    format!("{{{{{}}}}}/{}", name, rest.to_string_lossy())
+                }
+            }
+            None => path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Replaces the variable values in the text with the variables, so the text can be decoded
+    /// with `decode_string` on another machine. Braces of the text are escaped.
+    pub fn encode_text(&self, text: impl AsRef<str>) -> String {
+        let mut variables: Vec<(&String, &String)> = self
+            .encodable_variables()
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        variables.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+        let mut ret = String::new();
+        let mut literal = String::new();
+        let mut rest = text.as_ref();
+        while let Some(c) = rest.chars().next() {
+            let variable = variables.iter().find(|(_, value)| {
+                rest.starts_with(value.as_str())
+                    && !rest[value.len()..]
+                        .starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '-')
+            });
+
+            match variable {
+                Some((name, value)) => {
+                    ret.push_str(&escape_braces(&literal));
+                    literal.clear();
+                    ret.push_str(&format!("{{{{{}}}}}", name));
+                    rest = &rest[value.len()..];
+                }
+                None => {
+                    literal.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+        ret.push_str(&escape_braces(&literal));
+
+        ret
+    }
+
+    fn encodable_variables(&self) -> impl Iterator<Item = (&String, &String)> + '_ {
+        self.variables
+            .iter()
+            .filter(|(name, _)| self.encodable_variable_names.contains(*name))
+    }
+}
+
+fn escape_braces(text: &str) -> String {
+    text.replace("{{", "{{{").replace("}}", "}}}")
 }